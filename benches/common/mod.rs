@@ -0,0 +1,74 @@
+//! Dataset generation and timing shared by the benchmarks.
+
+// Each benchmark compiles its own copy and uses only some of the helpers
+#![allow(dead_code)]
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Rows generated when `BENCH_ROWS` is unset, roughly the full OurAirports file
+const DEFAULT_ROWS: usize = 80_000;
+
+/// Name words combined into airport names, a mix of ASCII and accented text
+const NAME_WORDS: [&str; 12] = [
+    "International",
+    "Regional",
+    "Municipal",
+    "Field",
+    "Heliport",
+    "County",
+    "São",
+    "Zürich",
+    "Kennedy",
+    "Heathrow",
+    "Lake",
+    "Memorial",
+];
+
+/// Row count from `BENCH_ROWS`, or `DEFAULT_ROWS`
+pub fn bench_rows() -> usize {
+    std::env::var("BENCH_ROWS")
+        .ok()
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or(DEFAULT_ROWS)
+}
+
+/// Writes an OurAirports-shaped CSV of `rows` airports, with every column the
+/// loader reads, to the temp directory and returns its path
+pub fn write_csv(name: &str, rows: usize) -> PathBuf {
+    let mut csv = String::from(
+        "ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,\
+         iso_region,municipality,scheduled_service,iata_code,home_link,wikipedia_link,keywords\n",
+    );
+    for i in 0..rows {
+        let first = NAME_WORDS[i % NAME_WORDS.len()];
+        let second = NAME_WORDS[(i / NAME_WORDS.len()) % NAME_WORDS.len()];
+        // Scattered over the globe rather than along a line, for the spatial index
+        let latitude = (i * 7919 % 179_999) as f64 / 1000.0 - 89.999;
+        let longitude = (i * 104_729 % 359_999) as f64 / 1000.0 - 179.999;
+        writeln!(
+            csv,
+            "X{i:05},small_airport,{first} {second} {i} Airport,{latitude},{longitude},{},EU,DE,DE-BY,\
+             Town {i},no,,,https://en.wikipedia.org/wiki/Airport_{i},\"Alpha {i}, Beta, Gamma\"",
+            i % 4000
+        )
+        .unwrap();
+    }
+    let path = std::env::temp_dir().join(format!("icao-api-bench-{}-{name}", std::process::id()));
+    std::fs::write(&path, csv).expect("failed to write benchmark CSV");
+    path
+}
+
+/// Median wall time of `runs` calls to `f`
+pub fn median<T>(runs: usize, mut f: impl FnMut() -> T) -> Duration {
+    let mut times: Vec<Duration> = (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[runs / 2]
+}
@@ -0,0 +1,138 @@
+//! Measures how heavy searches affect the latency of cheap requests served by
+//! the same HTTP worker.
+//!
+//! Starts the server in-process with a single worker over a generated
+//! OurAirports-sized dataset, keeps `HEAVY_CLIENTS` clients cycling through
+//! full-dataset `/airports/search`, `/airports/within`, and `/airports/fuzzy`
+//! scans, and times `PROBES` sequential `/health` requests alongside them. Reports the probe latency percentiles; if a scan blocked
+//! the worker, every probe queued behind it shows up in the tail.
+//!
+//! Run with `cargo bench --bench latency`. Set `BENCH_ROWS` to change the
+//! dataset size.
+
+mod common;
+
+use actix_web::{web, App, HttpServer};
+use icao_api::cache::LruCache;
+use icao_api::{handlers, load_airports, AppState};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Clients issuing heavy searches back to back
+const HEAVY_CLIENTS: usize = 4;
+
+/// Requests each heavy client cycles through, each scanning the whole
+/// dataset: a search matching and counting every generated airport, a box
+/// covering the globe, and a typo-tolerant search scoring every name
+const HEAVY_URIS: &[&str] = &[
+    "/airports/search?q=airport&count=true&limit=1",
+    "/airports/within?min_lat=-90&min_lon=-180&max_lat=90&max_lon=180&limit=1",
+    #[cfg(feature = "fuzzy")]
+    "/airports/fuzzy?q=airprot&limit=1",
+];
+
+/// Timed `/health` requests
+const PROBES: usize = 200;
+
+/// Pause between probes
+const PROBE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sends a `GET` for `uri` on a fresh connection and reads the whole response
+fn get(addr: SocketAddr, uri: &str) {
+    let mut stream = TcpStream::connect(addr).expect("connect failed");
+    write!(
+        stream,
+        "GET {uri} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .expect("request failed");
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).expect("response failed");
+    assert!(response.starts_with(b"HTTP/1.1 200"), "{uri} failed");
+}
+
+/// Latency at `percentile` (0-100) of sorted `times`
+fn percentile(times: &[Duration], percentile: usize) -> Duration {
+    times[(times.len() - 1) * percentile / 100]
+}
+
+fn main() {
+    let rows = common::bench_rows();
+    let path = common::write_csv("latency.csv", rows);
+    let csv_path = path.to_str().expect("temp path is UTF-8").to_string();
+    let airports = load_airports(&csv_path).expect("load failed");
+    let loaded = airports.len();
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind failed");
+    let addr = listener.local_addr().expect("no local address");
+    std::thread::spawn(move || {
+        let mut state = AppState::new(airports, csv_path);
+        // Every heavy request repeats the same search, which must not hit the cache
+        state.search_cache = LruCache::new(0);
+        let state = web::Data::new(state);
+        actix_web::rt::System::new().block_on(async move {
+            HttpServer::new(move || {
+                App::new()
+                    .app_data(state.clone())
+                    .configure(handlers::configure)
+            })
+            .workers(1)
+            .disable_signals()
+            .listen(listener)?
+            .run()
+            .await
+        })
+    });
+    get(addr, "/health");
+    std::fs::remove_file(&path).ok();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let searches = Arc::new(AtomicUsize::new(0));
+    let heavy: Vec<_> = (0..HEAVY_CLIENTS)
+        .map(|_| {
+            let (done, searches) = (done.clone(), searches.clone());
+            std::thread::spawn(move || {
+                for uri in HEAVY_URIS.iter().cycle() {
+                    if done.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    get(addr, uri);
+                    searches.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut times: Vec<Duration> = (0..PROBES)
+        .map(|_| {
+            std::thread::sleep(PROBE_INTERVAL);
+            let sent = Instant::now();
+            get(addr, "/health");
+            sent.elapsed()
+        })
+        .collect();
+    let elapsed = start.elapsed();
+    done.store(true, Ordering::Relaxed);
+    for client in heavy {
+        client.join().expect("heavy client panicked");
+    }
+    times.sort();
+
+    println!(
+        "/health under {HEAVY_CLIENTS} searching clients over {loaded} airports, {PROBES} probes:"
+    );
+    println!(
+        "  p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        percentile(&times, 50),
+        percentile(&times, 90),
+        percentile(&times, 99),
+        times[times.len() - 1]
+    );
+    println!(
+        "  {} searches in {elapsed:?}",
+        searches.load(Ordering::Relaxed)
+    );
+}
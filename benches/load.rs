@@ -0,0 +1,45 @@
+//! Times `load_airports` on a generated OurAirports-shaped CSV, once on a
+//! single Rayon thread and once on the full pool, to show the parallel speedup.
+//!
+//! Run with `cargo bench --bench load`. Set `BENCH_ROWS` to change the dataset
+//! size (default 80,000 rows, roughly the full OurAirports file).
+
+mod common;
+
+use icao_api::load_airports;
+use std::time::Duration;
+
+/// Timed loads per configuration; the median is reported
+const RUNS: usize = 7;
+
+/// Median wall time of `RUNS` loads of `path` on a pool of `threads` threads
+fn median_load(path: &str, threads: usize) -> Duration {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build thread pool");
+    common::median(RUNS, || {
+        pool.install(|| load_airports(path)).expect("load failed")
+    })
+}
+
+fn main() {
+    let rows = common::bench_rows();
+    let path = common::write_csv("load.csv", rows);
+    let path_str = path.to_str().expect("temp path is UTF-8");
+
+    // Warm the page cache so the first configuration is not charged for disk reads
+    load_airports(path_str).expect("load failed");
+    let threads = rayon::current_num_threads();
+    let serial = median_load(path_str, 1);
+    let parallel = median_load(path_str, threads);
+    std::fs::remove_file(&path).ok();
+
+    println!("load_airports, {rows} rows, median of {RUNS}:");
+    println!("  1 thread:   {serial:?}");
+    println!("  {threads} threads: {parallel:?}");
+    println!(
+        "  speedup:    {:.2}x",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
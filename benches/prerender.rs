@@ -0,0 +1,49 @@
+//! Compares rendering unfiltered `/airports` JSON pages from the airport JSON
+//! serialized at load, as `PrerenderedAirports::page_json` does, with running
+//! serde over the same page, and times building the serialized copy.
+//!
+//! Run with `cargo bench --bench prerender`. Set `BENCH_ROWS` to change the
+//! dataset size.
+
+mod common;
+
+use icao_api::format::PrerenderedAirports;
+use icao_api::{load_airports, paginate, PageLimits};
+
+/// Timed renders per page; the median is reported
+const RUNS: usize = 51;
+
+/// Timed builds of the serialized copy; the median is reported
+const BUILD_RUNS: usize = 7;
+
+/// Page sizes rendered, from the default page to the largest allowed
+const LIMITS: [usize; 2] = [20, 1000];
+
+fn main() {
+    let rows = common::bench_rows();
+    let path = common::write_csv("prerender.csv", rows);
+    let airports = load_airports(path.to_str().expect("temp path is UTF-8")).expect("load failed");
+    std::fs::remove_file(&path).ok();
+
+    let build = common::median(BUILD_RUNS, || PrerenderedAirports::new(&airports));
+    let json = PrerenderedAirports::new(&airports);
+    let limits = PageLimits::new(20, 1000);
+
+    println!("unfiltered JSON pages over {rows} airports, median of {RUNS}:");
+    println!("  building the serialized copy: {build:?} (median of {BUILD_RUNS})");
+    for limit in LIMITS {
+        // A page from the middle, so the copied slice is not at either end
+        let page = paginate(&airports, Some(rows / 2), Some(limit), limits);
+        assert_eq!(
+            json.page_json(&page),
+            serde_json::to_vec(&page).expect("page serializes"),
+            "prerendered page differs from serde output"
+        );
+        let serde = common::median(RUNS, || serde_json::to_vec(&page));
+        let prerendered = common::median(RUNS, || json.page_json(&page));
+        println!(
+            "  limit={limit:<5} serde {serde:?}, page_json {prerendered:?} ({:.1}x)",
+            serde.as_secs_f64() / prerendered.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Criterion benchmarks for search filtering over a generated
+//! OurAirports-sized dataset without the HTTP layer: `SearchQuery::run` (full
+//! match set) and `SearchQuery::run_head` (the bounded first page
+//! `/airports/search` uses) for short and long queries, and `fuzzy_search` as
+//! a full scan against `fuzzy_search_indexed` with a `TrigramIndex`.
+//!
+//! Run with `cargo bench --bench search`. Criterion keeps the last run under
+//! `target/criterion` and reports changes against it; save a named baseline
+//! with `-- --save-baseline main` and compare with `-- --baseline main`. Set
+//! `BENCH_ROWS` to change the dataset size.
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use icao_api::trigram::TrigramIndex;
+use icao_api::{fuzzy_search, fuzzy_search_indexed, load_airports, Airport, SearchQuery};
+use std::hint::black_box;
+use std::sync::LazyLock;
+
+/// Page window for `run_head`, the default page size
+const WINDOW: usize = 20;
+
+/// Samples per case, fewer than Criterion's default since full scans take
+/// milliseconds
+const SAMPLES: usize = 20;
+
+/// Short queries: a code-like prefix and a common word, matching many airports
+const SHORT_QUERIES: [&str; 2] = ["x1", "field"];
+
+/// Long queries: accented text, multi-word text, and text matching nothing
+const LONG_QUERIES: [&str; 3] = [
+    "zurich memorial airport",
+    "kennedy memorial",
+    "nowhere at all",
+];
+
+/// Misspelled queries for the fuzzy path, with their edit distance thresholds
+const FUZZY_QUERIES: [(&str, usize); 4] = [
+    ("heathrw", 2),
+    ("internatonal", 2),
+    ("zurih memorial", 2),
+    ("kenedy", 1),
+];
+
+/// Generated dataset shared by every benchmark, loaded once
+static AIRPORTS: LazyLock<Vec<Airport>> = LazyLock::new(|| {
+    let path = common::write_csv("search.csv", common::bench_rows());
+    let airports = load_airports(path.to_str().expect("temp path is UTF-8")).expect("load failed");
+    std::fs::remove_file(&path).ok();
+    airports
+});
+
+/// Benchmarks `run` and `run_head` for each of `queries` in a group `name`
+fn bench_queries(c: &mut Criterion, name: &str, queries: &[&str]) {
+    let airports = &*AIRPORTS;
+    let mut group = c.benchmark_group(name);
+    group.sample_size(SAMPLES);
+    for &text in queries {
+        let query = SearchQuery::new(text);
+        group.bench_with_input(BenchmarkId::new("run", text), &query, |b, query| {
+            b.iter(|| query.run(black_box(airports)))
+        });
+        group.bench_with_input(BenchmarkId::new("run_head", text), &query, |b, query| {
+            b.iter(|| query.run_head(black_box(airports), WINDOW))
+        });
+    }
+    group.finish();
+}
+
+/// Short queries, matching large parts of the dataset
+fn short_query(c: &mut Criterion) {
+    bench_queries(c, "short_query", &SHORT_QUERIES);
+}
+
+/// Long multi-word queries, matching few airports or none
+fn long_query(c: &mut Criterion) {
+    bench_queries(c, "long_query", &LONG_QUERIES);
+}
+
+/// Building the trigram index, and fuzzy queries scanned and through it
+fn fuzzy(c: &mut Criterion) {
+    let airports = &*AIRPORTS;
+    let mut group = c.benchmark_group("fuzzy");
+    group.sample_size(SAMPLES);
+    group.bench_function("trigram_index", |b| {
+        b.iter(|| TrigramIndex::new(black_box(airports)))
+    });
+
+    let index = TrigramIndex::new(airports);
+    for (text, max_distance) in FUZZY_QUERIES {
+        assert_eq!(
+            fuzzy_search_indexed(airports, &index, text, max_distance).len(),
+            fuzzy_search(airports, text, max_distance).len(),
+            "indexed results differ for {text:?}"
+        );
+        group.bench_function(BenchmarkId::new("scan", text), |b| {
+            b.iter(|| fuzzy_search(black_box(airports), text, max_distance))
+        });
+        group.bench_function(BenchmarkId::new("indexed", text), |b| {
+            b.iter(|| fuzzy_search_indexed(black_box(airports), &index, text, max_distance))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, short_query, long_query, fuzzy);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Times nearest and radius queries over a generated OurAirports-sized dataset
+//! without the HTTP layer: a full haversine scan and sort, as the handlers ran
+//! before the spatial index, against `SpatialIndex::nearest` and
+//! `SpatialIndex::within`.
+//!
+//! Run with `cargo bench --bench spatial`; compare the medians before and after
+//! a change to catch regressions. Set `BENCH_ROWS` to change the dataset size.
+
+mod common;
+
+use icao_api::geo::haversine_km;
+use icao_api::spatial::SpatialIndex;
+use icao_api::{load_airports, Airport};
+use rayon::prelude::*;
+use std::time::Instant;
+
+/// Timed runs per case; the median is reported
+const RUNS: usize = 25;
+
+/// Results per nearest query, the default page size
+const NEAREST: usize = 20;
+
+/// Query points: mid-latitude, equator, near a pole, and on the antimeridian
+const ORIGINS: [(f64, f64); 4] = [(51.5, -0.12), (0.0, 0.0), (89.0, 10.0), (10.0, 180.0)];
+
+/// Radii for the radius queries, in kilometers
+const RADII: [f64; 3] = [50.0, 500.0, 5000.0];
+
+/// Indexes of every airport with coordinates by ascending distance from `origin`
+fn scan(airports: &[Airport], origin: (f64, f64)) -> Vec<(usize, f64)> {
+    let mut ranked: Vec<(usize, f64)> = airports
+        .par_iter()
+        .enumerate()
+        .filter_map(|(index, airport)| {
+            let point = (airport.latitude?, airport.longitude?);
+            Some((index, haversine_km(origin, point)))
+        })
+        .collect();
+    ranked.par_sort_by(|a, b| a.1.total_cmp(&b.1));
+    ranked
+}
+
+fn main() {
+    let rows = common::bench_rows();
+    let path = common::write_csv("spatial.csv", rows);
+    let airports = load_airports(path.to_str().expect("temp path is UTF-8")).expect("load failed");
+    std::fs::remove_file(&path).ok();
+
+    let start = Instant::now();
+    let index = SpatialIndex::new(&airports);
+    println!(
+        "spatial queries over {} airports, median of {RUNS} (index built in {:?}):",
+        airports.len(),
+        start.elapsed()
+    );
+    for origin in ORIGINS {
+        let scanned: Vec<usize> = scan(&airports, origin)
+            .into_iter()
+            .take(NEAREST)
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(index.nearest(origin, NEAREST), scanned, "{origin:?}");
+        let full = common::median(RUNS, || scan(&airports, origin));
+        let indexed = common::median(RUNS, || index.nearest(origin, NEAREST));
+        println!("  nearest {NEAREST} to {origin:?}: scan {full:?}, indexed {indexed:?}");
+
+        for radius_km in RADII {
+            let total = index.within(origin, radius_km).len();
+            let full = common::median(RUNS, || {
+                let mut ranked = scan(&airports, origin);
+                ranked.retain(|&(_, distance)| distance <= radius_km);
+                ranked
+            });
+            let indexed = common::median(RUNS, || index.within(origin, radius_km));
+            println!(
+                "  within {radius_km} km of {origin:?} ({total} airports): scan {full:?}, indexed {indexed:?}"
+            );
+        }
+    }
+}
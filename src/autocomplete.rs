@@ -0,0 +1,122 @@
+//! Sorted prefix index over name words and ICAO codes for typeahead lookups.
+
+use crate::model::Airport;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Single indexed key pointing back at an airport
+#[derive(Debug)]
+struct PrefixEntry {
+    /// Lowercase name word or ICAO code
+    key: String,
+    /// 0 for the ICAO code, `n + 1` for the `n`-th word of the name
+    position: usize,
+    /// Index into the dataset's `airports`
+    airport: usize,
+}
+
+/// Prefix index built once per dataset load.
+///
+/// Keys are kept in one sorted vector, so all keys sharing a prefix form a
+/// contiguous run found by binary search. Lookups touch only that run instead
+/// of scanning every airport.
+///
+/// # Memory
+/// One owned key plus two `usize`s per ICAO code and per name word, typically
+/// four or five entries per airport.
+#[derive(Debug, Default)]
+pub struct PrefixIndex {
+    entries: Vec<PrefixEntry>,
+}
+
+impl PrefixIndex {
+    /// Indexes each airport's lowercase ICAO code and the alphanumeric words of
+    /// its lowercase name
+    pub fn new(airports: &[Airport]) -> Self {
+        let mut entries: Vec<PrefixEntry> = airports
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(index, airport)| {
+                let words = airport
+                    .lower_name
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty());
+                std::iter::once(airport.lower_icao.as_str())
+                    .chain(words)
+                    .enumerate()
+                    .map(move |(position, key)| PrefixEntry {
+                        key: key.to_string(),
+                        position,
+                        airport: index,
+                    })
+            })
+            .collect();
+        entries.par_sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        PrefixIndex { entries }
+    }
+
+    /// Finds airports with an indexed key starting with a lowercase `prefix`.
+    ///
+    /// # Returns
+    /// Up to `limit` airport indexes, ranked by the earliest position at which
+    /// the prefix matched (ICAO code first, then name words in order), with ties
+    /// in dataset order
+    pub fn lookup(&self, prefix: &str, limit: usize) -> Vec<usize> {
+        let start = self
+            .entries
+            .partition_point(|entry| entry.key.as_str() < prefix);
+
+        let mut best: HashMap<usize, usize> = HashMap::new();
+        for entry in self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key.starts_with(prefix))
+        {
+            best.entry(entry.airport)
+                .and_modify(|position| *position = (*position).min(entry.position))
+                .or_insert(entry.position);
+        }
+
+        let mut ranked: Vec<(usize, usize)> = best
+            .into_iter()
+            .map(|(airport, position)| (position, airport))
+            .collect();
+        if ranked.len() > limit {
+            ranked.select_nth_unstable(limit);
+            ranked.truncate(limit);
+        }
+        ranked.sort_unstable();
+        ranked.into_iter().map(|(_, airport)| airport).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::create_test_state;
+
+    /// Tests prefix ranking across ICAO codes and name words, and the limit
+    #[actix_web::test]
+    async fn test_prefix_index_lookup() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let index = PrefixIndex::new(&airports);
+        let icaos = |prefix: &str, limit: usize| -> Vec<&str> {
+            index
+                .lookup(prefix, limit)
+                .into_iter()
+                .map(|i| airports[i].icao.as_str())
+                .collect()
+        };
+
+        assert_eq!(icaos("kl", 10), ["KLAX"]);
+        // ICAO codes rank ahead of JFK's later "kennedy" word match
+        assert_eq!(icaos("k", 10), ["KJFK", "KLAX"]);
+        assert_eq!(icaos("ken", 10), ["KJFK"]);
+        // Both match on their first word; ties keep dataset order
+        assert_eq!(icaos("lo", 10), ["KLAX", "EGLL"]);
+        // "international" is LAX's third name word but JFK's fourth
+        assert_eq!(icaos("international", 10), ["KLAX", "KJFK"]);
+        assert_eq!(icaos("international", 1), ["KLAX"]);
+        assert!(icaos("xyz", 10).is_empty());
+        assert!(icaos("int", 0).is_empty());
+    }
+}
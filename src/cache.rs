@@ -0,0 +1,184 @@
+//! Bounded least-recently-used cache for rendered responses.
+
+use crate::format::FieldSelection;
+use crate::search::SearchQuery;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
+
+/// Normalized search parameters identifying a cacheable JSON search response
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchCacheKey {
+    /// Parsed query, including match mode, ordering, and attribute filters
+    pub query: SearchQuery,
+    /// Requested offset, 0 when absent
+    pub offset: usize,
+    /// Page size after defaulting and clamping to the maximum page size
+    pub limit: usize,
+    /// Whether relevance scores are included
+    pub debug: bool,
+    /// Whether match highlights are included
+    pub highlight: bool,
+    /// Whether a search without matches is answered 404, so such searches
+    /// are only ever cached when not strict
+    pub strict: bool,
+    /// Airport fields kept in the output; `None` keeps all
+    pub select: Option<FieldSelection>,
+}
+
+/// Mutable cache contents guarded by the `LruCache` mutex
+struct LruState<K, V> {
+    /// Cached values with the tick of their last use
+    entries: HashMap<K, (V, u64)>,
+    /// Keys ordered by last use, oldest first
+    recency: BTreeMap<u64, K>,
+    /// Monotonic use counter
+    tick: u64,
+    /// Bumped by `clear`; inserts computed under an older generation are dropped
+    generation: u64,
+}
+
+/// Thread-safe LRU cache holding at most `capacity` entries.
+///
+/// Recency is tracked with a use counter, so lookups and inserts cost
+/// `O(log n)`. A capacity of 0 disables caching entirely.
+///
+/// # Invalidation
+/// `clear` empties the cache and starts a new generation. Callers read
+/// `generation` before computing a value and pass it to `insert`, so a value
+/// computed from data that was replaced in the meantime is never stored.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    state: Mutex<LruState<K, V>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    /// Creates an empty cache holding at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            state: Mutex::new(LruState {
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+                generation: 0,
+            }),
+        }
+    }
+
+    /// Returns true if the cache never stores anything
+    pub fn is_disabled(&self) -> bool {
+        self.capacity == 0
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns true if nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Current generation, to be passed to `insert`
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Returns a clone of the cached value for `key`, marking it most recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut state = self.lock();
+        let state = &mut *state;
+        state.tick += 1;
+        let (value, used) = state.entries.get_mut(key)?;
+        state.recency.remove(used);
+        *used = state.tick;
+        state.recency.insert(state.tick, key.clone());
+        Some(value.clone())
+    }
+
+    /// Stores `value` for `key`, evicting the least recently used entries
+    /// beyond capacity. Does nothing if `generation` is no longer current.
+    pub fn insert(&self, generation: u64, key: K, value: V) {
+        if self.is_disabled() {
+            return;
+        }
+        let mut state = self.lock();
+        let state = &mut *state;
+        if generation != state.generation {
+            return;
+        }
+
+        state.tick += 1;
+        if let Some((_, used)) = state.entries.insert(key.clone(), (value, state.tick)) {
+            state.recency.remove(&used);
+        }
+        state.recency.insert(state.tick, key);
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+
+    /// Removes every entry and starts a new generation
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.recency.clear();
+        state.generation += 1;
+    }
+
+    /// Locks the state, recovering it if another thread panicked while holding
+    /// the lock; every mutation leaves the state consistent
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruState<K, V>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the least recently used entry is evicted first
+    #[actix_web::test]
+    async fn test_lru_eviction() {
+        let cache = LruCache::new(2);
+        let generation = cache.generation();
+        cache.insert(generation, "a", 1);
+        cache.insert(generation, "b", 2);
+        // Touching "a" makes "b" the eviction candidate
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.insert(generation, "c", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        // Replacing a value keeps a single entry
+        cache.insert(generation, "c", 4);
+        assert_eq!((cache.len(), cache.get(&"c")), (2, Some(4)));
+    }
+
+    /// Tests that clearing drops entries and rejects inserts from the old generation
+    #[actix_web::test]
+    async fn test_lru_clear_generation() {
+        let cache = LruCache::new(4);
+        let stale = cache.generation();
+        cache.insert(stale, "a", 1);
+        cache.clear();
+        assert!(cache.is_empty());
+
+        cache.insert(stale, "b", 2);
+        assert_eq!(cache.get(&"b"), None);
+        cache.insert(cache.generation(), "b", 2);
+        assert_eq!(cache.get(&"b"), Some(2));
+
+        let disabled = LruCache::new(0);
+        disabled.insert(disabled.generation(), "a", 1);
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+//! Server configuration from CLI arguments and environment variables.
+
+use crate::loader::{ColumnMapping, Delimiter};
+use crate::logging::LogFormat;
+use crate::pagination::{DEFAULT_MAX_PAGE_LIMIT, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT_CEILING};
+use std::str::FromStr;
+
+/// CSV path used when neither a CLI argument nor `AIRPORTS_CSV` is given.
+pub const DEFAULT_CSV_PATH: &str = "airports.csv";
+
+/// Bind address used when `BIND_ADDR` is not set.
+pub const DEFAULT_BIND_ADDR: &str = "0.0.0.0";
+
+/// Port used when `PORT` is not set.
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Shortest search query, in characters after trimming, accepted when
+/// `MIN_QUERY_LENGTH` is not set.
+pub const DEFAULT_MIN_QUERY_LENGTH: usize = 2;
+
+/// Number of rendered search responses cached when `SEARCH_CACHE_SIZE` is not set.
+pub const DEFAULT_SEARCH_CACHE_CAPACITY: usize = 1024;
+
+/// Requests a client may make in a burst when `RATE_LIMIT_BURST` is not set.
+pub const DEFAULT_RATE_LIMIT_BURST: u32 = 20;
+
+/// Seconds in-flight requests may take to finish after a shutdown signal when
+/// `SHUTDOWN_TIMEOUT` is not set.
+pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// Origins allowed to make cross-origin requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsOrigins {
+    /// Any origin (`*`)
+    Any,
+    /// Only the listed origins, e.g. `https://example.com`; empty allows none
+    List(Vec<String>),
+}
+
+impl CorsOrigins {
+    /// Parses a `CORS_ALLOWED_ORIGINS` value: `*` for any origin, or a
+    /// comma-separated allowlist. Falls back to `default` when absent or blank.
+    pub fn parse(value: Option<&str>, default: CorsOrigins) -> CorsOrigins {
+        match value.map(str::trim) {
+            None | Some("") => default,
+            Some("*") => CorsOrigins::Any,
+            Some(list) => CorsOrigins::List(
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|origin| !origin.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Default when `CORS_ALLOWED_ORIGINS` is not set: any origin in debug
+    /// builds for local development, none in release builds
+    pub fn default_for_build() -> CorsOrigins {
+        if cfg!(debug_assertions) {
+            CorsOrigins::Any
+        } else {
+            CorsOrigins::List(Vec::new())
+        }
+    }
+}
+
+/// Parses a configuration value, falling back to `default` when it is absent.
+///
+/// # Returns
+/// - The parsed value, or `default` if `value` is `None` or blank
+/// - An `InvalidInput` I/O error naming the setting if parsing fails, so that
+///   startup aborts with a clear message
+pub fn parse_setting<T: FromStr>(
+    name: &str,
+    value: Option<String>,
+    default: T,
+) -> std::io::Result<T> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(default),
+        Some(raw) => raw.parse().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid {name} value '{raw}'"),
+            )
+        }),
+    }
+}
+
+/// Reads and parses an environment variable via `parse_setting`
+pub fn env_setting<T: FromStr>(name: &str, default: T) -> std::io::Result<T> {
+    parse_setting(name, std::env::var(name).ok(), default)
+}
+
+/// Reads the CSV column mapping from the `COL_*` variables, keeping the
+/// OurAirports name for any that are unset or blank
+fn column_mapping() -> std::io::Result<ColumnMapping> {
+    let defaults = ColumnMapping::default();
+    Ok(ColumnMapping {
+        icao: env_setting("COL_ICAO", defaults.icao)?,
+        name: env_setting("COL_NAME", defaults.name)?,
+        latitude: env_setting("COL_LATITUDE", defaults.latitude)?,
+        longitude: env_setting("COL_LONGITUDE", defaults.longitude)?,
+        iata: env_setting("COL_IATA", defaults.iata)?,
+    })
+}
+
+/// Server configuration gathered from CLI arguments and environment variables
+#[derive(Debug)]
+pub struct Config {
+    /// CSV path: first CLI argument, else `AIRPORTS_CSV`, else `airports.csv`
+    pub csv_path: String,
+    /// Bearer token for admin endpoints from `ADMIN_TOKEN`, if set
+    pub admin_token: Option<String>,
+    /// Address to bind from `BIND_ADDR` (default: `0.0.0.0`)
+    pub bind_addr: String,
+    /// Port to bind from `PORT` (default: 8080)
+    pub port: u16,
+    /// Shortest accepted search query from `MIN_QUERY_LENGTH` (default: 2)
+    pub min_query_length: usize,
+    /// Largest page size from `MAX_PAGE_LIMIT` (default: 50), clamped to
+    /// 1-`MAX_PAGE_LIMIT_CEILING`
+    pub max_page_limit: usize,
+    /// Page size without a `limit` from `DEFAULT_PAGE_LIMIT` (default: 20), at
+    /// least 1 and lowered to `max_page_limit` if larger
+    pub default_page_limit: usize,
+    /// Skip rows with malformed ICAO codes when loading, from `STRICT_ICAO` (default: false)
+    pub strict_icao: bool,
+    /// Start with no airports if the CSV cannot be loaded, from `ALLOW_EMPTY` (default: false)
+    pub allow_empty: bool,
+    /// Search responses to cache from `SEARCH_CACHE_SIZE` (default: 1024; 0 disables)
+    pub search_cache_capacity: usize,
+    /// Cross-origin allowlist from `CORS_ALLOWED_ORIGINS` (default: see `CorsOrigins::default_for_build`)
+    pub cors_origins: CorsOrigins,
+    /// Sustained requests per second per client IP from `RATE_LIMIT_RPS` (default: 0, disabled)
+    pub rate_limit_rps: f64,
+    /// Requests per client IP allowed in a burst from `RATE_LIMIT_BURST` (default: 20)
+    pub rate_limit_burst: u32,
+    /// Log line format from `LOG_FORMAT`: `text` (default) or `json`
+    pub log_format: LogFormat,
+    /// CSV header names from `COL_ICAO`, `COL_NAME`, `COL_LATITUDE`,
+    /// `COL_LONGITUDE`, and `COL_IATA` (default: the OurAirports names)
+    pub columns: ColumnMapping,
+    /// CSV field separator from `CSV_DELIMITER`: a character, `tab`, or `auto`
+    /// (default: `,`)
+    pub csv_delimiter: Delimiter,
+    /// Seconds to drain in-flight requests on shutdown from `SHUTDOWN_TIMEOUT` (default: 30)
+    pub shutdown_timeout: u64,
+    /// HTTP worker threads from `WORKERS` (default: 0, one per available CPU)
+    pub workers: usize,
+    /// Search thread pool size from `SEARCH_THREADS` (default: 0, sized like
+    /// Rayon's global pool)
+    pub search_threads: usize,
+}
+
+impl Config {
+    /// Reads configuration from the process arguments and environment.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` I/O error if a value fails to parse (e.g., a
+    /// `PORT` that is not a valid `u16`)
+    pub fn from_env() -> std::io::Result<Self> {
+        Ok(Config {
+            csv_path: std::env::args()
+                .nth(1)
+                .or_else(|| std::env::var("AIRPORTS_CSV").ok())
+                .unwrap_or_else(|| DEFAULT_CSV_PATH.to_string()),
+            admin_token: std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            bind_addr: env_setting("BIND_ADDR", DEFAULT_BIND_ADDR.to_string())?,
+            port: env_setting("PORT", DEFAULT_PORT)?,
+            min_query_length: env_setting("MIN_QUERY_LENGTH", DEFAULT_MIN_QUERY_LENGTH)?,
+            max_page_limit: env_setting("MAX_PAGE_LIMIT", DEFAULT_MAX_PAGE_LIMIT)?
+                .clamp(1, MAX_PAGE_LIMIT_CEILING),
+            default_page_limit: env_setting("DEFAULT_PAGE_LIMIT", DEFAULT_PAGE_LIMIT)?.max(1),
+            strict_icao: env_setting("STRICT_ICAO", false)?,
+            allow_empty: env_setting("ALLOW_EMPTY", false)?,
+            search_cache_capacity: env_setting("SEARCH_CACHE_SIZE", DEFAULT_SEARCH_CACHE_CAPACITY)?,
+            cors_origins: CorsOrigins::parse(
+                std::env::var("CORS_ALLOWED_ORIGINS").ok().as_deref(),
+                CorsOrigins::default_for_build(),
+            ),
+            rate_limit_rps: env_setting("RATE_LIMIT_RPS", 0.0)?,
+            rate_limit_burst: env_setting("RATE_LIMIT_BURST", DEFAULT_RATE_LIMIT_BURST)?,
+            log_format: env_setting("LOG_FORMAT", LogFormat::default())?,
+            columns: column_mapping()?,
+            csv_delimiter: env_setting("CSV_DELIMITER", Delimiter::default())?,
+            shutdown_timeout: env_setting("SHUTDOWN_TIMEOUT", DEFAULT_SHUTDOWN_TIMEOUT_SECS)?,
+            workers: env_setting("WORKERS", 0)?,
+            search_threads: env_setting("SEARCH_THREADS", 0)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests setting parsing defaults and the error for an invalid port
+    #[actix_web::test]
+    async fn test_parse_setting() {
+        assert_eq!(parse_setting("PORT", None, 8080u16).unwrap(), 8080);
+        assert_eq!(
+            parse_setting("PORT", Some(" ".into()), 8080u16).unwrap(),
+            8080
+        );
+        assert_eq!(
+            parse_setting("PORT", Some("9000".into()), 8080u16).unwrap(),
+            9000
+        );
+
+        let err = parse_setting("PORT", Some("70000".into()), 8080u16).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("PORT"));
+    }
+
+    /// Tests CORS allowlist parsing, including the wildcard and the default
+    #[actix_web::test]
+    async fn test_cors_origins_parse() {
+        let none = CorsOrigins::List(Vec::new());
+        assert_eq!(CorsOrigins::parse(None, none.clone()), none);
+        assert_eq!(
+            CorsOrigins::parse(Some(" "), CorsOrigins::Any),
+            CorsOrigins::Any
+        );
+        assert_eq!(
+            CorsOrigins::parse(Some(" * "), none.clone()),
+            CorsOrigins::Any
+        );
+        assert_eq!(
+            CorsOrigins::parse(Some("https://a.example, https://b.example,"), none),
+            CorsOrigins::List(vec!["https://a.example".into(), "https://b.example".into()])
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Error type shared by the library and its HTTP handlers.
+
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+/// JSON body of every error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Human-readable description of what went wrong
+    pub error: String,
+    /// `X-Request-Id` of the failed request, when the `request_id` middleware is installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Unified error type for API operations, implementing Actix's `ResponseError`.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    /// Occurs when CSV parsing fails (malformed data or I/O issues)
+    #[error("CSV parsing error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    /// Occurs when a CSV file lacks required columns; names the file and the
+    /// missing columns
+    #[error("CSV schema error: {0}")]
+    SchemaError(String),
+
+    /// Occurs during file operations (e.g., missing airports.csv)
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Occurs when a requested resource (e.g., an ICAO code) does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Occurs when the client supplies invalid request parameters
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    /// Occurs when an admin endpoint is called without a valid bearer token
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    /// Occurs when a client exceeds the rate limit; carries the seconds until
+    /// it may retry
+    #[error("Too many requests, retry after {0} second(s)")]
+    TooManyRequests(u64),
+
+    /// Occurs when an endpoint was compiled out of this build; names the
+    /// endpoint and the Cargo feature providing it
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    /// General catch-all for unexpected errors
+    #[error("Internal server error")]
+    InternalError,
+}
+
+/// Implementation of Actix's error response conversion
+impl ResponseError for ApiError {
+    /// Maps each error variant to its HTTP status code:
+    /// - `NotFound` → 404
+    /// - `BadRequest` → 400
+    /// - `Unauthorized` → 401
+    /// - `TooManyRequests` → 429
+    /// - `NotImplemented` → 501
+    /// - `CsvError`, `SchemaError`, `IoError`, `InternalError` → 500
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            ApiError::CsvError(_)
+            | ApiError::SchemaError(_)
+            | ApiError::IoError(_)
+            | ApiError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Converts API errors into HTTP responses with appropriate status codes
+    /// and JSON-formatted error messages. `TooManyRequests` also sets `Retry-After`.
+    fn error_response(&self) -> HttpResponse {
+        self.response_for_request(None)
+    }
+}
+
+impl ApiError {
+    /// Builds the same response as `error_response`, echoing `request_id` in
+    /// the JSON body when given
+    pub fn response_for_request(&self, request_id: Option<&str>) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::TooManyRequests(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        response.json(ErrorBody {
+            error: self.to_string(),
+            request_id: request_id.map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that each error variant maps to the expected HTTP status code
+    #[actix_web::test]
+    async fn test_api_error_status_codes() {
+        assert_eq!(
+            ApiError::NotFound("x".into()).status_code(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            ApiError::BadRequest("x".into()).status_code(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            ApiError::NotImplemented("x".into()).status_code(),
+            StatusCode::NOT_IMPLEMENTED
+        );
+        assert_eq!(
+            ApiError::InternalError.status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            ApiError::IoError(std::io::Error::other("x")).status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            ApiError::BadRequest("x".into()).error_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        let limited = ApiError::TooManyRequests(3).error_response();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers().get(header::RETRY_AFTER).unwrap(), "3");
+    }
+}
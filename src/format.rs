@@ -0,0 +1,558 @@
+//! Response formats for airport pages: JSON, CSV, and GeoJSON.
+
+use crate::error::ApiError;
+use crate::model::Airport;
+use crate::pagination::PaginatedResponse;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Response encodings supported by airport list endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// `application/json` paginated envelope (default)
+    Json,
+    /// `text/csv` rows with a header line
+    Csv,
+    /// `application/geo+json` FeatureCollection of airport points
+    GeoJson,
+}
+
+impl FromStr for ResponseFormat {
+    type Err = ApiError;
+
+    /// Parses a `format` query value, rejecting unknown formats with `ApiError::BadRequest`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(ResponseFormat::Json),
+            "csv" => Ok(ResponseFormat::Csv),
+            "geojson" => Ok(ResponseFormat::GeoJson),
+            other => Err(ApiError::BadRequest(format!(
+                "invalid format '{other}', expected one of: json, csv, geojson"
+            ))),
+        }
+    }
+}
+
+impl ResponseFormat {
+    /// Resolves the response format: an explicit `format` query value wins,
+    /// otherwise the `Accept` header is negotiated.
+    pub fn resolve(req: &HttpRequest, format: Option<&str>) -> Result<Self, ApiError> {
+        match format {
+            Some(format) => format.parse(),
+            None => Ok(Self::negotiate(req)),
+        }
+    }
+
+    /// Picks the format from the request's `Accept` header.
+    ///
+    /// Media types are considered in quality order; the first of
+    /// `application/json` or `text/csv` wins. A missing header, wildcards, or
+    /// anything unrecognized yields JSON.
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let Ok(accept) = <header::Accept as header::Header>::parse(req) else {
+            return ResponseFormat::Json;
+        };
+        accept
+            .ranked()
+            .into_iter()
+            .find_map(
+                |mime| match (mime.type_().as_str(), mime.subtype().as_str()) {
+                    ("text", "csv") => Some(ResponseFormat::Csv),
+                    ("application", "json") => Some(ResponseFormat::Json),
+                    _ => None,
+                },
+            )
+            .unwrap_or(ResponseFormat::Json)
+    }
+}
+
+/// CSV row for an airport: its JSON fields in the same order, with `keywords`
+/// joined by `, ` as in the source CSV, since a cell cannot hold a list
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    icao: &'a str,
+    name: &'a str,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    geohash: Option<&'a str>,
+    timezone: Option<&'a str>,
+    elevation_ft: Option<i32>,
+    iata: Option<&'a str>,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    country: &'a str,
+    region: &'a str,
+    continent: &'a str,
+    city: Option<&'a str>,
+    scheduled_service: bool,
+    wikipedia: Option<&'a str>,
+    website: Option<&'a str>,
+    keywords: String,
+}
+
+impl<'a> From<&'a Airport> for CsvRow<'a> {
+    fn from(airport: &'a Airport) -> Self {
+        CsvRow {
+            icao: &airport.icao,
+            name: &airport.name,
+            latitude: airport.latitude,
+            longitude: airport.longitude,
+            geohash: airport.geohash.as_deref(),
+            timezone: airport.timezone.as_deref(),
+            elevation_ft: airport.elevation_ft,
+            iata: airport.iata.as_deref(),
+            kind: &airport.kind,
+            country: &airport.country,
+            region: &airport.region,
+            continent: &airport.continent,
+            city: airport.city.as_deref(),
+            scheduled_service: airport.scheduled_service,
+            wikipedia: airport.wikipedia.as_deref(),
+            website: airport.website.as_deref(),
+            keywords: airport.keywords.join(", "),
+        }
+    }
+}
+
+/// Serializes airports as CSV using the `csv` crate, always emitting a header row.
+///
+/// The header is derived from `CsvRow`'s field names, so an empty page still
+/// produces a header line.
+pub fn airports_to_csv<A: Borrow<Airport>>(airports: &[A]) -> Result<Vec<u8>, ApiError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for airport in airports {
+        writer.serialize(CsvRow::from(airport.borrow()))?;
+    }
+    if airports.is_empty() {
+        // Serialize a placeholder so the header gets written; its row is cut below
+        writer.serialize(CsvRow::from(&Airport::default()))?;
+    }
+
+    let mut body = writer
+        .into_inner()
+        .map_err(|e| ApiError::IoError(e.into_error()))?;
+    if airports.is_empty() {
+        let header_end = body
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(body.len(), |i| i + 1);
+        body.truncate(header_end);
+    }
+    Ok(body)
+}
+
+/// GeoJSON `FeatureCollection` of airport points
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureCollection<'a> {
+    /// Always `"FeatureCollection"`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// One feature per airport with coordinates
+    pub features: Vec<Feature<'a>>,
+}
+
+/// GeoJSON `Feature` wrapping a single airport
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Feature<'a> {
+    /// Always `"Feature"`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Point geometry at the airport's location
+    pub geometry: PointGeometry,
+    /// Identifying airport attributes
+    pub properties: FeatureProperties<'a>,
+}
+
+/// GeoJSON `Point` geometry
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PointGeometry {
+    /// Always `"Point"`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Position as `[longitude, latitude]`, per RFC 7946 axis order
+    pub coordinates: [f64; 2],
+}
+
+/// Properties attached to each airport feature
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureProperties<'a> {
+    /// Official ICAO code
+    pub icao: &'a str,
+    /// Full airport name
+    pub name: &'a str,
+}
+
+impl<'a> FeatureCollection<'a> {
+    /// Builds a collection from airports, omitting those without coordinates
+    pub fn from_airports<A: Borrow<Airport>>(airports: &'a [A]) -> Self {
+        let features = airports
+            .iter()
+            .map(Borrow::borrow)
+            .filter_map(|airport: &Airport| {
+                Some(Feature {
+                    kind: "Feature",
+                    geometry: PointGeometry {
+                        kind: "Point",
+                        coordinates: [airport.longitude?, airport.latitude?],
+                    },
+                    properties: FeatureProperties {
+                        icao: &airport.icao,
+                        name: &airport.name,
+                    },
+                })
+            })
+            .collect();
+
+        FeatureCollection {
+            kind: "FeatureCollection",
+            features,
+        }
+    }
+}
+
+/// Renders a page of airports in the given format.
+///
+/// # Response
+/// - JSON: the full `PaginatedResponse` envelope
+/// - CSV: only the page's rows, with a header line
+/// - GeoJSON: a `FeatureCollection` of the page's airports that have coordinates
+pub fn render_airports<A>(
+    format: ResponseFormat,
+    page: PaginatedResponse<'_, A>,
+) -> Result<HttpResponse, ApiError>
+where
+    A: Borrow<Airport> + Serialize,
+{
+    match format {
+        ResponseFormat::Json => Ok(HttpResponse::Ok().json(page)),
+        ResponseFormat::Csv => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .body(airports_to_csv(page.data)?)),
+        ResponseFormat::GeoJson => Ok(HttpResponse::Ok()
+            .content_type("application/geo+json")
+            .json(FeatureCollection::from_airports(page.data))),
+    }
+}
+
+/// Airport fields as named in JSON output, in serialization order
+pub const AIRPORT_FIELDS: [&str; 17] = [
+    "icao",
+    "name",
+    "latitude",
+    "longitude",
+    "geohash",
+    "timezone",
+    "elevation_ft",
+    "iata",
+    "type",
+    "country",
+    "region",
+    "continent",
+    "city",
+    "scheduled_service",
+    "wikipedia",
+    "website",
+    "keywords",
+];
+
+/// Airport fields to keep in JSON output, from a `select` query value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldSelection {
+    /// Selected names from `AIRPORT_FIELDS`, in that order without repeats
+    fields: Vec<&'static str>,
+}
+
+impl FromStr for FieldSelection {
+    type Err = ApiError;
+
+    /// Parses a comma-separated list of `AIRPORT_FIELDS` names, e.g.
+    /// `icao,name`. Entries are trimmed and blank ones skipped; an empty list
+    /// keeps every field.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut selected = [false; AIRPORT_FIELDS.len()];
+        for name in value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let index = AIRPORT_FIELDS
+                .iter()
+                .position(|field| *field == name)
+                .ok_or_else(|| {
+                    ApiError::BadRequest(format!(
+                        "invalid select field '{name}', expected one of: {}",
+                        AIRPORT_FIELDS.join(", ")
+                    ))
+                })?;
+            selected[index] = true;
+        }
+        if !selected.contains(&true) {
+            selected = [true; AIRPORT_FIELDS.len()];
+        }
+
+        Ok(FieldSelection {
+            fields: AIRPORT_FIELDS
+                .into_iter()
+                .zip(selected)
+                .filter_map(|(field, keep)| keep.then_some(field))
+                .collect(),
+        })
+    }
+}
+
+impl FieldSelection {
+    /// Wraps `item` so that it serializes without the unselected airport fields
+    pub fn project<'a, T>(&'a self, item: &'a T) -> Projected<'a, T> {
+        Projected {
+            item,
+            selection: self,
+        }
+    }
+}
+
+/// An item serialized through a `FieldSelection`.
+///
+/// The item is rendered to a JSON object and airport fields outside the
+/// selection are dropped. Other keys, such as the `distance` or `score` added
+/// by wrappers, are kept.
+pub struct Projected<'a, T> {
+    item: &'a T,
+    selection: &'a FieldSelection,
+}
+
+impl<T: Serialize> Serialize for Projected<'_, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(self.item).map_err(serde::ser::Error::custom)?;
+        if let serde_json::Value::Object(fields) = &mut value {
+            fields.retain(|key, _| {
+                !AIRPORT_FIELDS.contains(&key.as_str())
+                    || self.selection.fields.contains(&key.as_str())
+            });
+        }
+        value.serialize(serializer)
+    }
+}
+
+/// Renders a page like `render_airports`, keeping only the selected fields of
+/// each airport in JSON output. CSV and GeoJSON output are unaffected.
+pub fn render_selected<A>(
+    format: ResponseFormat,
+    page: PaginatedResponse<'_, A>,
+    selection: Option<&FieldSelection>,
+) -> Result<HttpResponse, ApiError>
+where
+    A: Borrow<Airport> + Serialize,
+{
+    match (format, selection) {
+        (ResponseFormat::Json, Some(selection)) => {
+            let projected: Vec<Projected<A>> = page
+                .data
+                .iter()
+                .map(|item| selection.project(item))
+                .collect();
+            Ok(HttpResponse::Ok().json(page.with_data(&projected)))
+        }
+        _ => render_airports(format, page),
+    }
+}
+
+/// Re-indents compact JSON the way `serde_json::to_vec_pretty` would, keeping
+/// keys in their original order.
+///
+/// Works on the bytes without parsing them into a `Value`, which would sort
+/// object keys. The input is assumed to be valid, compact JSON; stray
+/// whitespace outside strings is dropped.
+pub fn prettify_json(compact: &[u8]) -> Vec<u8> {
+    fn newline(out: &mut Vec<u8>, depth: usize) {
+        out.push(b'\n');
+        out.resize(out.len() + 2 * depth, b' ');
+    }
+
+    let mut out = Vec::with_capacity(compact.len() * 2);
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut bytes = compact.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        if in_string {
+            out.push(byte);
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match byte {
+            b'"' => {
+                in_string = true;
+                out.push(byte);
+            }
+            b'{' | b'[' => {
+                out.push(byte);
+                match bytes.peek() {
+                    Some(&close @ (b'}' | b']')) => {
+                        out.push(close);
+                        bytes.next();
+                    }
+                    _ => {
+                        depth += 1;
+                        newline(&mut out, depth);
+                    }
+                }
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut out, depth);
+                out.push(byte);
+            }
+            b',' => {
+                out.push(byte);
+                newline(&mut out, depth);
+            }
+            b':' => out.extend_from_slice(b": "),
+            byte if byte.is_ascii_whitespace() => {}
+            _ => out.push(byte),
+        }
+    }
+    out
+}
+
+/// Every airport serialized to JSON once, so unfiltered pages can be written
+/// by copying bytes instead of running serde per request.
+///
+/// `buffer` holds each airport's JSON object followed by a comma, in dataset
+/// order; `starts[i]` is where airport `i` begins, with a final entry at the
+/// end of the buffer.
+///
+/// # Memory
+/// About the size of the full JSON export (a few hundred bytes per airport)
+/// plus a `usize` per airport, rebuilt with the dataset on every reload.
+pub struct PrerenderedAirports {
+    buffer: Bytes,
+    starts: Vec<usize>,
+}
+
+impl PrerenderedAirports {
+    /// Serializes `airports` in parallel and concatenates the results
+    pub fn new(airports: &[Airport]) -> Self {
+        let rendered: Vec<Vec<u8>> = airports
+            .par_iter()
+            .map(|airport| serde_json::to_vec(airport).expect("airports serialize to JSON"))
+            .collect();
+
+        let mut buffer = Vec::with_capacity(rendered.iter().map(|json| json.len() + 1).sum());
+        let mut starts = Vec::with_capacity(rendered.len() + 1);
+        for json in rendered {
+            starts.push(buffer.len());
+            buffer.extend_from_slice(&json);
+            buffer.push(b',');
+        }
+        starts.push(buffer.len());
+
+        PrerenderedAirports {
+            buffer: buffer.into(),
+            starts,
+        }
+    }
+
+    /// Writes the `PaginatedResponse` envelope for `page`, a page of the
+    /// airports this was built from, byte-for-byte as serde would.
+    ///
+    /// The metadata is serialized with empty `data`, whose closing `]}` is then
+    /// replaced by the page's slice of `buffer`.
+    pub fn page_json(&self, page: &PaginatedResponse<'_, Airport>) -> Bytes {
+        let mut envelope = serde_json::to_vec(&page.with_data::<Airport>(&[]))
+            .expect("page metadata serializes to JSON");
+        envelope.truncate(envelope.len() - b"]}".len());
+
+        let start = self.starts[page.offset];
+        let end = self.starts[page.offset + page.data.len()];
+        // Drop the comma after the page's last airport
+        let items = &self.buffer[start..end.saturating_sub(1).max(start)];
+
+        let mut body = BytesMut::with_capacity(envelope.len() + items.len() + 2);
+        body.extend_from_slice(&envelope);
+        body.extend_from_slice(items);
+        body.extend_from_slice(b"]}");
+        body.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pagination::{paginate, PageLimits};
+    use crate::test_support::create_test_state;
+
+    /// Tests that re-indenting compact JSON matches `serde_json`'s pretty output
+    #[actix_web::test]
+    async fn test_prettify_json() {
+        let value = serde_json::json!({
+            "data": [{"name": "a \\\"{quoted}\\\", [x]: y", "tags": []}, 1, null],
+            "empty": {},
+            "nested": {"depth": {"level": [true, -1.5e3]}},
+        });
+        let compact = serde_json::to_vec(&value).unwrap();
+        assert_eq!(
+            String::from_utf8(prettify_json(&compact)).unwrap(),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        assert_eq!(prettify_json(b"[]"), b"[]");
+        assert_eq!(prettify_json(b"\"x\""), b"\"x\"");
+    }
+
+    /// Tests that `AIRPORT_FIELDS` lists the serialized airport keys in order
+    #[actix_web::test]
+    async fn test_airport_fields() {
+        let airport = Airport::default();
+        let value = serde_json::to_value(&airport).unwrap();
+        let keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut sorted = AIRPORT_FIELDS;
+        sorted.sort_unstable();
+        assert_eq!(keys, sorted);
+
+        let selection: FieldSelection = "name, icao,name".parse().unwrap();
+        assert_eq!(selection.fields, ["icao", "name"]);
+        assert_eq!(
+            serde_json::to_value(selection.project(&airport)).unwrap(),
+            serde_json::json!({ "icao": "", "name": "" })
+        );
+    }
+
+    /// Tests that pre-serialized pages match serde's output for every window
+    #[actix_web::test]
+    async fn test_prerendered_page_json() {
+        let dataset = create_test_state().snapshot();
+        let json = PrerenderedAirports::new(&dataset.airports);
+        for (offset, limit) in [(0, 100), (0, 1), (1, 1), (1, 2), (2, 5), (3, 1), (9, 10)] {
+            let page = paginate(
+                &dataset.airports,
+                Some(offset),
+                Some(limit),
+                PageLimits::default(),
+            );
+            assert_eq!(
+                json.page_json(&page),
+                serde_json::to_vec(&page).unwrap(),
+                "offset {offset}, limit {limit}"
+            );
+        }
+
+        let empty = PrerenderedAirports::new(&[]);
+        let page = paginate::<Airport>(&[], None, None, PageLimits::default());
+        assert_eq!(empty.page_json(&page), serde_json::to_vec(&page).unwrap());
+    }
+}
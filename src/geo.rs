@@ -0,0 +1,363 @@
+//! Geographic helpers for distance and bounding-box queries.
+
+use crate::error::ApiError;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Mean Earth radius in kilometers, used for great-circle distance calculations.
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Kilometers in one international nautical mile (exact by definition).
+pub const KM_PER_NAUTICAL_MILE: f64 = 1.852;
+
+/// Kilometers in one international statute mile (exact by definition).
+pub const KM_PER_STATUTE_MILE: f64 = 1.609_344;
+
+/// Characters of a geohash in order of the 5-bit values they encode.
+pub const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Length of the geohash computed for each airport, a cell of about 3.7 cm
+/// by 1.9 cm at the equator.
+pub const GEOHASH_PRECISION: usize = 12;
+
+/// Unit in which distances are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, ToSchema)]
+pub enum DistanceUnit {
+    /// Kilometers
+    #[default]
+    #[serde(rename = "km")]
+    Kilometers,
+    /// International nautical miles
+    #[serde(rename = "nm")]
+    NauticalMiles,
+    /// International statute miles
+    #[serde(rename = "mi")]
+    StatuteMiles,
+}
+
+impl DistanceUnit {
+    /// Converts a distance in kilometers to this unit
+    pub fn from_km(self, km: f64) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => km,
+            DistanceUnit::NauticalMiles => km / KM_PER_NAUTICAL_MILE,
+            DistanceUnit::StatuteMiles => km / KM_PER_STATUTE_MILE,
+        }
+    }
+}
+
+impl FromStr for DistanceUnit {
+    type Err = ApiError;
+
+    /// Parses a `unit` query value, rejecting unknown units with `ApiError::BadRequest`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "km" => Ok(DistanceUnit::Kilometers),
+            "nm" => Ok(DistanceUnit::NauticalMiles),
+            "mi" => Ok(DistanceUnit::StatuteMiles),
+            other => Err(ApiError::BadRequest(format!(
+                "invalid unit '{other}', expected one of: km, nm, mi"
+            ))),
+        }
+    }
+}
+
+/// Latitude/longitude rectangle with inclusive edges.
+///
+/// A box with `min_lon > max_lon` crosses the antimeridian, covering longitudes
+/// from `min_lon` east to 180 and from -180 east to `max_lon`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+pub struct BoundingBox {
+    /// Southern edge in decimal degrees
+    pub min_lat: f64,
+    /// Western edge in decimal degrees
+    pub min_lon: f64,
+    /// Northern edge in decimal degrees
+    pub max_lat: f64,
+    /// Eastern edge in decimal degrees
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// Computes the smallest box containing every point within `radius_km` of
+    /// `center`, as a cheap prefilter before exact haversine checks.
+    ///
+    /// # Behavior
+    /// - Latitude extends by the radius's central angle in both directions
+    /// - Longitude extends by the widest angle the circle reaches at the
+    ///   center's latitude, wrapping across the antimeridian when needed
+    /// - Covers all longitudes when the circle contains a pole or spans half
+    ///   the globe
+    pub fn around(center: (f64, f64), radius_km: f64) -> Self {
+        let angle = radius_km / EARTH_RADIUS_KM;
+        let (lat, lon) = (center.0.to_radians(), center.1.to_radians());
+        let min_lat = lat - angle;
+        let max_lat = lat + angle;
+        let half_pi = std::f64::consts::FRAC_PI_2;
+
+        if min_lat <= -half_pi || max_lat >= half_pi || angle >= half_pi {
+            return BoundingBox {
+                min_lat: min_lat.to_degrees().max(-90.0),
+                min_lon: -180.0,
+                max_lat: max_lat.to_degrees().min(90.0),
+                max_lon: 180.0,
+            };
+        }
+
+        let delta_lon = (angle.sin() / lat.cos()).asin().to_degrees();
+        let wrap = |lon: f64| {
+            if lon < -180.0 {
+                lon + 360.0
+            } else if lon > 180.0 {
+                lon - 360.0
+            } else {
+                lon
+            }
+        };
+        BoundingBox {
+            min_lat: min_lat.to_degrees(),
+            min_lon: wrap(lon.to_degrees() - delta_lon),
+            max_lat: max_lat.to_degrees(),
+            max_lon: wrap(lon.to_degrees() + delta_lon),
+        }
+    }
+
+    /// Edges with `-0.0` replaced by `0.0`, which compares equal to it
+    fn normalized_edges(&self) -> [f64; 4] {
+        [self.min_lat, self.min_lon, self.max_lat, self.max_lon].map(|edge| edge + 0.0)
+    }
+
+    /// Checks whether a point lies inside the box (edges inclusive)
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let lat_ok = lat >= self.min_lat && lat <= self.max_lat;
+        let lon_ok = if self.min_lon <= self.max_lon {
+            lon >= self.min_lon && lon <= self.max_lon
+        } else {
+            lon >= self.min_lon || lon <= self.max_lon
+        };
+        lat_ok && lon_ok
+    }
+}
+
+/// Boxes come from parsed JSON or `around`, whose edges are never NaN, so
+/// equality is total and boxes can key the search cache
+impl Eq for BoundingBox {}
+
+impl Hash for BoundingBox {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for edge in self.normalized_edges() {
+            edge.to_bits().hash(state);
+        }
+    }
+}
+
+/// Checks that `lat`/`lon` form a valid point, returning it as a pair
+///
+/// # Errors
+/// `ApiError::BadRequest` (400) if `lat` is outside [-90, 90] or `lon` outside [-180, 180]
+pub fn validate_point(lat: f64, lon: f64) -> Result<(f64, f64), ApiError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(ApiError::BadRequest(
+            "lat must be within [-90, 90] and lon within [-180, 180]".into(),
+        ));
+    }
+    Ok((lat, lon))
+}
+
+/// Computes the great-circle distance between two points using the haversine formula.
+///
+/// # Parameters
+/// - `a`: First point as `(latitude, longitude)` in decimal degrees
+/// - `b`: Second point as `(latitude, longitude)` in decimal degrees
+///
+/// # Returns
+/// Distance in kilometers along the surface of a spherical Earth
+pub fn haversine_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = lon2 - lon1;
+
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+/// Computes the initial great-circle bearing from one point towards another
+/// using the forward azimuth formula.
+///
+/// # Parameters
+/// - `from`: Starting point as `(latitude, longitude)` in decimal degrees
+/// - `to`: Destination point as `(latitude, longitude)` in decimal degrees
+///
+/// # Returns
+/// Degrees clockwise from true north in `[0, 360)`; 0 when the points coincide
+pub fn initial_bearing_deg(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let dlon = lon2 - lon1;
+
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    let bearing = y.atan2(x).to_degrees().rem_euclid(360.0);
+    // Rounding can carry a tiny negative angle up to exactly 360
+    if bearing >= 360.0 {
+        0.0
+    } else {
+        bearing
+    }
+}
+
+/// Encodes a point as a geohash of `precision` characters.
+///
+/// # Parameters
+/// - `point`: `(latitude, longitude)` in decimal degrees
+/// - `precision`: Number of characters; each adds 5 bits, alternately
+///   halving the longitude and latitude ranges starting with longitude
+///
+/// # Returns
+/// Lowercase geohash; every prefix of it is the geohash of a larger cell
+/// containing the point
+pub fn geohash(point: (f64, f64), precision: usize) -> String {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lon_range = (-180.0, 180.0);
+    let mut even_bit = true;
+    let mut hash = String::with_capacity(precision);
+    for _ in 0..precision {
+        let mut value = 0;
+        for _ in 0..5 {
+            let (range, coordinate) = if even_bit {
+                (&mut lon_range, point.1)
+            } else {
+                (&mut lat_range, point.0)
+            };
+            let mid = (range.0 + range.1) / 2.0;
+            value <<= 1;
+            if coordinate >= mid {
+                value |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even_bit = !even_bit;
+        }
+        hash.push(char::from(GEOHASH_ALPHABET.as_bytes()[value]));
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the haversine helper against a known JFK-LHR distance
+    #[actix_web::test]
+    async fn test_haversine_km() {
+        let jfk = (40.6398, -73.7789);
+        let lhr = (51.4706, -0.461941);
+        let distance = haversine_km(jfk, lhr);
+        assert!((distance - 5540.0).abs() < 10.0, "got {distance}");
+        assert_eq!(haversine_km(jfk, jfk), 0.0);
+    }
+
+    /// Tests bearings against known routes, the cardinal directions, and the 0-360 range
+    #[actix_web::test]
+    async fn test_initial_bearing_deg() {
+        let jfk = (40.6398, -73.7789);
+        let lhr = (51.4706, -0.461941);
+        let bearing = initial_bearing_deg(jfk, lhr);
+        assert!((bearing - 51.4).abs() < 0.5, "got {bearing}");
+        let back = initial_bearing_deg(lhr, jfk);
+        assert!((back - 288.1).abs() < 0.5, "got {back}");
+
+        let origin = (0.0, 0.0);
+        for (to, expected) in [
+            ((1.0, 0.0), 0.0),
+            ((0.0, 1.0), 90.0),
+            ((-1.0, 0.0), 180.0),
+            ((0.0, -1.0), 270.0),
+        ] {
+            let bearing = initial_bearing_deg(origin, to);
+            assert!((bearing - expected).abs() < 1e-9, "{to:?}: got {bearing}");
+        }
+        assert_eq!(initial_bearing_deg(jfk, jfk), 0.0);
+        // Crossing the antimeridian westwards stays positive
+        let west = initial_bearing_deg((0.0, 179.5), (0.0, -179.5));
+        assert!((west - 90.0).abs() < 1e-9, "got {west}");
+    }
+
+    /// Tests that radius boxes bound the circle, wrap the antimeridian, and
+    /// open up around the poles
+    #[actix_web::test]
+    async fn test_bounding_box_around() {
+        let center = (51.4706, -0.461941);
+        let bbox = BoundingBox::around(center, 500.0);
+        assert!(bbox.min_lat < 47.0 && bbox.max_lat > 55.9);
+        assert!(bbox.min_lon < -7.0 && bbox.max_lon > 6.0);
+        // Points just inside the radius in each direction pass the prefilter
+        for bearing in (0..360).step_by(15) {
+            let bearing = f64::from(bearing).to_radians();
+            let angle = 499.0 / EARTH_RADIUS_KM;
+            let lat1 = center.0.to_radians();
+            let lat2 = (lat1.sin() * angle.cos() + lat1.cos() * angle.sin() * bearing.cos()).asin();
+            let lon2 = center.1.to_radians()
+                + (bearing.sin() * angle.sin() * lat1.cos())
+                    .atan2(angle.cos() - lat1.sin() * lat2.sin());
+            let point = (lat2.to_degrees(), lon2.to_degrees());
+            assert!(haversine_km(center, point) < 500.0);
+            assert!(
+                bbox.contains(point.0, point.1),
+                "{point:?} outside {bbox:?}"
+            );
+        }
+        assert!(!bbox.contains(40.6398, -73.7789));
+
+        let wrapped = BoundingBox::around((0.0, 179.5), 200.0);
+        assert!(wrapped.min_lon > wrapped.max_lon);
+        assert!(wrapped.contains(0.0, -179.5));
+        assert!(wrapped.contains(0.0, 179.0));
+        assert!(!wrapped.contains(0.0, 0.0));
+
+        let polar = BoundingBox::around((89.0, 10.0), 200.0);
+        assert_eq!(
+            (polar.min_lon, polar.max_lon, polar.max_lat),
+            (-180.0, 180.0, 90.0)
+        );
+        assert!(polar.contains(89.5, -170.0));
+    }
+
+    /// Tests geohash encoding against published reference hashes
+    #[actix_web::test]
+    async fn test_geohash() {
+        assert_eq!(geohash((57.64911, 10.40744), 11), "u4pruydqqvj");
+        assert_eq!(geohash((42.6, -5.6), 5), "ezs42");
+        // JFK and Heathrow at the airports' default precision
+        let jfk = geohash((40.6398, -73.7789), GEOHASH_PRECISION);
+        assert_eq!(jfk.len(), GEOHASH_PRECISION);
+        assert!(jfk.starts_with("dr5x"), "{jfk}");
+        assert!(geohash((51.4706, -0.461941), GEOHASH_PRECISION).starts_with("gcp"));
+        assert_eq!(geohash((0.0, 0.0), 4), "s000");
+        assert_eq!(geohash((90.0, 180.0), 3), "zzz");
+        assert_eq!(geohash((-90.0, -180.0), 3), "000");
+        assert_eq!(geohash((1.0, 1.0), 0), "");
+    }
+
+    /// Tests unit parsing and conversion from kilometers
+    #[actix_web::test]
+    async fn test_distance_unit() {
+        assert_eq!(
+            "km".parse::<DistanceUnit>().unwrap(),
+            DistanceUnit::Kilometers
+        );
+        assert_eq!(DistanceUnit::default(), DistanceUnit::Kilometers);
+        assert!("miles".parse::<DistanceUnit>().is_err());
+
+        assert_eq!(DistanceUnit::Kilometers.from_km(10.0), 10.0);
+        let nm: DistanceUnit = "nm".parse().unwrap();
+        assert!((nm.from_km(1.852) - 1.0).abs() < 1e-12);
+        let mi: DistanceUnit = "mi".parse().unwrap();
+        assert!((mi.from_km(1.609_344) - 1.0).abs() < 1e-12);
+        assert_eq!(serde_json::to_value(mi).unwrap(), "mi");
+    }
+}
@@ -0,0 +1,4292 @@
+//! HTTP handlers for every endpoint, and their route registration.
+
+use crate::cache::SearchCacheKey;
+use crate::error::{ApiError, ErrorBody};
+use crate::format::{
+    render_airports, render_selected, FeatureCollection, FieldSelection, ResponseFormat,
+};
+use crate::geo::{haversine_km, DistanceUnit};
+#[cfg(feature = "spatial")]
+use crate::geo::{initial_bearing_deg, validate_point, GEOHASH_ALPHABET, GEOHASH_PRECISION};
+use crate::middleware::{dataset_etag, TOTAL_COUNT_HEADER};
+use crate::model::Airport;
+use crate::openapi::{ApiDoc, SWAGGER_UI_HTML};
+use crate::pagination::{
+    decode_cursor, encode_cursor, paginate, paginate_head, CursorPage, PagePosition,
+    PaginatedResponse,
+};
+#[cfg(feature = "fuzzy")]
+use crate::params::FuzzyParams;
+use crate::params::{
+    query_error, AutocompleteParams, BoundingBoxParams, CursorParams, DistanceParams,
+    PaginationParams, RandomParams, SearchBody, SearchParams, SelectParams,
+};
+#[cfg(feature = "spatial")]
+use crate::params::{GeohashParams, NearestParams, RadiusParams};
+use crate::sample::{random_seed, sample_indices};
+use crate::search::{
+    count_by, fold_text, AirportFilter, MatchMode, SearchFields, SearchQuery, SortOrder, TokenMatch,
+};
+#[cfg(feature = "fuzzy")]
+use crate::search::{fuzzy_matches, fuzzy_rank_indexed, FuzzyMatch};
+use crate::state::{offload_search, AppState, Dataset};
+use crate::stats::DatasetStats;
+use actix_web::{
+    body::MessageBody,
+    get,
+    http::header::{self, ContentType},
+    post, web, HttpRequest, HttpResponse,
+};
+use log::info;
+#[cfg(feature = "metrics")]
+use prometheus::Encoder;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
+use std::collections::HashSet;
+use std::str::FromStr;
+use utoipa::{OpenApi, ToSchema};
+
+/// Largest `offset + limit` window for which search selects only the requested
+/// page's prefix instead of materializing and sorting every match.
+pub const LAZY_WINDOW_LIMIT: usize = 1000;
+
+/// Number of suggestions returned by autocomplete when `limit` is not given.
+pub const DEFAULT_AUTOCOMPLETE_LIMIT: usize = 10;
+
+/// Number of airports serialized into each chunk of the NDJSON export stream.
+pub const STREAM_CHUNK_SIZE: usize = 256;
+
+/// Default maximum edit distance accepted by fuzzy search.
+pub const DEFAULT_FUZZY_DISTANCE: usize = 2;
+
+/// Maximum number of ICAO codes accepted by a single batch lookup.
+pub const MAX_BATCH_SIZE: usize = 200;
+
+/// Airport paired with its distance from a reference point.
+/// Serializes as the airport's fields plus `distance_km`, `distance`, `unit`,
+/// and `bearing_deg`.
+#[cfg(feature = "spatial")]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AirportDistance<'a> {
+    /// Referenced airport record
+    #[serde(flatten)]
+    pub airport: &'a Airport,
+    /// Great-circle distance from the reference point in kilometers
+    pub distance_km: f64,
+    /// The same distance in `unit`
+    pub distance: f64,
+    /// Unit of `distance`
+    pub unit: DistanceUnit,
+    /// Initial bearing from the reference point to the airport, in degrees
+    /// clockwise from true north (0-360)
+    pub bearing_deg: f64,
+}
+
+#[cfg(feature = "spatial")]
+impl<'a> AirportDistance<'a> {
+    /// Measures `airport` from `origin`, or `None` if it has no coordinates
+    fn measure(airport: &'a Airport, origin: (f64, f64), unit: DistanceUnit) -> Option<Self> {
+        let point = (airport.latitude?, airport.longitude?);
+        let distance_km = haversine_km(origin, point);
+        Some(AirportDistance {
+            airport,
+            distance_km,
+            distance: unit.from_km(distance_km),
+            unit,
+            bearing_deg: initial_bearing_deg(origin, point),
+        })
+    }
+}
+
+/// Great-circle distance between two airports
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AirportPairDistance<'a> {
+    /// Departure airport
+    pub from: &'a Airport,
+    /// Arrival airport
+    pub to: &'a Airport,
+    /// Great-circle distance in kilometers
+    pub distance_km: f64,
+    /// The same distance in `unit`
+    pub distance: f64,
+    /// Unit of `distance`
+    pub unit: DistanceUnit,
+}
+
+/// Number of airports sharing a country code
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CountryCount<'a> {
+    /// ISO country code
+    pub country: &'a str,
+    /// Number of airports in the country
+    pub count: usize,
+}
+
+/// Number of airports sharing a facility type
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TypeCount<'a> {
+    /// Facility type, e.g. `large_airport` or `heliport`
+    #[serde(rename = "type")]
+    pub kind: &'a str,
+    /// Number of airports of the type
+    pub count: usize,
+}
+
+/// Timezone of a single airport served by `/airports/{icao}/timezone`
+#[cfg(feature = "timezone")]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AirportTimezone<'a> {
+    /// Official ICAO code
+    pub icao: &'a str,
+    /// IANA timezone name, `null` if the airport has no coordinates or the
+    /// server was built without the `timezone` feature
+    pub timezone: Option<&'a str>,
+}
+
+/// Build and dataset identification served by `/version`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VersionInfo {
+    /// Crate version of the running server
+    pub version: &'static str,
+    /// Hash of the served airport data, the opaque part of the `ETag`
+    pub dataset_hash: String,
+    /// Number of airports in the served dataset
+    pub airports: usize,
+    /// Unix time in seconds when the server started
+    pub started_at: u64,
+    /// Threads in the pool searches run on
+    pub search_threads: usize,
+}
+
+/// Number of airports sharing a continent code
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ContinentCount<'a> {
+    /// Continent code
+    pub continent: &'a str,
+    /// Number of airports on the continent
+    pub count: usize,
+}
+
+/// Handler for GET /airports endpoint returning paginated airport list
+///
+/// # Parameters
+/// - `req`: Incoming request, used for `Accept` negotiation and the `Link` header
+/// - `data`: Application state with airport list
+/// - `query`: Pagination and attribute filter parameters from URL query string
+///
+/// # Behavior
+/// - Without filters, paginates the dataset slice directly (zero-copy); JSON
+///   pages are copied from the dataset's pre-serialized `json` rather than
+///   serialized per request, unless `select` trims the fields
+/// - With filters, collects matching airports in parallel before paginating,
+///   off the worker thread via `offload_search`
+/// - With `icaos`, looks up exactly those codes instead; see `lookup_icaos`
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse containing airport data slice, limited to
+///   the `select` fields if given, or CSV rows when the client sends
+///   `Accept: text/csv`
+/// - JSON-encoded IcaoList when `icaos` is given
+/// - Paged responses carry a `Link` header to the `next` and `prev` pages
+/// - `ApiError::BadRequest` (400) if `limit` is 0, `min_elevation` exceeds
+///   `max_elevation`, a `region` code is malformed, `select` names an unknown
+///   field, or `icaos` lists more than `MAX_BATCH_SIZE` codes
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports",
+    description = "Pages through the dataset in load order, optionally narrowed by attribute filters. \
+                   Send `Accept: text/csv` for CSV rows. With `icaos`, returns an `IcaoList` of exactly \
+                   those airports in request order instead.",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Page of airports", body = PaginatedResponse<Airport>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports")]
+pub async fn get_airports(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    if let Some(icaos) = query.icaos.as_deref() {
+        return Ok(HttpResponse::Ok().json(lookup_icaos(&dataset, icaos)?));
+    }
+    let format = ResponseFormat::negotiate(&req);
+    let selection = parse_selection(query.select.as_deref())?;
+    let filter = AirportFilter::from(&*query);
+    if !filter.is_active() {
+        let response = paginate(
+            &dataset.airports,
+            query.offset,
+            query.limit,
+            data.page_limits,
+        );
+        let position = response.position();
+        let rendered = match (format, &selection) {
+            // Served from the bytes serialized at load instead of running serde
+            (ResponseFormat::Json, None) => HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .body(dataset.json.page_json(&response)),
+            _ => render_selected(format, response, selection.as_ref())?,
+        };
+        return Ok(page_headers(&req, position, rendered));
+    }
+
+    let filtered: Vec<usize> = offload_search(&data, &dataset, move |dataset| {
+        dataset
+            .airports
+            .par_iter()
+            .enumerate()
+            .filter(|(_, airport)| filter.matches(airport))
+            .map(|(index, _)| index)
+            .collect()
+    })
+    .await?;
+    let filtered: Vec<&Airport> = filtered
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+
+    let response = paginate(&filtered, query.offset, query.limit, data.page_limits);
+    let position = response.position();
+    Ok(page_headers(
+        &req,
+        position,
+        render_selected(format, response, selection.as_ref())?,
+    ))
+}
+
+/// Parses an optional `select` value into the fields to keep
+fn parse_selection(select: Option<&str>) -> Result<Option<FieldSelection>, ApiError> {
+    select.map(FieldSelection::from_str).transpose()
+}
+
+/// Airports for a requested list of ICAO codes, in request order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IcaoList<'a> {
+    /// Airport for each requested code, `null` where no airport has it
+    pub data: Vec<Option<&'a Airport>>,
+    /// Requested codes that matched no airport, in request order
+    pub errors: Vec<&'a str>,
+}
+
+/// Resolves a comma-separated `icaos` list through the ICAO index.
+///
+/// Codes are case-insensitive and trimmed, and blank entries are skipped.
+/// Every other code gets a slot in `data`, repeats included, in request order
+/// rather than dataset order, so the result lines up with the request.
+/// Pagination and filter parameters do not apply.
+///
+/// # Errors
+/// `ApiError::BadRequest` if more than `MAX_BATCH_SIZE` codes are listed
+pub fn lookup_icaos<'a>(dataset: &'a Dataset, icaos: &'a str) -> Result<IcaoList<'a>, ApiError> {
+    let codes: Vec<&str> = icaos
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .collect();
+    if codes.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "at most {MAX_BATCH_SIZE} codes are allowed in icaos, got {}",
+            codes.len()
+        )));
+    }
+
+    let data: Vec<Option<&Airport>> = codes
+        .iter()
+        .map(|code| dataset.find_by_icao(code))
+        .collect();
+    let errors = codes
+        .iter()
+        .zip(&data)
+        .filter(|(_, airport)| airport.is_none())
+        .map(|(&code, _)| code)
+        .collect();
+    Ok(IcaoList { data, errors })
+}
+
+/// Handler for GET /airports/cursor endpoint listing airports with cursor pagination
+///
+/// # Parameters
+/// - `data`: Application state with airport list and ICAO index
+/// - `query`: Cursor, page size, and attribute filter parameters from URL query string
+///
+/// # Behavior
+/// - Resumes in dataset order right after the airport named by `cursor`, located
+///   through the ICAO index, so pages stay consistent across reloads
+/// - Scans sequentially, off the worker thread via `offload_search`, and stops
+///   as soon as the page is full
+/// - `limit` defaults to the configured default page size and is clamped to
+///   at least 1 and at most the maximum, so every page makes progress
+///
+/// # Response
+/// - JSON-encoded CursorPage with `next_cursor` set while more airports remain
+/// - `ApiError::BadRequest` (400) if the cursor is malformed or names an airport
+///   that is no longer in the dataset, or if a `region` code is malformed
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports with cursor pagination",
+    description = "Pages through the dataset after the airport named by `cursor`, so pages stay \
+                   consistent across reloads.",
+    params(CursorParams),
+    responses(
+        (status = 200, description = "Page of airports", body = CursorPage<Airport>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/cursor")]
+pub async fn get_airports_by_cursor(
+    data: web::Data<AppState>,
+    query: web::Query<CursorParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let filter = AirportFilter::from(&*query);
+    let limit = data.page_limits.resolve(query.limit).max(1);
+
+    let start = match query.cursor.as_deref() {
+        None => 0,
+        Some(cursor) => {
+            let icao = decode_cursor(cursor)?;
+            let index = dataset.by_icao.get(&icao.to_lowercase()).ok_or_else(|| {
+                ApiError::BadRequest(format!("cursor refers to unknown airport {icao}"))
+            })?;
+            index + 1
+        }
+    };
+
+    // Fetch one extra match to learn whether another page follows
+    let page: Vec<usize> = offload_search(&data, &dataset, move |dataset| {
+        dataset.airports[start..]
+            .iter()
+            .enumerate()
+            .filter(|(_, airport)| filter.matches(airport))
+            .map(|(index, _)| start + index)
+            .take(limit + 1)
+            .collect()
+    })
+    .await?;
+    let mut page: Vec<&Airport> = page
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+    let has_more = page.len() > limit;
+    page.truncate(limit);
+
+    let next_cursor = has_more
+        .then(|| page.last().map(|airport| encode_cursor(&airport.icao)))
+        .flatten();
+    Ok(HttpResponse::Ok().json(CursorPage {
+        data: page,
+        has_more,
+        next_cursor,
+    }))
+}
+
+/// Handler for GET /airports.geojson endpoint returning a GeoJSON FeatureCollection
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Pagination and attribute filter parameters from URL query string
+///
+/// # Behavior
+/// - Drops airports without coordinates before paginating, so pages stay full
+/// - Applies the same attribute filters as `/airports`
+///
+/// # Response
+/// - `application/geo+json` FeatureCollection of `Point` features
+/// - `ApiError::BadRequest` (400) if `limit` is 0
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports as GeoJSON",
+    description = "Pages through airports with coordinates as a GeoJSON `FeatureCollection`.",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Page of airport points", body = FeatureCollection,
+         content_type = "application/geo+json",
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports.geojson")]
+pub async fn get_airports_geojson(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let filter = AirportFilter::from(&*query);
+
+    let located: Vec<usize> = offload_search(&data, &dataset, move |dataset| {
+        dataset
+            .airports
+            .par_iter()
+            .enumerate()
+            .filter(|(_, airport)| airport.latitude.is_some() && airport.longitude.is_some())
+            .filter(|(_, airport)| filter.matches(airport))
+            .map(|(index, _)| index)
+            .collect()
+    })
+    .await?;
+    let located: Vec<&Airport> = located
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+
+    let response = paginate(&located, query.offset, query.limit, data.page_limits);
+    let position = response.position();
+    Ok(page_headers(
+        &req,
+        position,
+        render_airports(ResponseFormat::GeoJson, response)?,
+    ))
+}
+
+/// Handler for GET /airports/search endpoint with parallelized filtering
+///
+/// # Parameters
+/// - `req`: Incoming request, used for `Accept` negotiation when `format` is absent
+///   and for the `Link` header
+/// - `data`: Application state with airport list
+/// - `query`: Search parameters including query string and pagination
+///
+/// # Behavior
+/// - Performs case-insensitive search on ICAO codes, IATA codes, and names
+/// - In the default `contains` mode, every whitespace-separated query word must
+///   match, in any order, or with `match=any` at least one of them
+/// - Drops airports containing any `-word` exclusion from `q`
+/// - Matches by substring, exact equality, or prefix depending on `mode`, or
+///   with `mode=phonetic` by the Soundex codes of name words computed at load
+/// - Checks only the fields listed in `fields`, when given
+/// - Narrows results by the attribute filters (e.g., `type`)
+/// - Uses Rayon's parallel iterator for efficient multi-core filtering, run off the
+///   worker thread via `offload_search`
+/// - Sorts filtered results by the requested `sort` order; `relevance` ranks exact
+///   ICAO/IATA matches above prefix matches above plain substring matches
+/// - With `lat` and `lon`, measures each match's haversine distance from the
+///   point, and `sort` defaults to `distance`: nearest first, airports without
+///   coordinates last, or dropped entirely with `require_coordinates=true`
+/// - Applies pagination to sorted results
+/// - For small `offset + limit` windows, keeps only the leading matches in bounded
+///   heaps while counting the rest; `count=true` forces collecting and sorting every
+///   match instead. Both paths return identical pages and an exact `total`.
+///
+/// # Caching
+/// JSON bodies are cached in `AppState::search_cache`, keyed by the parsed query,
+/// filters, offset, effective limit, `debug`, `highlight`, `strict`, and `select`. Hits skip the scan entirely and
+/// are counted in the `search_cache_*` metrics; a reload clears the cache.
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse containing matching airports, CSV rows when
+///   the client sends `Accept: text/csv`, or GeoJSON with `format=geojson`
+/// - With `debug=true`, JSON results carry each airport's relevance `score`
+/// - With `highlight=true`, JSON results carry a `highlight` object with the
+///   `[start, end)` byte ranges of `name` and `icao` that matched the query
+/// - With `select`, JSON results keep only the listed airport fields
+/// - With `lat` and `lon`, JSON results carry each located airport's `distance_km`
+/// - `ApiError::BadRequest` (400) if `sort`, `mode`, `match`, `fields`, `select`, or `format` is not a known value,
+///   if `limit` is 0, if a `region` code is malformed, if `q` is blank, made only
+///   of `-word` exclusions, shorter than the configured minimum length, or longer
+///   than `MAX_QUERY_LENGTH` bytes,
+///   if only one of `lat` and `lon` is given or the point is out of range, or if
+///   `sort=distance` is given without them
+/// - `ApiError::NotFound` (404) with `strict=true` if no airport matches; without
+///   it such searches return 200 with an empty `data` array and `total` 0. A
+///   page past the end of a non-empty result is still 200.
+#[utoipa::path(
+    tag = "airports",
+    summary = "Search airports",
+    description = "Matches `q` against ICAO codes, IATA codes, names, and cities, ignoring case and \
+                   accents. With `lat` and `lon`, results carry `distance_km` and default to nearest \
+                   first. Send `Accept: text/csv` or `format=csv` for CSV rows, `format=geojson` for \
+                   GeoJSON.",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Page of matching airports", body = PaginatedResponse<Airport>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 404, description = "No airport matches, with `strict=true`", body = ErrorBody),
+    )
+)]
+#[get("/airports/search")]
+pub async fn search_airports(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<SearchParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate(data.min_query_length)?;
+    let filter = AirportFilter::from(&*query);
+    search_response(&req, &data, &query, filter, true).await
+}
+
+/// Handler for POST /airports/search endpoint, taking the search as a JSON body
+///
+/// # Parameters
+/// - `req`: Incoming request, used for `Accept` negotiation when `format` is absent
+/// - `data`: Application state with airport list
+/// - `body`: Query string, list filters as arrays, optional `bbox` and elevation
+///   bounds, and the output options of GET /airports/search
+///
+/// # Behavior
+/// - Runs exactly the search GET /airports/search runs for the same values,
+///   sharing its matching, ranking, pagination, and cache
+/// - Array entries are taken whole, so values may contain commas
+/// - `bbox` keeps airports whose coordinates lie in the box, edges inclusive,
+///   and drops airports without coordinates
+///
+/// # Response
+/// - Same as GET /airports/search, without the `Link` header, since the
+///   pages it would point to need the body too; page with `offset` instead
+/// - `ApiError::BadRequest` (400) for the values GET /airports/search rejects,
+///   if the body is not a JSON object of the documented fields, or if
+///   `min_elevation` exceeds `max_elevation` or `bbox.min_lat` exceeds `bbox.max_lat`
+/// - `ApiError::NotFound` (404) with `strict: true` if no airport matches
+#[utoipa::path(
+    tag = "airports",
+    summary = "Search airports with a JSON body",
+    description = "Same search as `GET /airports/search`, with list filters as JSON arrays and an \
+                   optional bounding box and elevation range. Suited to clients assembling many \
+                   filters.",
+    request_body(content = SearchBody, example = json!({
+        "q": "international",
+        "type": ["large_airport"],
+        "country": ["US", "CA"],
+        "bbox": {"min_lat": 30.0, "min_lon": -125.0, "max_lat": 50.0, "max_lon": -100.0},
+        "limit": 10
+    })),
+    responses(
+        (status = 200, description = "Page of matching airports", body = PaginatedResponse<Airport>,
+         headers(("X-Total-Count" = usize, description = "Number of matching airports"))),
+        (status = 400, description = "Malformed body or invalid values", body = ErrorBody),
+        (status = 404, description = "No airport matches, with `strict: true`", body = ErrorBody),
+    )
+)]
+#[post("/airports/search")]
+pub async fn search_airports_by_body(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<SearchBody>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate(data.min_query_length)?;
+    let (query, filter) = body.into_inner().into_search();
+    search_response(&req, &data, &query, filter, false).await
+}
+
+/// Runs a validated search, serving it from and storing it in the search cache
+/// where possible, and adds an `X-Total-Count` header, plus a `Link` header
+/// when `link` is set
+async fn search_response(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    query: &SearchParams,
+    filter: AirportFilter,
+    link: bool,
+) -> Result<HttpResponse, ApiError> {
+    let page_headers = |position: PagePosition, response: HttpResponse| {
+        if link {
+            page_headers(req, position, response)
+        } else {
+            total_count(position, response)
+        }
+    };
+    // Read before the snapshot, so a reload in between invalidates this request's insert
+    let generation = data.search_cache.generation();
+    let dataset = data.snapshot();
+    let origin = query.origin()?;
+    let sort = match query.sort.as_deref().map(SortOrder::from_str).transpose()? {
+        Some(SortOrder::Distance) if origin.is_none() => {
+            return Err(ApiError::BadRequest(
+                "sort=distance requires lat and lon".into(),
+            ));
+        }
+        Some(sort) => sort,
+        None if origin.is_some() => SortOrder::Distance,
+        None => SortOrder::default(),
+    };
+    let search = SearchQuery {
+        sort,
+        mode: query
+            .mode
+            .as_deref()
+            .map(MatchMode::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        fields: query
+            .fields
+            .as_deref()
+            .map(SearchFields::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        tokens: query
+            .token_match
+            .as_deref()
+            .map(TokenMatch::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        filter,
+        origin,
+        ..SearchQuery::new(&query.q)
+    };
+    let format = ResponseFormat::resolve(req, query.format.as_deref())?;
+    let debug = query.debug.unwrap_or(false);
+    let highlight = query.highlight.unwrap_or(false);
+    let strict = query.strict.unwrap_or(false);
+    let selection = parse_selection(query.select.as_deref())?;
+    let offset = query.offset.unwrap_or(0);
+    let limit = data.page_limits.resolve(query.limit);
+
+    let cache_key =
+        (format == ResponseFormat::Json && !data.search_cache.is_disabled()).then(|| {
+            SearchCacheKey {
+                query: search.clone(),
+                offset,
+                limit,
+                debug,
+                highlight,
+                strict,
+                select: selection.clone(),
+            }
+        });
+    if let Some(key) = &cache_key {
+        if let Some((body, position)) = data.search_cache.get(key) {
+            #[cfg(feature = "metrics")]
+            data.metrics.search_cache_hits.inc();
+            let response = HttpResponse::Ok()
+                .content_type(header::ContentType::json())
+                .body(body);
+            return Ok(page_headers(position, response));
+        }
+        #[cfg(feature = "metrics")]
+        data.metrics.search_cache_misses.inc();
+    }
+
+    let no_match = || ApiError::NotFound(format!("no airports match '{}'", query.q.trim()));
+    let window = offset.saturating_add(limit);
+    let (position, response) = if !query.count.unwrap_or(false) && window <= LAZY_WINDOW_LIMIT {
+        // Keep only the leading `window` matches while counting the rest,
+        // avoiding a full allocation and sort for large match sets
+        let scan = search.clone();
+        let (head, total) = offload_search(data, &dataset, move |dataset| {
+            scan.run_head_indices(&dataset.airports, window)
+        })
+        .await?;
+        let head: Vec<&Airport> = head
+            .into_iter()
+            .map(|index| &dataset.airports[index])
+            .collect();
+        if strict && total == 0 {
+            return Err(no_match());
+        }
+        let page = paginate_head(&head, total, query.offset, query.limit, data.page_limits);
+        (
+            page.position(),
+            render_search_page(&search, format, debug, highlight, selection.as_ref(), page)?,
+        )
+    } else {
+        let scan = search.clone();
+        let matches = offload_search(data, &dataset, move |dataset| {
+            scan.run_indices(&dataset.airports)
+        })
+        .await?;
+        let matches: Vec<&Airport> = matches
+            .into_iter()
+            .map(|index| &dataset.airports[index])
+            .collect();
+        if strict && matches.is_empty() {
+            return Err(no_match());
+        }
+        let page = paginate(&matches, query.offset, query.limit, data.page_limits);
+        (
+            page.position(),
+            render_search_page(&search, format, debug, highlight, selection.as_ref(), page)?,
+        )
+    };
+
+    let Some(key) = cache_key else {
+        return Ok(page_headers(position, response));
+    };
+    match response.into_body().try_into_bytes() {
+        Ok(body) => {
+            data.search_cache
+                .insert(generation, key, (body.clone(), position));
+            let response = HttpResponse::Ok()
+                .content_type(header::ContentType::json())
+                .body(body);
+            Ok(page_headers(position, response))
+        }
+        // JSON pages are always rendered as a single in-memory buffer
+        Err(_) => Err(ApiError::InternalError),
+    }
+}
+
+/// Adds the `X-Total-Count` header of `position` to `response`, and a `Link`
+/// header with its `next` and `prev` pages, built from the request's own path
+/// and query string
+fn page_headers(req: &HttpRequest, position: PagePosition, response: HttpResponse) -> HttpResponse {
+    let mut response = total_count(position, response);
+    let links = position.link_header(req.path(), req.query_string());
+    if let Some(value) = links.and_then(|links| header::HeaderValue::from_str(&links).ok()) {
+        response.headers_mut().insert(header::LINK, value);
+    }
+    response
+}
+
+/// Adds an `X-Total-Count` header with the `total` of `position` to `response`
+fn total_count(position: PagePosition, mut response: HttpResponse) -> HttpResponse {
+    response.headers_mut().insert(
+        TOTAL_COUNT_HEADER,
+        header::HeaderValue::from(position.total),
+    );
+    response
+}
+
+/// Renders a page of search results, adding each airport's `score` to JSON
+/// output when `debug` is set, its `highlight` when `highlight` is set, and
+/// its `distance_km` when the search has an origin, and keeping only the
+/// `selection` fields of each airport.
+/// Only the page's own airports are annotated.
+fn render_search_page(
+    search: &SearchQuery,
+    format: ResponseFormat,
+    debug: bool,
+    highlight: bool,
+    selection: Option<&FieldSelection>,
+    page: PaginatedResponse<'_, &Airport>,
+) -> Result<HttpResponse, ApiError> {
+    if (debug || highlight || search.origin.is_some()) && format == ResponseFormat::Json {
+        let annotated = search.annotated(page.data, debug, highlight);
+        return render_selected(format, page.with_data(&annotated), selection);
+    }
+    render_selected(format, page, selection)
+}
+
+/// Handler for GET /airports/autocomplete endpoint serving typeahead suggestions
+///
+/// # Parameters
+/// - `data`: Application state with the dataset's prefix index
+/// - `query`: Typed prefix and suggestion count
+///
+/// # Behavior
+/// - Binary-searches the prefix index built at load time instead of scanning
+///   every airport, so short prefixes stay cheap
+/// - Matches the prefix against ICAO codes and individual name words
+/// - Ranks ICAO matches first, then matches on earlier name words
+///
+/// # Response
+/// - JSON array of up to `limit` airports
+/// - `ApiError::BadRequest` (400) if `q` is blank or longer than `MAX_QUERY_LENGTH`
+///   bytes, or `limit` is 0
+#[utoipa::path(
+    tag = "airports",
+    summary = "Suggest airports by prefix",
+    description = "Returns airports whose ICAO code or a name word starts with `q`.",
+    params(AutocompleteParams),
+    responses(
+        (status = 200, description = "Suggested airports", body = Vec<Airport>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/autocomplete")]
+pub async fn autocomplete_airports(
+    data: web::Data<AppState>,
+    query: web::Query<AutocompleteParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let prefix = fold_text(query.q.trim());
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUTOCOMPLETE_LIMIT)
+        .min(data.page_limits.max);
+
+    let dataset = data.snapshot();
+    let suggestions: Vec<&Airport> = dataset
+        .prefixes
+        .lookup(&prefix, limit)
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+/// Handler for GET /airports/stream endpoint exporting the dataset as JSON Lines
+///
+/// # Parameters
+/// - `data`: Application state with airport list
+///
+/// # Behavior
+/// - Streams one JSON-encoded `Airport` per line, `STREAM_CHUNK_SIZE` airports per chunk
+/// - Not subject to the maximum page size; intended for bulk export
+/// - Holds an `Arc` to the dataset snapshot taken at request start rather than any
+///   lock, so reloads proceed immediately and the old snapshot is freed once the
+///   stream finishes
+///
+/// # Response
+/// - `application/x-ndjson` streaming body
+#[utoipa::path(
+    tag = "airports",
+    summary = "Export every airport",
+    description = "Streams the whole dataset as JSON Lines, one airport per line.",
+    responses(
+        (status = 200, description = "One JSON-encoded airport per line", body = Airport,
+         content_type = "application/x-ndjson"),
+    )
+)]
+#[get("/airports/stream")]
+pub async fn stream_airports(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let dataset = data.snapshot();
+    let total = dataset.airports.len();
+
+    let chunks =
+        futures_util::stream::iter((0..total).step_by(STREAM_CHUNK_SIZE).map(move |start| {
+            let end = (start + STREAM_CHUNK_SIZE).min(total);
+            let mut chunk = Vec::new();
+            for airport in &dataset.airports[start..end] {
+                serde_json::to_writer(&mut chunk, airport).map_err(|_| ApiError::InternalError)?;
+                chunk.push(b'\n');
+            }
+            Ok::<_, ApiError>(web::Bytes::from(chunk))
+        }));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(chunks))
+}
+
+/// Handler for GET /airports/nearest endpoint ranking airports by distance
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Reference point coordinates, distance unit, and pagination parameters
+///
+/// # Behavior
+/// - Walks the dataset's `SpatialIndex` for the `offset + limit` nearest
+///   airports instead of measuring every one, ties in file order, off the
+///   worker thread via `offload_search`; `offset` counts at most every
+///   indexed airport
+/// - Excludes airports without coordinates from the ranking and the total
+/// - Computes distance and initial bearing for the returned page only
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of airports with `distance_km`, plus
+///   `distance` in the requested `unit` and `bearing_deg` from the point
+/// - `ApiError::BadRequest` (400) if the coordinates are out of range, `unit`
+///   is not a known value, or `limit` is 0
+#[cfg(feature = "spatial")]
+#[utoipa::path(
+    tag = "airports",
+    summary = "Find the nearest airports",
+    description = "Ranks airports with coordinates by great-circle distance from `lat`/`lon`.",
+    params(NearestParams),
+    responses(
+        (status = 200, description = "Page of airports with `distance_km`",
+         body = PaginatedResponse<AirportDistance>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/nearest")]
+pub async fn nearest_airports(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<NearestParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let origin = validate_point(query.lat, query.lon)?;
+    let unit = parse_unit(query.unit.as_deref())?;
+
+    let indexed = dataset.spatial.len();
+    let window = query
+        .offset
+        .unwrap_or(0)
+        .min(indexed)
+        .saturating_add(data.page_limits.resolve(query.limit));
+    let ranked = offload_search(&data, &dataset, move |dataset| {
+        dataset.spatial.nearest(origin, window)
+    })
+    .await?;
+
+    // Every indexed airport has coordinates, so measuring just the page never
+    // drops an item
+    let page = paginate_head(
+        &ranked,
+        indexed,
+        query.offset,
+        query.limit,
+        data.page_limits,
+    );
+    let measured: Vec<AirportDistance> = page
+        .data
+        .iter()
+        .filter_map(|&index| AirportDistance::measure(&dataset.airports[index], origin, unit))
+        .collect();
+    Ok(page_headers(
+        &req,
+        page.position(),
+        HttpResponse::Ok().json(page.with_data(&measured)),
+    ))
+}
+
+/// Handler for GET /airports/radius endpoint listing airports within a distance
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Circle center and radius, distance unit, and pagination parameters
+///
+/// # Behavior
+/// - Walks the dataset's `SpatialIndex`, skipping subtrees that lie wholly
+///   outside the circle, for the airports within `radius_km`
+/// - Excludes airports without coordinates
+/// - Sorts by ascending distance, ties in file order, before applying pagination
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of airports with `distance_km`, plus
+///   `distance` in the requested `unit` and `bearing_deg` from the center
+/// - `ApiError::BadRequest` (400) if the coordinates are out of range, `radius_km`
+///   is negative or not finite, `unit` is not a known value, or `limit` is 0
+#[cfg(feature = "spatial")]
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports within a radius",
+    description = "Returns airports within `radius_km` of `lat`/`lon`, nearest first.",
+    params(RadiusParams),
+    responses(
+        (status = 200, description = "Page of airports with `distance_km`",
+         body = PaginatedResponse<AirportDistance>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/radius")]
+pub async fn airports_in_radius(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<RadiusParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let center = validate_point(query.lat, query.lon)?;
+    if !query.radius_km.is_finite() || query.radius_km < 0.0 {
+        return Err(ApiError::BadRequest(
+            "radius_km must be a non-negative number".into(),
+        ));
+    }
+    let unit = parse_unit(query.unit.as_deref())?;
+
+    let radius_km = query.radius_km;
+    let found = offload_search(&data, &dataset, move |dataset| {
+        dataset.spatial.within(center, radius_km)
+    })
+    .await?;
+
+    // The spatial index holds only airports with coordinates, so measuring
+    // just the page never drops an item
+    let page = paginate(&found, query.offset, query.limit, data.page_limits);
+    let measured: Vec<AirportDistance> = page
+        .data
+        .iter()
+        .filter_map(|&index| AirportDistance::measure(&dataset.airports[index], center, unit))
+        .collect();
+    Ok(page_headers(
+        &req,
+        page.position(),
+        HttpResponse::Ok().json(page.with_data(&measured)),
+    ))
+}
+
+/// Parses an optional `unit` query value, defaulting to kilometers
+fn parse_unit(unit: Option<&str>) -> Result<DistanceUnit, ApiError> {
+    Ok(unit
+        .map(DistanceUnit::from_str)
+        .transpose()?
+        .unwrap_or_default())
+}
+
+/// Handler for GET /airports/distance endpoint measuring between two airports
+///
+/// # Parameters
+/// - `data`: Application state with airport list and ICAO index
+/// - `query`: ICAO codes of both airports and the distance unit
+///
+/// # Behavior
+/// - Resolves both codes through the ICAO index, case-insensitively
+/// - Computes the haversine distance between their coordinates, reported in
+///   kilometers and in the requested `unit`
+///
+/// # Response
+/// - JSON-encoded AirportPairDistance with both airports
+/// - `ApiError::NotFound` (404) if either code is unknown
+/// - `ApiError::BadRequest` (400) if either airport lacks coordinates or `unit`
+///   is not a known value
+#[utoipa::path(
+    tag = "airports",
+    summary = "Measure the distance between two airports",
+    description = "Returns the great-circle distance between the airports with ICAO codes `from` and `to`.",
+    params(DistanceParams),
+    responses(
+        (status = 200, description = "Both airports and the distance between them",
+         body = AirportPairDistance),
+        (status = 400, description = "An airport has no coordinates, or the unit is unknown",
+         body = ErrorBody),
+        (status = 404, description = "No airport has one of the codes", body = ErrorBody),
+    )
+)]
+#[get("/airports/distance")]
+pub async fn airport_distance(
+    data: web::Data<AppState>,
+    query: web::Query<DistanceParams>,
+) -> Result<HttpResponse, ApiError> {
+    let unit = parse_unit(query.unit.as_deref())?;
+    let dataset = data.snapshot();
+    let locate = |code: &str| {
+        let airport = dataset
+            .find_by_icao(code)
+            .ok_or_else(|| ApiError::NotFound(format!("airport {}", code.trim())))?;
+        match (airport.latitude, airport.longitude) {
+            (Some(lat), Some(lon)) => Ok((airport, (lat, lon))),
+            _ => Err(ApiError::BadRequest(format!(
+                "airport {} has no coordinates",
+                airport.icao
+            ))),
+        }
+    };
+    let (from, from_point) = locate(&query.from)?;
+    let (to, to_point) = locate(&query.to)?;
+
+    let distance_km = haversine_km(from_point, to_point);
+    Ok(HttpResponse::Ok().json(AirportPairDistance {
+        from,
+        to,
+        distance_km,
+        distance: unit.from_km(distance_km),
+        unit,
+    }))
+}
+
+/// Handler for GET /airports/within endpoint filtering airports by bounding box
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Box edges and pagination parameters
+///
+/// # Behavior
+/// - Filters airports with coordinates inside the box in parallel using Rayon
+/// - Boxes with `min_lon > max_lon` wrap around the antimeridian
+/// - Airports without coordinates are never included
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse containing matching airports
+/// - `ApiError::BadRequest` (400) if `min_lat > max_lat` or `limit` is 0
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports in a bounding box",
+    description = "Returns airports inside the box; `min_lon > max_lon` crosses the antimeridian.",
+    params(BoundingBoxParams),
+    responses(
+        (status = 200, description = "Page of airports", body = PaginatedResponse<Airport>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/within")]
+pub async fn airports_within(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<BoundingBoxParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    if query.min_lat > query.max_lat {
+        return Err(ApiError::BadRequest(
+            "min_lat must not be greater than max_lat".into(),
+        ));
+    }
+
+    let bounds = query.bounds();
+    let filtered: Vec<usize> = offload_search(&data, &dataset, move |dataset| {
+        dataset
+            .airports
+            .par_iter()
+            .enumerate()
+            .filter(|(_, airport)| match (airport.latitude, airport.longitude) {
+                (Some(lat), Some(lon)) => bounds.contains(lat, lon),
+                _ => false,
+            })
+            .map(|(index, _)| index)
+            .collect()
+    })
+    .await?;
+    let filtered: Vec<&Airport> = filtered
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+
+    let response = paginate(&filtered, query.offset, query.limit, data.page_limits);
+    Ok(page_headers(
+        &req,
+        response.position(),
+        HttpResponse::Ok().json(response),
+    ))
+}
+
+/// Handler for GET /airports/geohash/{prefix} endpoint listing airports in a
+/// geohash cell
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `path`: Geohash prefix from the URL path (case-insensitive); each extra
+///   character narrows the cell about 32-fold
+/// - `query`: Pagination parameters
+///
+/// # Behavior
+/// - Binary-searches the dataset's `GeohashIndex` for the run of airports
+///   whose geohash starts with the prefix, without scanning the dataset
+/// - Orders results by geohash, so neighbouring airports stay together across
+///   pages, ties in file order
+/// - Airports without coordinates have no geohash and are never included
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse containing matching airports, empty when
+///   the cell holds none
+/// - `ApiError::BadRequest` (400) if the prefix is empty, longer than
+///   `GEOHASH_PRECISION`, or contains a character outside the geohash alphabet,
+///   or if `limit` is 0
+#[cfg(feature = "spatial")]
+#[utoipa::path(
+    tag = "airports",
+    summary = "List airports in a geohash cell",
+    description = "Returns airports whose geohash starts with `prefix`, in geohash order.",
+    params(
+        ("prefix" = String, Path, description = "Geohash prefix, 1 to 12 characters, case-insensitive",
+         example = "gcpv"),
+        GeohashParams,
+    ),
+    responses(
+        (status = 200, description = "Page of airports", body = PaginatedResponse<Airport>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid geohash prefix or query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/geohash/{prefix}")]
+pub async fn airports_by_geohash(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<GeohashParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let prefix = path.trim().to_ascii_lowercase();
+    if prefix.is_empty()
+        || prefix.len() > GEOHASH_PRECISION
+        || !prefix.chars().all(|c| GEOHASH_ALPHABET.contains(c))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "invalid geohash prefix '{}', expected 1 to {GEOHASH_PRECISION} of the characters \
+             {GEOHASH_ALPHABET}",
+            path.trim()
+        )));
+    }
+
+    let dataset = data.snapshot();
+    let matches: Vec<&Airport> = dataset
+        .geohashes
+        .prefixed(&dataset.airports, &prefix)
+        .iter()
+        .map(|&index| &dataset.airports[index as usize])
+        .collect();
+
+    let response = paginate(&matches, query.offset, query.limit, data.page_limits);
+    Ok(page_headers(
+        &req,
+        response.position(),
+        HttpResponse::Ok().json(response),
+    ))
+}
+
+/// Handler for GET /airports/fuzzy endpoint with typo-tolerant ranking
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Search query, distance threshold, and pagination parameters
+///
+/// # Behavior
+/// - Shortlists candidates with the dataset's `TrigramIndex`, computing edit
+///   distances only for airports sharing enough trigrams with the query
+/// - Ranks airports with `fuzzy_search`: the smallest Levenshtein distance
+///   between the query and the lowercase ICAO code, full name, or any single
+///   name word, dropping those beyond `max_distance`
+/// - Sorts by ascending distance, breaking ties by name for deterministic output
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of airports with their `distance`
+/// - `ApiError::BadRequest` (400) if `q` is longer than `MAX_QUERY_LENGTH` bytes
+///   or `limit` is 0
+#[cfg(feature = "fuzzy")]
+#[utoipa::path(
+    tag = "airports",
+    summary = "Typo-tolerant airport search",
+    description = "Ranks airports by Levenshtein distance between `q` and their ICAO code, name, or \
+                   name words.",
+    params(FuzzyParams),
+    responses(
+        (status = 200, description = "Page of airports with `distance`",
+         body = PaginatedResponse<FuzzyMatch>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/fuzzy")]
+pub async fn fuzzy_search_airports(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<FuzzyParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let max_distance = query.max_distance.unwrap_or(DEFAULT_FUZZY_DISTANCE);
+    let text = query.q.clone();
+    let ranked = offload_search(&data, &dataset, move |dataset| {
+        fuzzy_rank_indexed(&dataset.airports, &dataset.trigrams, &text, max_distance)
+    })
+    .await?;
+
+    let page = paginate(&ranked, query.offset, query.limit, data.page_limits);
+    let matches = fuzzy_matches(&dataset.airports, page.data);
+    Ok(page_headers(
+        &req,
+        page.position(),
+        HttpResponse::Ok().json(page.with_data(&matches)),
+    ))
+}
+
+/// Handler for GET /countries endpoint aggregating airports per country
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Pagination parameters from URL query string
+///
+/// # Behavior
+/// - Counts airports per `country` with a parallel fold/reduce on every request
+/// - Sorts by descending count, then by country code
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of `{country, count}` objects
+/// - `ApiError::BadRequest` (400) if `limit` is 0
+#[utoipa::path(
+    tag = "aggregates",
+    summary = "Count airports per country",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Countries by descending airport count",
+         body = PaginatedResponse<CountryCount>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/countries")]
+pub async fn get_countries(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let countries: Vec<CountryCount> = count_by(&dataset.airports, |airport| &airport.country)
+        .into_iter()
+        .map(|(country, count)| CountryCount { country, count })
+        .collect();
+
+    let response = paginate(&countries, query.offset, query.limit, data.page_limits);
+    Ok(page_headers(
+        &req,
+        response.position(),
+        HttpResponse::Ok().json(response),
+    ))
+}
+
+/// Handler for GET /continents endpoint aggregating airports per continent
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with airport list
+/// - `query`: Pagination parameters from URL query string
+///
+/// # Behavior
+/// - Counts airports per `continent` with a parallel fold/reduce on every request
+/// - Sorts by descending count, then by continent code
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of `{continent, count}` objects
+/// - `ApiError::BadRequest` (400) if `limit` is 0
+#[utoipa::path(
+    tag = "aggregates",
+    summary = "Count airports per continent",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Continents by descending airport count",
+         body = PaginatedResponse<ContinentCount>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/continents")]
+pub async fn get_continents(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let continents: Vec<ContinentCount> = count_by(&dataset.airports, |airport| &airport.continent)
+        .into_iter()
+        .map(|(continent, count)| ContinentCount { continent, count })
+        .collect();
+
+    let response = paginate(&continents, query.offset, query.limit, data.page_limits);
+    Ok(page_headers(
+        &req,
+        response.position(),
+        HttpResponse::Ok().json(response),
+    ))
+}
+
+/// Handler for GET /types endpoint listing the facility types in the dataset
+///
+/// # Parameters
+/// - `req`: Incoming request, whose path and query string the `Link` header reuses
+/// - `data`: Application state with the current dataset
+/// - `query`: Pagination parameters from URL query string
+///
+/// # Behavior
+/// - Serves the dataset's `type_counts`, counted when it was loaded, so every
+///   type present appears, including ones outside the OurAirports set
+/// - Sorted by descending count, then by type
+///
+/// # Response
+/// - JSON-encoded PaginatedResponse of `{type, count}` objects
+/// - `ApiError::BadRequest` (400) if `limit` is 0
+#[utoipa::path(
+    tag = "aggregates",
+    summary = "Count airports per facility type",
+    description = "Lists every distinct `type` in the dataset with its airport count, for building \
+                   type filters.",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Types by descending airport count",
+         body = PaginatedResponse<TypeCount>,
+         headers(("Link" = String, description = "`next` and `prev` page URLs, where those pages exist"),
+                 ("X-Total-Count" = usize, description = "Number of items across all pages"))),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/types")]
+pub async fn get_types(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let types: Vec<TypeCount> = dataset
+        .type_counts
+        .iter()
+        .map(|(kind, count)| TypeCount {
+            kind,
+            count: *count,
+        })
+        .collect();
+
+    let response = paginate(&types, query.offset, query.limit, data.page_limits);
+    Ok(page_headers(
+        &req,
+        response.position(),
+        HttpResponse::Ok().json(response),
+    ))
+}
+
+/// Handler for GET /stats endpoint summarizing the whole dataset
+///
+/// # Parameters
+/// - `data`: Application state with the current dataset
+///
+/// # Behavior
+/// - Serves the `DatasetStats` computed when the dataset was loaded, without
+///   scanning the airports; a reload recomputes them
+///
+/// # Response
+/// - JSON-encoded DatasetStats
+#[utoipa::path(
+    tag = "aggregates",
+    summary = "Summarize the dataset",
+    description = "Returns the airport total, counts per type and continent, how many airports have coordinates \
+                   and scheduled service, and when the data was loaded.",
+    responses(
+        (status = 200, description = "Dataset summary", body = DatasetStats),
+    )
+)]
+#[get("/stats")]
+pub async fn get_stats(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(&data.snapshot().stats))
+}
+
+/// Handler for GET /version endpoint identifying the build and dataset
+///
+/// # Parameters
+/// - `data`: Application state with the current dataset
+///
+/// # Behavior
+/// - The version is fixed at compile time from the crate manifest, the start
+///   time when the server starts, and the hash and count when the dataset is
+///   loaded; a reload changes the latter two
+///
+/// # Response
+/// - JSON-encoded VersionInfo
+#[utoipa::path(
+    tag = "operations",
+    summary = "Identify the build and dataset",
+    description = "Returns the server version, the dataset hash (matching the `ETag`), the number of airports \
+                   loaded, when the server started, and the size of the search thread pool.",
+    responses(
+        (status = 200, description = "Build and dataset identification", body = VersionInfo),
+    )
+)]
+#[get("/version")]
+pub async fn get_version(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let dataset = data.snapshot();
+    Ok(HttpResponse::Ok().json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        dataset_hash: dataset_etag(dataset.version).tag().to_string(),
+        airports: dataset.airports.len(),
+        started_at: data.started_at,
+        search_threads: data.search_threads(),
+    }))
+}
+
+/// Batch lookup results, serialized as a JSON object from each requested code
+/// to its airport, or `null` if none has that code, in request order
+#[derive(Debug)]
+pub struct BatchLookup<'a>(pub Vec<(&'a str, Option<&'a Airport>)>);
+
+impl Serialize for BatchLookup<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().copied())
+    }
+}
+
+/// Handler for POST /airports/batch endpoint resolving many ICAO codes at once
+///
+/// # Parameters
+/// - `data`: Application state with airport list and ICAO index
+/// - `codes`: JSON array of ICAO codes from the request body
+///
+/// # Behavior
+/// - Resolves each code through the ICAO index in O(1), case-insensitively and
+///   ignoring surrounding whitespace
+/// - Keys the result by the trimmed code as sent; repeated codes appear once
+/// - Emits keys in request order (by first occurrence), never dataset order, so
+///   clients can zip the result with their input
+///
+/// # Response
+/// - JSON object mapping each code to its Airport, or `null` if not found
+/// - `ApiError::BadRequest` (400) if the body is not a JSON array of strings or
+///   holds more than `MAX_BATCH_SIZE` codes
+#[utoipa::path(
+    tag = "airports",
+    summary = "Look up many airports by ICAO code",
+    description = "Resolves up to 200 ICAO codes in one request. Unknown codes map to `null`. \
+                   Keys appear in the order the codes were first requested.",
+    request_body(content = Vec<String>, example = json!(["KJFK", "EGLL", "ZZZZ"])),
+    responses(
+        (status = 200, description = "Each requested code mapped to its airport or `null`",
+         body = std::collections::HashMap<String, Airport>),
+        (status = 400, description = "Malformed body or too many codes", body = ErrorBody),
+    )
+)]
+#[post("/airports/batch")]
+pub async fn batch_airports(
+    data: web::Data<AppState>,
+    codes: web::Json<Vec<String>>,
+) -> Result<HttpResponse, ApiError> {
+    if codes.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "at most {MAX_BATCH_SIZE} codes are allowed per batch, got {}",
+            codes.len()
+        )));
+    }
+    let dataset = data.snapshot();
+    let mut seen = HashSet::new();
+    let results = codes
+        .iter()
+        .map(|code| code.trim())
+        .filter(|code| seen.insert(*code))
+        .map(|code| (code, dataset.find_by_icao(code)))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(BatchLookup(results)))
+}
+
+/// Randomly selected airports together with the seed that selected them
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RandomSample<'a> {
+    /// Seed to pass as `seed` to repeat this selection
+    pub seed: u64,
+    /// Selected airports, in random order
+    pub data: Vec<&'a Airport>,
+}
+
+/// Handler for GET /airports/random endpoint sampling airports without replacement
+///
+/// # Parameters
+/// - `data`: Application state with airport list
+/// - `query`: Sample size and optional seed
+///
+/// # Behavior
+/// - Draws `count` distinct airports (default 1, clamped to the maximum page
+///   size and the dataset size) with a `SplitMix64` generator seeded by `seed`
+/// - Without `seed`, a fresh random seed is drawn and echoed in the response
+/// - The same seed yields the same airports for as long as the dataset is unchanged
+///
+/// # Response
+/// - JSON-encoded RandomSample
+/// - `ApiError::BadRequest` (400) if `count` is 0
+#[utoipa::path(
+    tag = "airports",
+    summary = "Sample random airports",
+    description = "Returns `count` distinct airports chosen at random. Passing the returned `seed` back \
+                   repeats the selection while the dataset is unchanged.",
+    params(RandomParams),
+    responses(
+        (status = 200, description = "Randomly selected airports", body = RandomSample),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/airports/random")]
+pub async fn random_airports(
+    data: web::Data<AppState>,
+    query: web::Query<RandomParams>,
+) -> Result<HttpResponse, ApiError> {
+    query.validate()?;
+    let dataset = data.snapshot();
+    let count = query.count.unwrap_or(1).min(data.page_limits.max);
+    let seed = query.seed.unwrap_or_else(random_seed);
+
+    let airports = sample_indices(dataset.airports.len(), count, seed)
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+    Ok(HttpResponse::Ok().json(RandomSample {
+        seed,
+        data: airports,
+    }))
+}
+
+/// Handler for GET /airports/{icao} endpoint returning a single airport
+///
+/// # Parameters
+/// - `data`: Application state with airport list and ICAO index
+/// - `path`: ICAO code from the URL path (case-insensitive, surrounding whitespace ignored)
+/// - `query`: Optional `select` list of fields to return
+///
+/// # Response
+/// - JSON-encoded Airport on a match, limited to the `select` fields if given
+/// - `ApiError::NotFound` (404) if no airport has the given ICAO code
+/// - `ApiError::BadRequest` (400) if `select` names an unknown field
+///
+/// # Routing
+/// Must be registered after the other `/airports/...` services so that literal
+/// segments such as `search` are not captured as an ICAO code.
+#[utoipa::path(
+    tag = "airports",
+    summary = "Look up an airport by ICAO code",
+    params(
+        ("icao" = String, Path, description = "ICAO code, case-insensitive", example = "KJFK"),
+        SelectParams,
+    ),
+    responses(
+        (status = 200, description = "The airport", body = Airport),
+        (status = 400, description = "Unknown `select` field", body = ErrorBody),
+        (status = 404, description = "No matching airport", body = ErrorBody),
+    )
+)]
+#[get("/airports/{icao}")]
+pub async fn get_airport_by_icao(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<SelectParams>,
+) -> Result<HttpResponse, ApiError> {
+    let selection = parse_selection(query.select.as_deref())?;
+    let dataset = data.snapshot();
+    let airport = dataset
+        .find_by_icao(&path)
+        .ok_or_else(|| ApiError::NotFound(format!("airport {}", path.trim())))?;
+
+    Ok(match selection {
+        Some(selection) => HttpResponse::Ok().json(selection.project(airport)),
+        None => HttpResponse::Ok().json(airport),
+    })
+}
+
+/// Handler for GET /airports/{icao}/timezone endpoint returning an airport's timezone
+///
+/// # Parameters
+/// - `data`: Application state with airport list and ICAO index
+/// - `path`: ICAO code from the URL path (case-insensitive, surrounding whitespace ignored)
+///
+/// # Response
+/// - JSON-encoded AirportTimezone with the airport's `timezone`, resolved at
+///   load time
+/// - `ApiError::NotFound` (404) if no airport has the given ICAO code
+#[cfg(feature = "timezone")]
+#[utoipa::path(
+    tag = "airports",
+    summary = "Look up an airport's timezone",
+    params(("icao" = String, Path, description = "ICAO code, case-insensitive", example = "KJFK")),
+    responses(
+        (status = 200, description = "The airport's IANA timezone", body = AirportTimezone),
+        (status = 404, description = "No matching airport", body = ErrorBody),
+    )
+)]
+#[get("/airports/{icao}/timezone")]
+pub async fn get_airport_timezone(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let dataset = data.snapshot();
+    let airport = dataset
+        .find_by_icao(&path)
+        .ok_or_else(|| ApiError::NotFound(format!("airport {}", path.trim())))?;
+
+    Ok(HttpResponse::Ok().json(AirportTimezone {
+        icao: &airport.icao,
+        timezone: airport.timezone.as_deref(),
+    }))
+}
+
+/// Handler for GET /airports/iata/{code} endpoint resolving an IATA code
+///
+/// # Parameters
+/// - `data`: Application state with airport list
+/// - `path`: IATA code from the URL path (case-insensitive, surrounding whitespace ignored)
+///
+/// # Response
+/// - JSON-encoded Airport when exactly one airport has the code
+/// - JSON array of Airports when several records share the code
+/// - `ApiError::NotFound` (404) if no airport has the code
+#[utoipa::path(
+    tag = "airports",
+    summary = "Look up airports by IATA code",
+    description = "Returns a single airport, or an array when several records share the code.",
+    params(("code" = String, Path, description = "IATA code, case-insensitive", example = "JFK")),
+    responses(
+        (status = 200, description = "The airport, or every airport with the code", body = Airport),
+        (status = 404, description = "No matching airport", body = ErrorBody),
+    )
+)]
+#[get("/airports/iata/{code}")]
+pub async fn get_airport_by_iata(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let dataset = data.snapshot();
+    let code = path.trim().to_lowercase();
+
+    let matches: Vec<usize> = offload_search(&data, &dataset, move |dataset| {
+        dataset
+            .airports
+            .par_iter()
+            .enumerate()
+            .filter(|(_, airport)| airport.lower_iata.as_deref() == Some(code.as_str()))
+            .map(|(index, _)| index)
+            .collect()
+    })
+    .await?;
+    let matches: Vec<&Airport> = matches
+        .into_iter()
+        .map(|index| &dataset.airports[index])
+        .collect();
+
+    match matches.as_slice() {
+        [] => Err(ApiError::NotFound(format!("IATA code {}", path.trim()))),
+        [airport] => Ok(HttpResponse::Ok().json(airport)),
+        _ => Ok(HttpResponse::Ok().json(matches)),
+    }
+}
+
+/// Handler for GET /metrics endpoint in Prometheus text exposition format
+///
+/// # Parameters
+/// - `data`: Application state holding the metrics registry
+///
+/// # Response
+/// - `text/plain; version=0.0.4` body with all registered metrics
+/// - 500 if encoding fails
+#[cfg(feature = "metrics")]
+#[utoipa::path(
+    tag = "operations",
+    summary = "Prometheus metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String,
+         content_type = "text/plain"),
+    )
+)]
+#[get("/metrics")]
+pub async fn get_metrics(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let encoder = prometheus::TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&data.metrics.registry.gather(), &mut body)
+        .map_err(|_| ApiError::InternalError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(body))
+}
+
+/// Handler for GET /health liveness probe
+///
+/// # Response
+/// - Always 200 with `{"status":"ok"}`; does not touch the dataset
+#[utoipa::path(
+    tag = "operations",
+    summary = "Liveness probe",
+    responses((status = 200, description = "The process is up")),
+)]
+#[get("/health")]
+pub async fn health() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "ok" })))
+}
+
+/// Handler for GET /ready readiness probe
+///
+/// # Parameters
+/// - `data`: Application state with the current dataset
+///
+/// # Response
+/// - 200 with `{"status":"ready"}` once airports are loaded
+/// - 503 with `{"status":"unavailable"}` while the dataset is empty
+#[utoipa::path(
+    tag = "operations",
+    summary = "Readiness probe",
+    responses(
+        (status = 200, description = "Airports are loaded"),
+        (status = 503, description = "The dataset is empty"),
+    )
+)]
+#[get("/ready")]
+pub async fn ready(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    if data.dataset.load().airports.is_empty() {
+        Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({ "status": "unavailable" })))
+    } else {
+        Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "ready" })))
+    }
+}
+
+/// Handler for GET /openapi.json endpoint describing the API
+///
+/// # Response
+/// - JSON-encoded OpenAPI 3 document built from `ApiDoc`
+#[get("/openapi.json")]
+pub async fn get_openapi() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(ApiDoc::openapi()))
+}
+
+/// Handler for GET /docs endpoint serving interactive API documentation
+///
+/// # Response
+/// - Swagger UI page (`SWAGGER_UI_HTML`) rendering `/openapi.json`
+#[get("/docs")]
+pub async fn get_docs() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(SWAGGER_UI_HTML))
+}
+
+/// Checks the request's `Authorization: Bearer <token>` header against the
+/// configured admin token.
+///
+/// The comparison examines every byte so that response timing does not reveal
+/// how much of a guessed token was correct.
+pub fn is_authorized(req: &HttpRequest, expected: Option<&str>) -> bool {
+    let Some(expected) = expected else {
+        return false;
+    };
+    let Some(provided) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Handler for POST /admin/reload endpoint re-reading the airport CSV
+///
+/// # Parameters
+/// - `req`: Incoming request carrying the `Authorization` header
+/// - `data`: Application state with the dataset, CSV path, and admin token
+///
+/// # Behavior
+/// - Re-runs `load_airports` on a blocking thread and atomically swaps the dataset
+/// - On load failure the previous dataset keeps being served
+///
+/// # Response
+/// - JSON `{"airports": <count>}` with the new airport count
+/// - `ApiError::Unauthorized` (401) if the bearer token is missing or wrong,
+///   or if no admin token is configured
+/// - 500 if the CSV cannot be loaded
+#[utoipa::path(
+    tag = "operations",
+    summary = "Reload the airport CSV",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "New airport count, as `{\"airports\": <count>}`"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 500, description = "The CSV could not be loaded", body = ErrorBody),
+    )
+)]
+#[post("/admin/reload")]
+pub async fn admin_reload(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    if !is_authorized(&req, data.admin_token.as_deref()) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let state = data.clone();
+    let count = web::block(move || state.reload())
+        .await
+        .map_err(|_| ApiError::InternalError)??;
+    info!("Reloaded {} airports via /admin/reload", count);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "airports": count })))
+}
+
+/// Registers every endpoint on an Actix app or scope.
+///
+/// Query strings and JSON bodies that fail to deserialize (e.g., `limit=-1`)
+/// are reported as `ApiError::BadRequest`, so they get the same JSON error body
+/// as every other client error instead of Actix's plain-text default. Query
+/// errors name the offending parameter and the kind of value it expects.
+///
+/// # Routing
+/// `get_airport_by_icao` is registered after the other `/airports/...`
+/// services so that literal segments such as `search` are not captured as an
+/// ICAO code. Endpoints whose Cargo feature is disabled are registered first,
+/// answering every method with `ApiError::NotImplemented` (501).
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(
+        web::QueryConfig::default()
+            .error_handler(|err, req| query_error(&err, req.query_string()).into()),
+    )
+    .app_data(
+        web::JsonConfig::default()
+            .error_handler(|err, _req| ApiError::BadRequest(err.to_string()).into()),
+    );
+    for &(path, feature) in DISABLED_ENDPOINTS {
+        cfg.route(
+            path,
+            web::route().to(move || async move {
+                Err::<HttpResponse, _>(ApiError::NotImplemented(format!(
+                    "{path} requires the `{feature}` feature, which this server was built without"
+                )))
+            }),
+        );
+    }
+
+    cfg.service(get_airports)
+        .service(search_airports)
+        .service(search_airports_by_body)
+        .service(get_airports_geojson)
+        .service(get_airports_by_cursor)
+        .service(autocomplete_airports)
+        .service(stream_airports)
+        .service(airport_distance)
+        .service(airports_within);
+    #[cfg(feature = "spatial")]
+    cfg.service(nearest_airports)
+        .service(airports_in_radius)
+        .service(airports_by_geohash);
+    #[cfg(feature = "fuzzy")]
+    cfg.service(fuzzy_search_airports);
+    cfg.service(random_airports)
+        .service(batch_airports)
+        .service(get_airport_by_iata);
+    #[cfg(feature = "timezone")]
+    cfg.service(get_airport_timezone);
+    cfg.service(get_airport_by_icao)
+        .service(get_countries)
+        .service(get_continents)
+        .service(get_types)
+        .service(get_stats)
+        .service(get_version)
+        .service(admin_reload)
+        .service(health)
+        .service(ready);
+    #[cfg(feature = "metrics")]
+    cfg.service(get_metrics);
+    cfg.service(get_openapi).service(get_docs);
+}
+
+/// Route patterns of the endpoints compiled out with their Cargo feature,
+/// with the feature that provides each
+pub const DISABLED_ENDPOINTS: &[(&str, &str)] = &[
+    #[cfg(not(feature = "spatial"))]
+    ("/airports/nearest", "spatial"),
+    #[cfg(not(feature = "spatial"))]
+    ("/airports/radius", "spatial"),
+    #[cfg(not(feature = "spatial"))]
+    ("/airports/geohash/{prefix}", "spatial"),
+    #[cfg(not(feature = "fuzzy"))]
+    ("/airports/fuzzy", "fuzzy"),
+    #[cfg(not(feature = "timezone"))]
+    ("/airports/{icao}/timezone", "timezone"),
+    #[cfg(not(feature = "metrics"))]
+    ("/metrics", "metrics"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::LruCache;
+    use crate::model::CsvAirport;
+    use crate::params::MAX_QUERY_LENGTH;
+    use crate::test_support::{create_test_state, write_fixture, TestPaginatedResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    /// Tests basic airport listing without pagination parameters
+    #[actix_web::test]
+    async fn test_get_airports_no_pagination() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(get_airports)).await;
+        let req = test::TestRequest::get().uri("/airports").to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 3);
+        assert_eq!(resp.data.len(), 3);
+        assert!(!resp.has_more);
+        assert_eq!(resp.remaining, 0);
+    }
+
+    /// Tests pagination behavior with offset and limit parameters
+    #[actix_web::test]
+    async fn test_get_airports_with_pagination() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(get_airports)).await;
+        let req = test::TestRequest::get()
+            .uri("/airports?limit=2&offset=1")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 3);
+        assert_eq!(resp.data.len(), 2);
+        assert_eq!(resp.data[0].icao, "KLAX");
+        assert!(!resp.has_more);
+        assert_eq!(resp.remaining, 0);
+    }
+
+    /// Tests walking every page by cursor, with filters, and cursor rejection
+    #[actix_web::test]
+    async fn test_get_airports_by_cursor() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports_by_cursor),
+        )
+        .await;
+
+        let mut icaos = Vec::new();
+        let mut uri = "/airports/cursor?limit=2".to_string();
+        loop {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            for airport in resp["data"].as_array().unwrap() {
+                icaos.push(airport["icao"].as_str().unwrap().to_string());
+            }
+            match resp["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/airports/cursor?limit=2&cursor={cursor}"),
+                None => {
+                    assert_eq!(resp["has_more"], false);
+                    break;
+                }
+            }
+        }
+        assert_eq!(icaos, ["KJFK", "KLAX", "EGLL"]);
+
+        // The cursor resumes after KJFK, skipping non-matching airports
+        let uri = format!(
+            "/airports/cursor?country=gb&cursor={}",
+            encode_cursor("KJFK")
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["icao"], "EGLL");
+        assert!(resp["next_cursor"].is_null());
+
+        for cursor in ["%21%21".to_string(), encode_cursor("ZZZZ")] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/cursor?cursor={cursor}"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{cursor}");
+        }
+    }
+
+    /// Tests that zero and unparseable limits are rejected with a JSON 400
+    #[actix_web::test]
+    async fn test_pagination_validation() {
+        let state = create_test_state();
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+
+        for uri in [
+            "/airports?limit=0",
+            "/airports?limit=-1",
+            "/airports?offset=many",
+            "/airports/search?q=kj&limit=0",
+            "/airports/search?q=kj&limit=-5",
+            "/countries?limit=0",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(
+                body["error"].as_str().unwrap().starts_with("Bad request: "),
+                "{uri}"
+            );
+        }
+    }
+
+    /// Tests that query deserialization errors name the parameter and its type
+    #[actix_web::test]
+    async fn test_query_error_detail() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+
+        let cases = [
+            (
+                "/airports?limit=abc",
+                "invalid limit 'abc', expected a non-negative integer",
+            ),
+            (
+                "/airports?q=x&offset=-1",
+                "invalid offset '-1', expected a non-negative integer",
+            ),
+            (
+                "/airports?min_elevation=high",
+                "invalid min_elevation 'high', expected an integer",
+            ),
+            (
+                "/airports/search?q=kj&count=maybe",
+                "invalid count 'maybe', expected true or false",
+            ),
+            (
+                "/airports/random?count=true",
+                "invalid count 'true', expected a non-negative integer",
+            ),
+            (
+                "/airports/nearest?lat=north&lon=0",
+                "invalid lat 'north', expected a number",
+            ),
+            (
+                "/airports/nearest?lat=10",
+                "missing required parameter 'lon'",
+            ),
+        ];
+        // Without the `spatial` feature, /airports/nearest answers 501 instead
+        let cases = cases
+            .into_iter()
+            .filter(|(uri, _)| cfg!(feature = "spatial") || !uri.starts_with("/airports/nearest"));
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"], format!("Bad request: {expected}"), "{uri}");
+        }
+    }
+
+    /// Tests successful search operation with exact ICAO match
+    #[actix_web::test]
+    async fn test_search_airports() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=kjfk")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.data.len(), 1);
+        assert_eq!(resp.data[0].icao, "KJFK");
+        assert!(!resp.has_more);
+        assert_eq!(resp.remaining, 0);
+    }
+
+    /// Tests that the query is trimmed, and that blank and exclusion-only
+    /// queries are rejected
+    #[actix_web::test]
+    async fn test_search_airports_blank_query() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=%20kjfk%20")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.data[0].icao, "KJFK");
+
+        for uri in [
+            "/airports/search?q=",
+            "/airports/search?q=%20%09",
+            "/airports/search?q=-heliport",
+            "/airports/search?q=-kennedy%20-angeles",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests the configurable minimum query length, counted after trimming
+    #[actix_web::test]
+    async fn test_search_airports_min_query_length() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+        for uri in ["/airports/search?q=k", "/airports/search?q=%20k%20"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+
+        let mut state = AppState::new(state.snapshot().airports.clone(), "airports.csv");
+        state.min_query_length = 1;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(search_airports),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=k")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 2);
+    }
+
+    /// Tests that POST /airports/search matches the equivalent GET search, and
+    /// its array filters, elevation bounds, bounding box, and validation
+    #[actix_web::test]
+    async fn test_search_airports_by_body() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let post = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/airports/search")
+                .set_json(body)
+                .to_request()
+        };
+        let icaos = |resp: TestPaginatedResponse<Vec<Airport>>| -> Vec<String> {
+            resp.data.into_iter().map(|airport| airport.icao).collect()
+        };
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=international&country=us,gb&sort=icao&limit=1")
+            .to_request();
+        let expected = test::call_and_read_body(&app, req).await;
+        let req = post(serde_json::json!({
+            "q": "international",
+            "country": ["us", " GB "],
+            "sort": "icao",
+            "limit": 1,
+        }));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::LINK).is_none());
+        assert_eq!(test::read_body(resp).await, expected);
+
+        let req = post(serde_json::json!({
+            "q": "airport",
+            "bbox": {"min_lat": 35.0, "min_lon": -80.0, "max_lat": 45.0, "max_lon": -70.0},
+        }));
+        let resp = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(icaos(resp), ["KJFK"]);
+
+        let req = post(serde_json::json!({
+            "q": "airport",
+            "min_elevation": 50,
+            "type": ["large_airport"],
+            "sort": "icao",
+        }));
+        let resp = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(icaos(resp), ["EGLL", "KLAX"]);
+
+        let req = post(serde_json::json!({"q": "kjfk", "select": ["icao", "name"]}));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp["data"][0],
+            serde_json::json!({"icao": "KJFK", "name": "John F. Kennedy International Airport"})
+        );
+
+        for body in [
+            serde_json::json!({"q": " "}),
+            serde_json::json!({"q": "airport", "limit": 0}),
+            serde_json::json!({"q": "airport", "region": ["NEWYORK"]}),
+            serde_json::json!({"q": "airport", "min_elevation": 100, "max_elevation": 10}),
+            serde_json::json!({
+                "q": "airport",
+                "bbox": {"min_lat": 45.0, "min_lon": 0.0, "max_lat": 35.0, "max_lon": 1.0},
+            }),
+            serde_json::json!({"q": "airport", "countries": ["US"]}),
+            serde_json::json!({"q": "airport", "country": "US"}),
+        ] {
+            let resp = test::call_service(&app, post(body.clone())).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{body}");
+        }
+    }
+
+    /// Tests that overlong `q` values are rejected on every free-text endpoint
+    #[actix_web::test]
+    async fn test_query_length_limit() {
+        let state = create_test_state();
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+        let longest = "k".repeat(MAX_QUERY_LENGTH);
+        let overlong = "k".repeat(MAX_QUERY_LENGTH + 1);
+
+        let mut paths = vec!["/airports/search", "/airports/autocomplete"];
+        if cfg!(feature = "fuzzy") {
+            paths.push("/airports/fuzzy");
+        }
+        for path in paths {
+            let req = test::TestRequest::get()
+                .uri(&format!("{path}?q={longest}"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{path}");
+
+            let req = test::TestRequest::get()
+                .uri(&format!("{path}?q={overlong}"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{path}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(
+                body["error"].as_str().unwrap().contains("at most"),
+                "{path}"
+            );
+        }
+    }
+
+    /// Tests autocomplete ranking, limits, and validation
+    #[actix_web::test]
+    async fn test_autocomplete_airports() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(autocomplete_airports)
+                .service(get_airport_by_icao),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports/autocomplete?q=%20HEA")
+            .to_request();
+        let resp: Vec<Airport> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.len(), 1);
+        assert_eq!(resp[0].icao, "EGLL");
+
+        let req = test::TestRequest::get()
+            .uri("/airports/autocomplete?q=k&limit=1")
+            .to_request();
+        let resp: Vec<Airport> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.len(), 1);
+        assert_eq!(resp[0].icao, "KJFK");
+
+        for uri in [
+            "/airports/autocomplete?q=",
+            "/airports/autocomplete?q=k&limit=0",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests search behavior with non-matching query
+    #[actix_web::test]
+    async fn test_search_airports_no_match() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=XYZ")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 0);
+        assert_eq!(resp.data.len(), 0);
+        assert!(!resp.has_more);
+        assert_eq!(resp.remaining, 0);
+    }
+
+    /// Tests that `strict=true` turns an empty search into a 404, on both the
+    /// lazy and full paths and after the lenient result was cached
+    #[actix_web::test]
+    async fn test_search_airports_strict() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .service(search_airports),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let resp = test::call_service(&app, get("/airports/search?q=XYZ")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for uri in [
+            "/airports/search?q=XYZ&strict=true",
+            "/airports/search?q=XYZ&strict=true&count=true",
+        ] {
+            let resp = test::call_service(&app, get(uri)).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["error"], "Not found: no airports match 'XYZ'");
+        }
+
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, get("/airports/search?q=KJFK&strict=true")).await;
+        assert_eq!(resp.total, 1);
+        // Paging past the end of a non-empty result is not an error
+        let resp: TestPaginatedResponse<Vec<Airport>> = test::call_and_read_body_json(
+            &app,
+            get("/airports/search?q=KJFK&strict=true&offset=5"),
+        )
+        .await;
+        assert_eq!((resp.total, resp.data.len()), (1, 0));
+    }
+
+    /// Tests single airport lookup with mixed case and surrounding whitespace
+    #[actix_web::test]
+    async fn test_get_airport_by_icao() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(search_airports)
+                .service(get_airport_by_icao),
+        )
+        .await;
+
+        for uri in ["/airports/KJFK", "/airports/kjfk", "/airports/%20kJfK%20"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: Airport = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.icao, "KJFK");
+        }
+
+        // Literal routes registered earlier must not be shadowed
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=egll")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+    }
+
+    /// Tests single airport lookup for an unknown ICAO code
+    #[actix_web::test]
+    async fn test_get_airport_by_icao_not_found() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airport_by_icao),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/airports/ZZZZ").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Tests nearest-airport ranking, and exclusion of airports without coordinates
+    #[cfg(feature = "spatial")]
+    #[actix_web::test]
+    async fn test_nearest_airports() {
+        let state = create_test_state();
+        let mut airports = state.snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "XXXX".into(),
+            name: "Unknown Location".into(),
+            ..Default::default()
+        }));
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(nearest_airports)).await;
+
+        // Reference point in central London
+        let req = test::TestRequest::get()
+            .uri("/airports/nearest?lat=51.5&lon=-0.12")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 3);
+        let order: Vec<&str> = resp
+            .data
+            .iter()
+            .map(|a| a["icao"].as_str().unwrap())
+            .collect();
+        assert_eq!(order, ["EGLL", "KJFK", "KLAX"]);
+        assert!(resp.data[0]["distance_km"].as_f64().unwrap() < 30.0);
+        assert_eq!(resp.data[0]["unit"], "km");
+        // Heathrow lies west of central London, JFK further west across the Atlantic
+        let bearing = resp.data[0]["bearing_deg"].as_f64().unwrap();
+        assert!((180.0..360.0).contains(&bearing), "got {bearing}");
+        let bearing = resp.data[1]["bearing_deg"].as_f64().unwrap();
+        assert!((270.0..300.0).contains(&bearing), "got {bearing}");
+
+        let req = test::TestRequest::get()
+            .uri("/airports/nearest?lat=51.5&lon=-0.12&unit=mi")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        let nearest = &resp.data[0];
+        assert_eq!(nearest["unit"], "mi");
+        let miles = nearest["distance"].as_f64().unwrap();
+        let km = nearest["distance_km"].as_f64().unwrap();
+        assert!((miles * crate::geo::KM_PER_STATUTE_MILE - km).abs() < 1e-6);
+
+        // An offset past every indexed airport yields the empty last page
+        // rather than a window spanning the offset
+        let req = test::TestRequest::get()
+            .uri("/airports/nearest?lat=51.5&lon=-0.12&offset=100000000&limit=2")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!((resp.total, resp.has_more), (3, false));
+        assert!(resp.data.is_empty());
+
+        for uri in [
+            "/airports/nearest?lat=91&lon=0",
+            "/airports/nearest?lat=51.5&lon=-0.12&unit=KM",
+            "/airports/nearest?lat=51.5&lon=-0.12&limit=0",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests bounding-box filtering, including antimeridian wrapping and validation
+    #[actix_web::test]
+    async fn test_airports_within() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(airports_within)).await;
+
+        // Box around the British Isles
+        let req = test::TestRequest::get()
+            .uri("/airports/within?min_lat=49&min_lon=-11&max_lat=59&max_lon=2")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.data[0].icao, "EGLL");
+
+        // Wrapping box covering lon >= -1 and lon <= -100, which excludes KJFK
+        let req = test::TestRequest::get()
+            .uri("/airports/within?min_lat=-90&min_lon=-1&max_lat=90&max_lon=-100")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        let icaos: Vec<&str> = resp.data.iter().map(|a| a.icao.as_str()).collect();
+        assert_eq!(icaos, ["KLAX", "EGLL"]);
+
+        for uri in [
+            "/airports/within?min_lat=10&min_lon=0&max_lat=5&max_lon=1",
+            "/airports/within?min_lat=49&min_lon=-11&max_lat=59&max_lon=2&limit=0",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests geohash prefix lookups: the `geohash` field, cell sizes, case,
+    /// pagination, and prefix validation
+    #[cfg(feature = "spatial")]
+    #[actix_web::test]
+    async fn test_airports_by_geohash() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/airports/kjfk").to_request();
+        let resp: Airport = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.geohash.as_deref(), Some("dr5x1n5zgpbc"));
+
+        let icaos = |resp: &TestPaginatedResponse<Vec<Airport>>| -> Vec<String> {
+            resp.data.iter().map(|a| a.icao.clone()).collect()
+        };
+        for (prefix, expected) in [
+            ("d", vec!["KJFK"]),
+            ("DR5X1N", vec!["KJFK"]),
+            ("dr5x1n5zgpbc", vec!["KJFK"]),
+            ("9q5", vec!["KLAX"]),
+            ("gcp", vec!["EGLL"]),
+            ("zz", vec![]),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/geohash/{prefix}"))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(icaos(&resp), expected, "{prefix}");
+            assert_eq!(resp.total, expected.len());
+        }
+
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.extend(["KJFK", "KLAX"].map(|icao| Airport {
+            icao: format!("{icao}2"),
+            ..airports.iter().find(|a| a.icao == icao).unwrap().clone()
+        }));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::new(airports, "airports.csv")))
+                .configure(configure),
+        )
+        .await;
+        // Geohash order keeps each airport next to its copy, ties in file order
+        let req = test::TestRequest::get()
+            .uri("/airports/geohash/9?limit=2&offset=1")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 2);
+        assert_eq!(icaos(&resp), ["KLAX2"]);
+        let req = test::TestRequest::get()
+            .uri("/airports/geohash/dr5")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(icaos(&resp), ["KJFK", "KJFK2"]);
+
+        for prefix in ["dr5a", "dr5x1n5zgpbcx", "%20"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/geohash/{prefix}"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{prefix}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/geohash/d?limit=0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests timezone lookups: the `timezone` field, the focused endpoint,
+    /// airports without coordinates, and unknown codes
+    #[cfg(feature = "timezone")]
+    #[actix_web::test]
+    async fn test_get_airport_timezone() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "XNOC".into(),
+            name: "Nowhere".into(),
+            iso_country: "US".into(),
+            ..Default::default()
+        }));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::new(airports, "airports.csv")))
+                .configure(configure),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/airports/egll").to_request();
+        let resp: Airport = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.timezone.as_deref(), Some("Europe/London"));
+
+        for (icao, zone) in [
+            ("kjfk", Some("America/New_York")),
+            (" KLAX ", Some("America/Los_Angeles")),
+            ("XNOC", None),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/{}/timezone", icao.replace(' ', "%20")))
+                .to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp["icao"], icao.trim().to_uppercase(), "{icao}");
+            assert_eq!(resp["timezone"].as_str(), zone, "{icao}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/zzzz/timezone")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Tests radius searches: distance cutoff, ordering, pagination, and validation
+    #[cfg(feature = "spatial")]
+    #[actix_web::test]
+    async fn test_airports_in_radius() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let icaos = |resp: &TestPaginatedResponse<Vec<serde_json::Value>>| {
+            resp.data
+                .iter()
+                .map(|airport| airport["icao"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Central London: Heathrow is ~23 km away, JFK ~5,570 km
+        let req = test::TestRequest::get()
+            .uri("/airports/radius?lat=51.5074&lon=-0.1278&radius_km=50")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(icaos(&resp), ["EGLL"]);
+        let distance = resp.data[0]["distance_km"].as_f64().unwrap();
+        assert!(distance < 50.0, "got {distance}");
+        assert!(resp.data[0]["bearing_deg"].is_number());
+
+        let req = test::TestRequest::get()
+            .uri("/airports/radius?lat=51.5074&lon=-0.1278&radius_km=6000&unit=nm")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 2);
+        assert_eq!(icaos(&resp), ["EGLL", "KJFK"]);
+        assert_eq!(resp.data[1]["unit"], "nm");
+
+        let req = test::TestRequest::get()
+            .uri("/airports/radius?lat=51.5074&lon=-0.1278&radius_km=20000&limit=1&offset=2")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 3);
+        assert_eq!(icaos(&resp), ["KLAX"]);
+
+        let req = test::TestRequest::get()
+            .uri("/airports/radius?lat=0&lon=0&radius_km=0")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 0);
+
+        for uri in [
+            "/airports/radius?lat=0&lon=0&radius_km=-1",
+            "/airports/radius?lat=0&lon=0&radius_km=inf",
+            "/airports/radius?lat=91&lon=0&radius_km=10",
+            "/airports/radius?lat=0&lon=0&radius_km=10&unit=ft",
+            "/airports/radius?lat=0&lon=0&radius_km=10&limit=0",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests batch lookups: case-insensitive codes, nulls, duplicates, and the size cap
+    #[actix_web::test]
+    async fn test_batch_airports() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let batch = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/airports/batch")
+                .set_json(body)
+                .to_request()
+        };
+
+        let req = batch(serde_json::json!(["KJFK", " egll ", "ZZZZ", "KJFK"]));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let results = resp.as_object().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results["KJFK"]["name"],
+            "John F. Kennedy International Airport"
+        );
+        assert_eq!(results["egll"]["icao"], "EGLL");
+        assert!(results["ZZZZ"].is_null());
+
+        let req = batch(serde_json::json!([]));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp, serde_json::json!({}));
+
+        let too_many = vec!["KJFK"; MAX_BATCH_SIZE + 1];
+        for body in [
+            serde_json::json!(too_many),
+            serde_json::json!({ "codes": ["KJFK"] }),
+        ] {
+            let resp = test::call_service(&app, batch(body)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body["error"].is_string());
+        }
+    }
+
+    /// Tests `Link` headers on paged responses, including cached search pages
+    #[actix_web::test]
+    async fn test_link_headers() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let link = |resp: &actix_web::dev::ServiceResponse| {
+            resp.headers()
+                .get(header::LINK)
+                .map(|value| value.to_str().unwrap().to_owned())
+        };
+
+        let req = test::TestRequest::get()
+            .uri("/airports?offset=1&limit=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            link(&resp).as_deref(),
+            Some(
+                "</airports?offset=2&limit=1>; rel=\"next\", \
+                 </airports?offset=0&limit=1>; rel=\"prev\""
+            )
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/airports?limit=2&offset=2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            link(&resp).as_deref(),
+            Some("</airports?offset=0&limit=2>; rel=\"prev\"")
+        );
+
+        // Past the end, `prev` points at the last page holding airports
+        let req = test::TestRequest::get()
+            .uri("/airports?offset=10&limit=2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            link(&resp).as_deref(),
+            Some("</airports?offset=2&limit=2>; rel=\"prev\"")
+        );
+
+        let req = test::TestRequest::get().uri("/airports").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(link(&resp), None);
+
+        // The second request is served from the search cache
+        for _ in 0..2 {
+            let req = test::TestRequest::get()
+                .uri("/airports/search?q=international&limit=1")
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                link(&resp).as_deref(),
+                Some("</airports/search?q=international&offset=1&limit=1>; rel=\"next\"")
+            );
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/countries?limit=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(link(&resp).unwrap().contains("rel=\"next\""));
+    }
+
+    /// Tests `X-Total-Count` on list responses in every format, including
+    /// prerendered, cached, and body-search pages
+    #[actix_web::test]
+    async fn test_total_count_header() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let total = |resp: &actix_web::dev::ServiceResponse| {
+            resp.headers()
+                .get(TOTAL_COUNT_HEADER)
+                .map(|value| value.to_str().unwrap().to_owned())
+        };
+
+        for (uri, accept, expected) in [
+            ("/airports?limit=1", "application/json", "3"),
+            ("/airports?country=us&limit=1", "application/json", "2"),
+            ("/airports?country=us", "text/csv", "2"),
+            ("/airports?offset=10", "application/json", "3"),
+            ("/airports/search?q=international", "application/json", "2"),
+            // Served from the search cache
+            ("/airports/search?q=international", "application/json", "2"),
+            ("/airports/search?q=heathrow", "text/csv", "1"),
+            ("/airports/search?q=zzzz", "application/json", "0"),
+            ("/countries", "application/json", "2"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT, accept))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+            assert_eq!(total(&resp).as_deref(), Some(expected), "{uri} as {accept}");
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/airports/search")
+            .set_json(serde_json::json!({"q": "international", "limit": 1}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(total(&resp).as_deref(), Some("2"));
+
+        let req = test::TestRequest::get().uri("/airports/KJFK").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(total(&resp), None);
+    }
+
+    /// Tests that `/stats` serves the load-time summary and follows reloads
+    #[actix_web::test]
+    async fn test_get_stats() {
+        let state = create_test_state();
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["total"], 3);
+        assert_eq!(resp["by_type"], serde_json::json!({ "large_airport": 3 }));
+        assert_eq!(
+            resp["by_continent"],
+            serde_json::json!({ "EU": 1, "NA": 2 })
+        );
+        assert_eq!(resp["with_coordinates"], 3);
+        assert_eq!(resp["with_scheduled_service"], 3);
+        assert!(resp["loaded_at"].as_u64().unwrap() > 0);
+
+        let airports = state.snapshot().airports[..1].to_vec();
+        state
+            .dataset
+            .store(std::sync::Arc::new(Dataset::new(airports)));
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["total"], 1);
+        assert_eq!(resp["by_continent"], serde_json::json!({ "NA": 1 }));
+    }
+
+    /// Tests that `/version` reports the build, the ETag's dataset hash, and reloads
+    #[actix_web::test]
+    async fn test_get_version() {
+        use crate::middleware::conditional_get;
+        use actix_web::middleware::from_fn;
+
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(conditional_get))
+                .app_data(state.clone())
+                .configure(configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/airports").to_request();
+        let resp = test::call_service(&app, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
+        let etag = etag.to_string();
+
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(header::ETAG).is_none());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            etag,
+            format!("W/\"{}\"", body["dataset_hash"].as_str().unwrap())
+        );
+        assert_eq!(body["airports"], 3);
+        assert_eq!(body["started_at"], state.started_at);
+        assert_eq!(body["search_threads"], rayon::current_num_threads());
+
+        let airports = state.snapshot().airports[..1].to_vec();
+        state
+            .dataset
+            .store(std::sync::Arc::new(Dataset::new(airports)));
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["airports"], 1);
+        assert_ne!(resp["dataset_hash"], body["dataset_hash"]);
+        assert_eq!(resp["started_at"], body["started_at"]);
+    }
+
+    /// Tests that `highlight=true` adds matched ranges, alone or with `debug` scores
+    #[actix_web::test]
+    async fn test_search_airports_highlight() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let search = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let resp: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            search("/airports/search?q=kennedy&highlight=true"),
+        )
+        .await;
+        assert_eq!(
+            resp["data"][0]["highlight"],
+            serde_json::json!({ "name": [[8, 15]], "icao": [] })
+        );
+        assert!(resp["data"][0].get("score").is_none());
+
+        let resp: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            search("/airports/search?q=kjfk&highlight=true&debug=true"),
+        )
+        .await;
+        assert_eq!(
+            resp["data"][0]["highlight"]["icao"],
+            serde_json::json!([[0, 4]])
+        );
+        assert!(resp["data"][0]["score"].is_number());
+
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, search("/airports/search?q=kennedy")).await;
+        assert!(resp["data"][0].get("highlight").is_none());
+    }
+
+    /// Tests the distance between two airports and its 404 and 400 cases
+    #[actix_web::test]
+    async fn test_airport_distance() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "XNOC".into(),
+            name: "Uncharted Strip".into(),
+            ..Default::default()
+        }));
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(App::new().app_data(state).configure(configure)).await;
+        let distance = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/airports/distance?{query}"))
+                .to_request()
+        };
+
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, distance("from=kjfk&to=EGLL")).await;
+        assert_eq!(resp["from"]["icao"], "KJFK");
+        assert_eq!(resp["to"]["icao"], "EGLL");
+        let km = resp["distance_km"].as_f64().unwrap();
+        assert!((km - 5540.0).abs() < 10.0, "got {km}");
+        assert_eq!(resp["distance"], resp["distance_km"]);
+        assert_eq!(resp["unit"], "km");
+
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, distance("from=KJFK&to=EGLL&unit=nm")).await;
+        assert_eq!(resp["unit"], "nm");
+        let nm = resp["distance"].as_f64().unwrap();
+        assert!((nm * crate::geo::KM_PER_NAUTICAL_MILE - km).abs() < 1e-6);
+
+        for (query, status) in [
+            ("from=KJFK&to=ZZZZ", StatusCode::NOT_FOUND),
+            ("from=ZZZZ&to=KJFK", StatusCode::NOT_FOUND),
+            ("from=KJFK&to=XNOC", StatusCode::BAD_REQUEST),
+            ("from=KJFK", StatusCode::BAD_REQUEST),
+            ("from=KJFK&to=EGLL&unit=furlong", StatusCode::BAD_REQUEST),
+        ] {
+            let resp = test::call_service(&app, distance(query)).await;
+            assert_eq!(resp.status(), status, "{query}");
+        }
+    }
+
+    /// Tests that batch lookups answer in request order rather than dataset order
+    #[actix_web::test]
+    async fn test_batch_request_order() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+
+        // The fixture lists KJFK before KLAX
+        let req = test::TestRequest::get()
+            .uri("/airports?icaos=KLAX,KJFK")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["icao"], "KLAX");
+        assert_eq!(resp["data"][1]["icao"], "KJFK");
+
+        // Parsed JSON objects lose key order, so check the raw body
+        let req = test::TestRequest::post()
+            .uri("/airports/batch")
+            .set_json(["KLAX", "ZZZZ", "KJFK", "KLAX"])
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let position = |key: &str| body.find(&format!("\"{key}\":")).unwrap();
+        assert!(position("KLAX") < position("ZZZZ"));
+        assert!(position("ZZZZ") < position("KJFK"));
+    }
+
+    /// Tests that `icaos` returns the requested airports in order with misses reported
+    #[actix_web::test]
+    async fn test_get_airports_icaos() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports?icaos=EGLL,%20kjfk,,ZZZZ&limit=1")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let data = resp["data"].as_array().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0]["icao"], "EGLL");
+        assert_eq!(data[1]["icao"], "KJFK");
+        assert!(data[2].is_null());
+        assert_eq!(resp["errors"], serde_json::json!(["ZZZZ"]));
+        assert!(resp.get("total").is_none());
+
+        let too_many = vec!["KJFK"; MAX_BATCH_SIZE + 1].join(",");
+        let req = test::TestRequest::get()
+            .uri(&format!("/airports?icaos={too_many}"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests random sampling: count clamping, distinctness, and seed reproducibility
+    #[actix_web::test]
+    async fn test_random_airports() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(random_airports)
+                .service(get_airport_by_icao),
+        )
+        .await;
+        let sample = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, sample("/airports/random")).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+        assert!(resp["seed"].is_u64());
+
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, sample("/airports/random?count=500&seed=7")).await;
+        let mut icaos: Vec<&str> = resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|airport| airport["icao"].as_str().unwrap())
+            .collect();
+        assert_eq!(resp["seed"], 7);
+        let again: serde_json::Value =
+            test::call_and_read_body_json(&app, sample("/airports/random?count=500&seed=7")).await;
+        assert_eq!(again, resp);
+        icaos.sort_unstable();
+        assert_eq!(icaos, ["EGLL", "KJFK", "KLAX"]);
+
+        for uri in ["/airports/random?count=0", "/airports/random?seed=-1"] {
+            let resp = test::call_service(&app, sample(uri)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests fuzzy search ranking and the distance threshold
+    #[cfg(feature = "fuzzy")]
+    #[actix_web::test]
+    async fn test_fuzzy_search_airports() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(fuzzy_search_airports),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports/fuzzy?q=kenedy")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.data[0]["icao"], "KJFK");
+        assert_eq!(resp.data[0]["distance"], 1);
+
+        // "klax" vs "kjfk" is 3 edits; raising the threshold pulls it in second
+        let req = test::TestRequest::get()
+            .uri("/airports/fuzzy?q=klax&max_distance=3")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.data[0]["icao"], "KLAX");
+        assert_eq!(resp.data[0]["distance"], 0);
+        assert!(resp.total >= 2);
+
+        let req = test::TestRequest::get()
+            .uri("/airports/fuzzy?q=kenedy&limit=0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests each search sort order and rejection of unknown values
+    #[actix_web::test]
+    async fn test_search_airports_sort() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+
+        let cases = [
+            ("name", ["KJFK", "EGLL", "KLAX"]),
+            ("icao", ["EGLL", "KJFK", "KLAX"]),
+            // "airport" starts at 16 in Heathrow's name, 26 in LAX's, and 30 in JFK's
+            ("relevance", ["EGLL", "KLAX", "KJFK"]),
+        ];
+        for (sort, expected) in cases {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/search?q=ai&sort={sort}"))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            let icaos: Vec<&str> = resp.data.iter().map(|a| a.icao.as_str()).collect();
+            assert_eq!(icaos, expected, "sort={sort}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=kj&sort=size")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests searches measured from `lat`/`lon`: the default nearest-first
+    /// order, `distance_km`, airports without coordinates last or dropped, and
+    /// validation
+    #[actix_web::test]
+    async fn test_search_airports_by_distance() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.insert(
+            0,
+            Airport::from(CsvAirport {
+                ident: "XNOC".into(),
+                name: "Nowhere Airport".into(),
+                ..Default::default()
+            }),
+        );
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+        let search = |uri: String| {
+            let app = &app;
+            async move {
+                let req = test::TestRequest::get().uri(&uri).to_request();
+                let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+                    test::call_and_read_body_json(app, req).await;
+                resp
+            }
+        };
+        let icaos = |resp: &TestPaginatedResponse<Vec<serde_json::Value>>| -> Vec<String> {
+            resp.data
+                .iter()
+                .map(|airport| airport["icao"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        for count in ["false", "true"] {
+            let resp = search(format!(
+                "/airports/search?q=airport&lat=51.5&lon=-0.12&count={count}"
+            ))
+            .await;
+            assert_eq!(
+                icaos(&resp),
+                ["EGLL", "KJFK", "KLAX", "XNOC"],
+                "count={count}"
+            );
+            let heathrow = resp.data[0]["distance_km"].as_f64().unwrap();
+            assert!((heathrow - 24.0).abs() < 2.0, "{heathrow}");
+            assert!(resp.data[3].get("distance_km").is_none());
+        }
+
+        // A different origin is a different cache entry
+        let resp = search("/airports/search?q=airport&lat=34.0&lon=-118.0&limit=1".into()).await;
+        assert_eq!(icaos(&resp), ["KLAX"]);
+
+        let resp =
+            search("/airports/search?q=airport&lat=51.5&lon=-0.12&require_coordinates=true".into())
+                .await;
+        assert_eq!(resp.total, 3);
+        assert_eq!(icaos(&resp), ["EGLL", "KJFK", "KLAX"]);
+
+        // An explicit order still reports distances
+        let resp = search("/airports/search?q=airport&lat=51.5&lon=-0.12&sort=icao".into()).await;
+        assert_eq!(icaos(&resp), ["EGLL", "KJFK", "KLAX", "XNOC"]);
+        assert!(resp.data[1]["distance_km"].as_f64().unwrap() > 5000.0);
+
+        for uri in [
+            "/airports/search?q=airport&lat=51.5",
+            "/airports/search?q=airport&lon=-0.12",
+            "/airports/search?q=airport&lat=91&lon=0",
+            "/airports/search?q=airport&sort=distance",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests that exact code matches rank first and `debug` exposes scores
+    #[actix_web::test]
+    async fn test_search_airports_scoring() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.insert(
+            0,
+            Airport::from(CsvAirport {
+                ident: "EGXX".into(),
+                name: "Flaxby Airfield".into(),
+                ..Default::default()
+            }),
+        );
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+
+        for count in ["false", "true"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/search?q=lax&debug=true&count={count}"))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.data[0]["icao"], "KLAX", "count={count}");
+            assert_eq!(resp.data[0]["score"], 100, "count={count}");
+            assert_eq!(resp.data[1]["icao"], "EGXX", "count={count}");
+            assert_eq!(resp.data[1]["score"], 1, "count={count}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=lax")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert!(resp.data[0].get("score").is_none());
+    }
+
+    /// Tests exact and prefix match modes against the default substring mode
+    #[actix_web::test]
+    async fn test_search_airports_mode() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+
+        let cases = [
+            ("contains", "angeles", 1),
+            ("exact", "angeles", 0),
+            ("exact", "KLAX", 1),
+            ("exact", "lax", 1),
+            ("prefix", "kl", 1),
+            ("prefix", "lo", 2),
+            ("prefix", "ax", 0),
+        ];
+        for (mode, q, expected) in cases {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/search?q={q}&mode={mode}"))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "mode={mode} q={q}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=kj&mode=regex")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests `match=all` and `match=any` for multi-word, single-word, and exact queries
+    #[actix_web::test]
+    async fn test_search_airports_token_match() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .service(search_airports),
+        )
+        .await;
+
+        let cases = [
+            ("heathrow%20kennedy", "", 0),
+            ("heathrow%20kennedy", "&match=all", 0),
+            ("heathrow%20kennedy", "&match=any", 2),
+            ("heathrow%20zzzz", "&match=any", 1),
+            ("heathrow", "&match=all", 1),
+            ("heathrow", "&match=any", 1),
+            ("klax%20egll", "&match=any&mode=exact", 0),
+            ("loos%20angelees", "&mode=phonetic", 1),
+            ("heethrow%20kenedy", "&mode=phonetic", 0),
+            ("heethrow%20kenedy", "&mode=phonetic&match=any", 2),
+        ];
+        for (q, params, expected) in cases {
+            let req = test::TestRequest::get()
+                .uri(&format!("/airports/search?q={q}{params}"))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "q={q}{params}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=heathrow&match=most")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests restricting the matched fields, an empty mask, and unknown fields
+    #[actix_web::test]
+    async fn test_search_airports_fields() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .service(search_airports),
+        )
+        .await;
+
+        let cases = [
+            ("york", "", 1),
+            ("york", "name", 0),
+            ("york", "city", 1),
+            ("york", "name,icao", 0),
+            ("lax", "icao", 1),
+            ("lax", "name", 0),
+            ("lax", " name , iata ", 1),
+            ("manhattan", "keywords", 1),
+            ("manhattan", "name,icao,iata,city", 0),
+        ];
+        for (q, fields, expected) in cases {
+            let req = test::TestRequest::get()
+                .uri(&format!(
+                    "/airports/search?q={q}&fields={}",
+                    fields.replace(' ', "%20")
+                ))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "q={q} fields={fields}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=lax&fields=icao,runway")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests `select` projections on listing, search, and lookup, keeping added
+    /// annotations and rejecting unknown fields
+    #[actix_web::test]
+    async fn test_select_fields() {
+        use serde_json::{json, Value};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .service(get_airports)
+                .service(search_airports)
+                .service(get_airport_by_icao),
+        )
+        .await;
+
+        for uri in [
+            "/airports?select=icao,name&limit=1",
+            "/airports?select=name,%20icao,icao&country=US&limit=1",
+            "/airports/search?q=kennedy&select=icao,name",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Value>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(
+                resp.data,
+                [json!({ "icao": "KJFK", "name": "John F. Kennedy International Airport" })],
+                "{uri}"
+            );
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=kjfk&select=type&debug=true")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        let keys: Vec<&str> = resp.data[0]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(keys, ["score", "type"]);
+
+        let req = test::TestRequest::get()
+            .uri("/airports/kjfk?select=iata,city")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp, json!({ "iata": "JFK", "city": "New York" }));
+
+        // An empty list keeps every field
+        let req = test::TestRequest::get()
+            .uri("/airports/kjfk?select=")
+            .to_request();
+        let resp: Airport = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.icao, "KJFK");
+
+        for uri in [
+            "/airports?select=icao,runway",
+            "/airports/search?q=kennedy&select=lower_name",
+            "/airports/kjfk?select=Name",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests IATA lookup returning a single object, a list for shared codes, or 404
+    #[actix_web::test]
+    async fn test_get_airport_by_iata() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        for ident in ["XAA1", "XAA2"] {
+            airports.push(Airport::from(CsvAirport {
+                ident: ident.into(),
+                name: "Shared Code Field".into(),
+                iata_code: Some("XAA".into()),
+                ..Default::default()
+            }));
+        }
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(search_airports)
+                .service(get_airport_by_iata),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports/iata/lhr")
+            .to_request();
+        let resp: Airport = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.icao, "EGLL");
+        assert_eq!(resp.iata.as_deref(), Some("LHR"));
+
+        let req = test::TestRequest::get()
+            .uri("/airports/iata/XAA")
+            .to_request();
+        let resp: Vec<Airport> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.len(), 2);
+
+        let req = test::TestRequest::get()
+            .uri("/airports/iata/ZZZ")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // IATA codes participate in text search
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=lhr")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.data[0].icao, "EGLL");
+    }
+
+    /// Tests the comma-separated `type` filter on the list and search endpoints
+    #[actix_web::test]
+    async fn test_type_filter() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "KJRB".into(),
+            name: "Downtown Manhattan Heliport".into(),
+            kind: "heliport".into(),
+            ..Default::default()
+        }));
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports),
+        )
+        .await;
+
+        let cases = [
+            ("/airports", 4),
+            ("/airports?type=heliport", 1),
+            ("/airports?type=LARGE_AIRPORT", 3),
+            ("/airports?type=heliport,%20large_airport", 4),
+            ("/airports?type=closed", 0),
+            ("/airports/search?q=port&type=heliport", 1),
+            ("/airports/search?q=port&type=large_airport", 3),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+    }
+
+    /// Tests the case-insensitive, comma-separated `country` filter
+    #[actix_web::test]
+    async fn test_country_filter() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports),
+        )
+        .await;
+
+        let cases = [
+            ("/airports?country=US", 2),
+            ("/airports?country=gb", 1),
+            ("/airports?country=us,GB", 3),
+            ("/airports?country=FR", 0),
+            ("/airports/search?q=international&country=us", 2),
+            ("/airports/search?q=london&country=US", 0),
+            ("/airports/search?q=airport%20international&country=us", 2),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+    }
+
+    /// Tests the case-insensitive, comma-separated `city` filter
+    #[actix_web::test]
+    async fn test_city_filter() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports)
+                .service(get_airports_by_cursor),
+        )
+        .await;
+
+        let cases = [
+            ("/airports?city=new%20york", 1),
+            ("/airports?city=LONDON,Los%20Angeles", 2),
+            ("/airports?city=york", 0),
+            ("/airports/search?q=international&city=new%20york", 1),
+            ("/airports/search?q=heathrow&city=los%20angeles", 0),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/cursor?city=london")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["icao"], "EGLL");
+        assert_eq!(resp["data"][0]["city"], "London");
+        assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    }
+
+    /// Tests the `scheduled` filter on list, search, and cursor endpoints
+    #[actix_web::test]
+    async fn test_scheduled_filter() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "K0S9".into(),
+            name: "Jefferson County International Airport".into(),
+            scheduled_service: Some("no".into()),
+            ..Default::default()
+        }));
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(
+            App::new()
+                .app_data(state)
+                .service(get_airports)
+                .service(search_airports)
+                .service(get_airports_by_cursor),
+        )
+        .await;
+
+        let cases = [
+            ("/airports", 4),
+            ("/airports?scheduled=true", 3),
+            ("/airports?scheduled=false", 1),
+            ("/airports/search?q=international&scheduled=true", 2),
+            ("/airports/search?q=international&scheduled=false", 1),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports/cursor?scheduled=false")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["icao"], "K0S9");
+        assert_eq!(resp["data"][0]["scheduled_service"], false);
+
+        let req = test::TestRequest::get()
+            .uri("/airports?scheduled=sometimes")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests the inclusive elevation range, below sea level and with unknown elevations
+    #[actix_web::test]
+    async fn test_elevation_filter() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "EHAM".into(),
+            name: "Amsterdam Airport Schiphol".into(),
+            elevation_ft: Some(-11),
+            ..Default::default()
+        }));
+        airports.push(Airport::from(CsvAirport {
+            ident: "XXXX".into(),
+            name: "Unsurveyed Strip".into(),
+            ..Default::default()
+        }));
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(get_airports)).await;
+
+        let cases = [
+            ("/airports", 5),
+            ("/airports?min_elevation=100", 1),
+            ("/airports?max_elevation=0", 1),
+            ("/airports?min_elevation=-20&max_elevation=13", 2),
+            ("/airports?min_elevation=-1000", 4),
+            ("/airports?min_elevation=83&max_elevation=83", 1),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/airports?min_elevation=100&max_elevation=50")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests per-country aggregation ordering and pagination
+    #[actix_web::test]
+    async fn test_get_countries() {
+        let state = create_test_state();
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(get_countries)).await;
+
+        let req = test::TestRequest::get().uri("/countries").to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 2);
+        assert_eq!(
+            resp.data,
+            [
+                serde_json::json!({ "country": "US", "count": 2 }),
+                serde_json::json!({ "country": "GB", "count": 1 }),
+            ]
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/countries?offset=1&limit=1")
+            .to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.data[0]["country"], "GB");
+        assert!(!resp.has_more);
+    }
+
+    /// Tests the `region` filter, alone and with `country`, and region code validation
+    #[actix_web::test]
+    async fn test_region_filter() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports)
+                .service(get_airports_by_cursor),
+        )
+        .await;
+
+        let cases = [
+            ("/airports?region=US-NY", 1),
+            ("/airports?region=us-ny,US-CA", 2),
+            ("/airports?region=gb-eng&country=US", 0),
+            ("/airports?region=US-CA&country=us", 1),
+            ("/airports/search?q=airport&region=GB-ENG", 1),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+        let req = test::TestRequest::get()
+            .uri("/airports/cursor?region=us-ny,US-CA")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+
+        for uri in [
+            "/airports?region=NY",
+            "/airports?region=US-NY,USA-NY",
+            "/airports?region=US-ABCD",
+            "/airports?region=U1-NY",
+            "/airports/search?q=airport&region=US_NY",
+            "/airports/cursor?region=US-",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    /// Tests the case-insensitive `continent` filter and per-continent aggregation
+    #[actix_web::test]
+    async fn test_continents() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports)
+                .service(get_continents),
+        )
+        .await;
+
+        let cases = [
+            ("/airports?continent=EU", 1),
+            ("/airports?continent=na", 2),
+            ("/airports?continent=na,eu", 3),
+            ("/airports?continent=AS", 0),
+            ("/airports/search?q=international&continent=Na", 2),
+            ("/airports/search?q=heathrow&continent=NA", 0),
+        ];
+        for (uri, expected) in cases {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, expected, "{uri}");
+        }
+
+        let req = test::TestRequest::get().uri("/continents").to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 2);
+        assert_eq!(
+            resp.data,
+            [
+                serde_json::json!({ "continent": "NA", "count": 2 }),
+                serde_json::json!({ "continent": "EU", "count": 1 }),
+            ]
+        );
+    }
+
+    /// Tests that `/types` lists every type with its count, including unusual ones
+    #[actix_web::test]
+    async fn test_get_types() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        for (ident, kind) in [("XHEL", "heliport"), ("XBAL", "balloonport")] {
+            airports.push(Airport::from(CsvAirport {
+                ident: ident.into(),
+                name: format!("{kind} {ident}"),
+                kind: kind.into(),
+                ..Default::default()
+            }));
+        }
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(App::new().app_data(state).service(get_types)).await;
+
+        let req = test::TestRequest::get().uri("/types").to_request();
+        let resp: TestPaginatedResponse<Vec<serde_json::Value>> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.total, 3);
+        assert_eq!(
+            resp.data,
+            [
+                serde_json::json!({ "type": "large_airport", "count": 3 }),
+                serde_json::json!({ "type": "balloonport", "count": 1 }),
+                serde_json::json!({ "type": "heliport", "count": 1 }),
+            ]
+        );
+
+        let req = test::TestRequest::get().uri("/types?limit=0").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests that page sizes follow the configured default and maximum
+    #[actix_web::test]
+    async fn test_configured_page_limits() {
+        use crate::pagination::{PageLimits, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT_CEILING};
+
+        let airports: Vec<Airport> = (0..120)
+            .map(|i| {
+                Airport::from(CsvAirport {
+                    ident: format!("X{i:03}"),
+                    name: format!("Regional Airfield Number {i}"),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        for max_page_limit in [2, 100, MAX_PAGE_LIMIT_CEILING] {
+            let mut state = AppState::new(airports.clone(), "airports.csv");
+            state.page_limits = PageLimits::new(DEFAULT_PAGE_LIMIT, max_page_limit);
+            let app = test::init_service(
+                App::new()
+                    .app_data(web::Data::new(state))
+                    .configure(configure),
+            )
+            .await;
+            let expected = max_page_limit.min(airports.len());
+
+            let req = test::TestRequest::get().uri("/airports").to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            let default = DEFAULT_PAGE_LIMIT.min(max_page_limit);
+            assert_eq!(resp["limit"], default);
+            assert_eq!(resp["data"].as_array().unwrap().len(), default);
+
+            for uri in [
+                "/airports?limit=5000",
+                "/airports/search?q=regional&limit=5000",
+            ] {
+                let req = test::TestRequest::get().uri(uri).to_request();
+                let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+                assert_eq!(resp["limit"], max_page_limit, "{uri}");
+                assert_eq!(resp["data"].as_array().unwrap().len(), expected, "{uri}");
+            }
+            let req = test::TestRequest::get()
+                .uri("/airports/cursor?limit=5000")
+                .to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp["data"].as_array().unwrap().len(), expected);
+        }
+    }
+
+    /// Tests that the lazy search path matches the full materialization path
+    #[actix_web::test]
+    async fn test_search_airports_lazy_matches_full() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let mut state = AppState::new(airports, "airports.csv");
+        // Both paths share a cache key, so the cache would hide the second one
+        state.search_cache = LruCache::new(0);
+        let state = web::Data::new(state);
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+
+        for params in [
+            "q=ai",
+            "q=ai&sort=name&limit=1",
+            "q=ai&sort=icao&offset=1&limit=1",
+            "q=international&offset=1",
+            "q=ai&offset=5",
+            "q=xyz",
+        ] {
+            let mut pages = Vec::new();
+            for count in ["false", "true"] {
+                let req = test::TestRequest::get()
+                    .uri(&format!("/airports/search?{params}&count={count}"))
+                    .to_request();
+                let resp: TestPaginatedResponse<Vec<Airport>> =
+                    test::call_and_read_body_json(&app, req).await;
+                let icaos: Vec<String> = resp.data.into_iter().map(|a| a.icao).collect();
+                pages.push((resp.total, resp.has_more, resp.remaining, icaos));
+            }
+            assert_eq!(pages[0], pages[1], "{params}");
+        }
+    }
+
+    /// Tests that repeated JSON searches are served from the cache until a reload
+    #[cfg(feature = "metrics")]
+    #[actix_web::test]
+    async fn test_search_cache() {
+        let path = write_fixture(
+            "search-cache.csv",
+            "ident,name\nKJFK,John F. Kennedy International Airport\n",
+        );
+        let airports = create_test_state().snapshot().airports.clone();
+        let state = web::Data::new(AppState::new(airports, path.to_str().unwrap()));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(search_airports)).await;
+        let search = |uri: &'static str| test::TestRequest::get().uri(uri).to_request();
+        let counts = || {
+            (
+                state.metrics.search_cache_hits.get(),
+                state.metrics.search_cache_misses.get(),
+            )
+        };
+
+        let first = test::call_and_read_body(&app, search("/airports/search?q=airport")).await;
+        let second = test::call_and_read_body(&app, search("/airports/search?q=%20AIRPORT")).await;
+        assert_eq!(first, second);
+        assert_eq!(counts(), (1, 1));
+
+        // Different pages and non-JSON formats are distinct or uncached
+        test::call_service(&app, search("/airports/search?q=airport&limit=1")).await;
+        test::call_service(&app, search("/airports/search?q=airport&format=csv")).await;
+        assert_eq!(counts(), (1, 2));
+        assert_eq!(state.search_cache.len(), 2);
+
+        state.reload().unwrap();
+        assert!(state.search_cache.is_empty());
+        let resp: TestPaginatedResponse<Vec<Airport>> =
+            test::call_and_read_body_json(&app, search("/airports/search?q=airport")).await;
+        assert_eq!(resp.total, 1);
+        assert_eq!(counts(), (1, 3));
+    }
+
+    /// Tests admin reload authorization and the returned airport count
+    #[actix_web::test]
+    async fn test_admin_reload() {
+        let path = write_fixture(
+            "admin-reload.csv",
+            "ident,name\nKJFK,John F. Kennedy International Airport\nEGLL,London Heathrow Airport\n",
+        );
+        let mut state = AppState::new(Vec::new(), path.to_str().unwrap());
+        state.admin_token = Some("secret".into());
+        let state = web::Data::new(state);
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(admin_reload)).await;
+
+        for auth in [None, Some("Bearer wrong"), Some("secret")] {
+            let mut req = test::TestRequest::post().uri("/admin/reload");
+            if let Some(auth) = auth {
+                req = req.insert_header((header::AUTHORIZATION, auth));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "{auth:?}");
+        }
+        assert!(state.snapshot().airports.is_empty());
+
+        let req = test::TestRequest::post()
+            .uri("/admin/reload")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["airports"], 2);
+        assert_eq!(state.snapshot().airports.len(), 2);
+    }
+
+    /// Tests liveness, and readiness for loaded and empty datasets
+    #[actix_web::test]
+    async fn test_health_and_ready() {
+        let empty = web::Data::new(AppState::new(Vec::new(), "airports.csv"));
+        for (state, ready_status) in [
+            (create_test_state(), StatusCode::OK),
+            (empty, StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            let app = test::init_service(
+                App::new()
+                    .app_data(state.clone())
+                    .service(health)
+                    .service(ready),
+            )
+            .await;
+
+            let req = test::TestRequest::get().uri("/health").to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let req = test::TestRequest::get().uri("/ready").to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), ready_status);
+        }
+    }
+
+    /// Tests CSV output negotiated via the `Accept` header
+    #[actix_web::test]
+    async fn test_csv_content_negotiation() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(search_airports),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports?limit=1")
+            .insert_header((header::ACCEPT, "text/csv"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        let body = test::read_body(resp).await;
+        let mut lines = std::str::from_utf8(&body).unwrap().lines();
+        assert!(lines.next().unwrap().starts_with("icao,name,"));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("KJFK,John F. Kennedy International Airport,"));
+        assert!(
+            row.ends_with(",\"Manhattan, New York City, NYC, Idlewild\""),
+            "{row}"
+        );
+        assert!(lines.next().is_none());
+
+        // Empty results still carry a header line
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=xyz")
+            .insert_header((header::ACCEPT, "text/csv"))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(std::str::from_utf8(&body).unwrap().lines().count(), 1);
+
+        // JSON stays the default, and wins when preferred
+        for accept in ["*/*", "application/json, text/csv;q=0.5"] {
+            let req = test::TestRequest::get()
+                .uri("/airports")
+                .insert_header((header::ACCEPT, accept))
+                .to_request();
+            let resp: TestPaginatedResponse<Vec<Airport>> =
+                test::call_and_read_body_json(&app, req).await;
+            assert_eq!(resp.total, 3, "{accept}");
+        }
+    }
+
+    /// Tests GeoJSON output from the dedicated endpoint and the search format option
+    #[actix_web::test]
+    async fn test_geojson_output() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.insert(
+            0,
+            Airport::from(CsvAirport {
+                ident: "XXXX".into(),
+                name: "Unknown International".into(),
+                ..Default::default()
+            }),
+        );
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(search_airports)
+                .service(get_airports_geojson),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports.geojson?limit=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/geo+json"
+        );
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["type"], "FeatureCollection");
+        assert_eq!(
+            body["features"][0],
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [-73.7789, 40.6398] },
+                "properties": { "icao": "KJFK", "name": "John F. Kennedy International Airport" }
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=international&format=geojson")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["features"].as_array().unwrap().len(), 2);
+
+        let req = test::TestRequest::get()
+            .uri("/airports/search?q=international&format=xml")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests the NDJSON export spans chunks and emits one airport per line
+    #[actix_web::test]
+    async fn test_stream_airports() {
+        let airports: Vec<Airport> = (0..STREAM_CHUNK_SIZE + 3)
+            .map(|i| {
+                Airport::from(CsvAirport {
+                    ident: format!("X{i:03}"),
+                    name: format!("Field {i}"),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app =
+            test::init_service(App::new().app_data(state.clone()).service(stream_airports)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports/stream")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        let body = test::read_body(resp).await;
+        let lines: Vec<Airport> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), STREAM_CHUNK_SIZE + 3);
+        assert_eq!(lines[0].icao, "X000");
+        assert_eq!(
+            lines.last().unwrap().name,
+            format!("Field {}", STREAM_CHUNK_SIZE + 2)
+        );
+    }
+
+    /// Tests that the OpenAPI document covers the routes, parameters, and schemas
+    #[actix_web::test]
+    async fn test_openapi() {
+        let app = test::init_service(App::new().service(get_openapi).service(get_docs)).await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let doc: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        for path in [
+            "/airports",
+            "/airports/search",
+            "/airports/{icao}",
+            "/countries",
+        ] {
+            assert!(doc["paths"][path]["get"].is_object(), "{path}");
+        }
+        let params: Vec<&str> = doc["paths"]["/airports/search"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        for name in ["q", "limit", "offset", "sort", "mode", "type"] {
+            assert!(params.contains(&name), "{name} in {params:?}");
+        }
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["Airport"]["properties"]["icao"].is_object());
+        assert!(schemas["ErrorBody"].is_object());
+
+        let req = test::TestRequest::get().uri("/docs").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("/openapi.json"));
+    }
+
+    /// Tests that endpoints of features left out of the build answer 501
+    /// naming the feature, and are absent from the OpenAPI document
+    #[actix_web::test]
+    async fn test_disabled_endpoints() {
+        let app = test::init_service(
+            App::new()
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let document = serde_json::to_value(crate::openapi::ApiDoc::openapi()).unwrap();
+
+        for &(path, feature) in DISABLED_ENDPOINTS {
+            assert!(document["paths"].get(path).is_none(), "{path}");
+            let uri = path.replace("{prefix}", "dr5").replace("{icao}", "KJFK");
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED, "{path}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(
+                body["error"]
+                    .as_str()
+                    .unwrap()
+                    .contains(&format!("`{feature}`")),
+                "{path}"
+            );
+        }
+    }
+}
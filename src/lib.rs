@@ -0,0 +1,53 @@
+//! ICAO airport lookup service.
+//!
+//! The library exposes airport loading, search, and pagination as plain
+//! functions alongside the Actix handlers and middleware that serve them, so
+//! the binary in `main.rs` only wires configuration to an `HttpServer`.
+
+pub mod autocomplete;
+pub mod cache;
+pub mod config;
+pub mod error;
+pub mod format;
+pub mod geo;
+pub mod handlers;
+pub mod loader;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+pub mod model;
+pub mod openapi;
+pub mod pagination;
+pub mod params;
+pub mod ratelimit;
+pub mod sample;
+pub mod search;
+#[cfg(feature = "spatial")]
+pub mod spatial;
+pub mod state;
+pub mod stats;
+#[cfg(feature = "timezone")]
+pub mod timezone;
+#[cfg(feature = "fuzzy")]
+pub mod trigram;
+
+#[cfg(test)]
+mod test_support;
+
+pub use error::ApiError;
+pub use loader::{
+    load_airports, load_airports_with, ColumnMapping, Delimiter, LoadOptions, LoadStats,
+};
+pub use model::Airport;
+pub use pagination::{
+    paginate, paginate_head, PageLimits, PaginatedResponse, DEFAULT_MAX_PAGE_LIMIT,
+    DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT_CEILING,
+};
+#[cfg(feature = "fuzzy")]
+pub use search::fuzzy_search_indexed;
+pub use search::{
+    fuzzy_search, AirportFilter, FuzzyMatch, MatchMode, SearchFields, SearchQuery, SortOrder,
+    TokenMatch,
+};
+pub use state::{AppState, Dataset};
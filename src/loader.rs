@@ -0,0 +1,888 @@
+//! CSV loading of airport data.
+
+use crate::error::ApiError;
+use crate::model::{Airport, CsvAirport};
+use flate2::read::MultiGzDecoder;
+use log::{info, warn};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Leading bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Options controlling how `load_airports_with` reads CSV files and which rows
+/// it accepts
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Skip rows whose ident is not a well-formed ICAO code (see `is_valid_icao`)
+    pub strict_icao: bool,
+    /// Header names of the columns that vary between datasets
+    pub columns: ColumnMapping,
+    /// Field separator of the files
+    pub delimiter: Delimiter,
+}
+
+/// Field separator used to split CSV rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// This byte, e.g. `b','`, `b';'`, or `b'\t'`
+    Byte(u8),
+    /// Whichever of `,`, `;`, and tab occurs most often in each file's header
+    /// line, preferring `,` on ties
+    Auto,
+}
+
+impl Default for Delimiter {
+    fn default() -> Self {
+        Delimiter::Byte(b',')
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = String;
+
+    /// Parses a `CSV_DELIMITER` value: `auto`, `tab` (or `\t`), or any other
+    /// single ASCII character except a quote or line break
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(Delimiter::Auto),
+            "tab" | "\\t" => Ok(Delimiter::Byte(b'\t')),
+            _ => match value.as_bytes() {
+                [byte] if byte.is_ascii() && !matches!(byte, b'"' | b'\n' | b'\r') => {
+                    Ok(Delimiter::Byte(*byte))
+                }
+                _ => Err(format!(
+                    "invalid delimiter '{value}', expected a single character, tab, or auto"
+                )),
+            },
+        }
+    }
+}
+
+impl Delimiter {
+    /// Candidates for `Auto`, in tie-breaking order
+    const DETECTED: [u8; 3] = [b',', b';', b'\t'];
+
+    /// Resolves the separator for a file whose contents start with `head`
+    fn resolve(self, head: &[u8]) -> u8 {
+        match self {
+            Delimiter::Byte(byte) => byte,
+            Delimiter::Auto => {
+                let line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+                let count = |delimiter: u8| line.iter().filter(|&&b| b == delimiter).count();
+                // `max_by_key` keeps the last maximum, so scan in reverse to prefer earlier candidates
+                Delimiter::DETECTED
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|&delimiter| count(delimiter))
+                    .unwrap_or(b',')
+            }
+        }
+    }
+}
+
+/// CSV header names to read the ICAO code, name, coordinates, and IATA code
+/// from, so datasets with their own naming load without preprocessing.
+///
+/// Defaults to the OurAirports names. Every other column is always read by its
+/// OurAirports name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// ICAO code column (default: `ident`)
+    pub icao: String,
+    /// Airport name column (default: `name`)
+    pub name: String,
+    /// Latitude column (default: `latitude_deg`)
+    pub latitude: String,
+    /// Longitude column (default: `longitude_deg`)
+    pub longitude: String,
+    /// IATA code column (default: `iata_code`)
+    pub iata: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            icao: "ident".into(),
+            name: "name".into(),
+            latitude: "latitude_deg".into(),
+            longitude: "longitude_deg".into(),
+            iata: "iata_code".into(),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// `(configured name, OurAirports name)` for each mapped column
+    fn pairs(&self) -> [(&str, &'static str); 5] {
+        [
+            (&self.icao, "ident"),
+            (&self.name, "name"),
+            (&self.latitude, "latitude_deg"),
+            (&self.longitude, "longitude_deg"),
+            (&self.iata, "iata_code"),
+        ]
+    }
+
+    /// Columns every file must have, by their configured names
+    fn required(&self) -> [&str; 2] {
+        [&self.icao, &self.name]
+    }
+
+    /// Rewrites a header row to OurAirports names.
+    ///
+    /// Configured columns take the OurAirports name of the field they map to.
+    /// A column that already has that name but is not the configured source is
+    /// blanked, so it is ignored rather than read as a duplicate field.
+    fn apply(&self, headers: &csv::ByteRecord) -> csv::ByteRecord {
+        let pairs = self.pairs();
+        headers
+            .iter()
+            .map(|header| {
+                if let Some(&(_, field)) =
+                    pairs.iter().find(|(source, _)| source.as_bytes() == header)
+                {
+                    field.as_bytes()
+                } else if pairs.iter().any(|(_, field)| field.as_bytes() == header) {
+                    b""
+                } else {
+                    header
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks that a code is shaped like an ICAO location indicator: exactly four
+/// ASCII letters or digits
+pub fn is_valid_icao(code: &str) -> bool {
+    code.len() == 4 && code.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Loads airport data from CSV file with validation and preprocessing, using
+/// the default (lenient) `LoadOptions`
+///
+/// # Parameters
+/// - `path`: Filesystem path to a CSV file, a directory of `*.csv` files, or a
+///   comma-separated list of either, merged in the order given. Gzip-compressed
+///   files (e.g., `airports.csv.gz`) are decompressed transparently
+///
+/// # Returns
+/// - Vector of parsed Airport records
+/// - Skips entries with empty ICAO codes
+/// - Keeps only the first row for each ICAO code (compared case-insensitively),
+///   across all files, logging one warning that lists the dropped duplicates
+/// - Missing or unparseable coordinates and elevations are stored as `None`
+/// - Blank IATA codes, municipalities, and links are stored as `None`
+/// - `scheduled_service` is true only for `yes`; other or missing values are false
+///
+/// # Preprocessing
+/// - Converts ICAO and names to lowercase for search optimization
+/// - Stores original case values for display purposes
+pub fn load_airports(path: &str) -> Result<Vec<Airport>, ApiError> {
+    load_airports_with(path, &LoadOptions::default()).map(|(airports, _)| airports)
+}
+
+/// Row counts gathered while loading CSV files, summed across all files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadStats {
+    /// Data rows read from the files
+    pub rows: usize,
+    /// Airports kept after skipping and deduplication
+    pub loaded: usize,
+    /// Rows skipped because their ident was blank
+    pub empty_ident: usize,
+    /// Rows dropped because an earlier row had the same ICAO code
+    pub duplicates: usize,
+    /// Rows whose ident is not a well-formed ICAO code, whether kept or skipped
+    pub malformed_icao: usize,
+    /// Loaded airports lacking a latitude or longitude (blank or unparseable)
+    pub missing_coordinates: usize,
+}
+
+/// Checks whether a file name looks like CSV input: `*.csv` or `*.csv.gz`
+fn is_csv_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    name.ends_with(".csv") || name.ends_with(".csv.gz")
+}
+
+/// Opens a CSV file for reading, transparently decompressing it when its
+/// contents start with the gzip magic bytes, whatever its extension
+fn open_csv(path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Expands a `path` argument into the CSV files it names.
+///
+/// Entries are separated by commas. A directory entry expands to the `*.csv`
+/// and `*.csv.gz` files directly inside it, sorted by file name so merge order
+/// is stable.
+fn resolve_paths(path: &str) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in path
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let entry = Path::new(entry);
+        if entry.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(entry)?
+                .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            found.retain(|file| file.is_file() && is_csv_file(file));
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(entry.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Latest modification time among the files `path` names, as resolved by
+/// `load_airports`.
+///
+/// # Returns
+/// `None` if `path` names no files or any of their timestamps is unavailable
+pub fn source_modified(path: &str) -> Option<SystemTime> {
+    resolve_paths(path)
+        .ok()?
+        .iter()
+        .map(|file| {
+            std::fs::metadata(file)
+                .and_then(|meta| meta.modified())
+                .ok()
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .max()
+}
+
+/// Rows converted per parallel batch, bounding how many raw records are
+/// buffered at once
+const PARSE_BATCH_ROWS: usize = 8192;
+
+/// Reads every data row of one CSV file, deserializing and converting rows
+/// to `Airport`s in parallel.
+///
+/// Fields are split on `options.delimiter`, and headers are first renamed per
+/// `options.columns`.
+///
+/// Records are split off serially, so quoted fields spanning lines are
+/// handled by the `csv` reader, then each batch of `PARSE_BATCH_ROWS` is
+/// converted with Rayon, computing the lowercase search fields.
+///
+/// # Returns
+/// One entry per data row in file order: the airport, or `None` if the row's
+/// ident is blank
+///
+/// # Errors
+/// - `ApiError::SchemaError` naming `file` and the columns it lacks if its
+///   header row is missing the configured ICAO code or name column
+/// - `ApiError::CsvError` for the first row in the file that cannot be read or
+///   deserialized, with that row's position
+fn parse_rows(
+    file: &Path,
+    mut reader: impl BufRead,
+    options: &LoadOptions,
+) -> Result<Vec<Option<Airport>>, ApiError> {
+    let columns = &options.columns;
+    let delimiter = options.delimiter.resolve(reader.fill_buf()?);
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = rdr.byte_headers()?.clone();
+    let missing: Vec<&str> = columns
+        .required()
+        .into_iter()
+        .filter(|column| !headers.iter().any(|header| header == column.as_bytes()))
+        .collect();
+    if !missing.is_empty() {
+        return Err(ApiError::SchemaError(format!(
+            "{} is missing required column(s): {}",
+            file.display(),
+            missing.join(", ")
+        )));
+    }
+    let headers = columns.apply(&headers);
+    let mut rows = Vec::new();
+    let mut batch = Vec::with_capacity(PARSE_BATCH_ROWS);
+    let mut records = rdr.byte_records();
+    loop {
+        batch.clear();
+        // A read error ends the file, but a row before it may still fail to
+        // deserialize, and that error comes first
+        let mut read_error = None;
+        for result in records.by_ref().take(PARSE_BATCH_ROWS) {
+            match result {
+                Ok(record) => batch.push(record),
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            }
+        }
+        let parsed: Vec<csv::Result<Option<Airport>>> = batch
+            .par_iter()
+            .map(|record| {
+                let row: CsvAirport = record.deserialize(Some(&headers))?;
+                Ok((!row.ident.trim().is_empty()).then(|| Airport::from(row)))
+            })
+            .collect();
+        for row in parsed {
+            rows.push(row?);
+        }
+        if let Some(e) = read_error {
+            return Err(e.into());
+        }
+        if batch.len() < PARSE_BATCH_ROWS {
+            return Ok(rows);
+        }
+    }
+}
+
+/// Loads airport data like `load_airports`, applying `options`.
+///
+/// Rows with a malformed ICAO code are counted in the returned `LoadStats`. With
+/// `strict_icao` they are skipped; otherwise they are kept.
+///
+/// # Returns
+/// The loaded airports together with their `LoadStats`. Each file's airport
+/// count and the merged totals are logged.
+///
+/// # Errors
+/// - `ApiError::IoError` if a file or directory cannot be opened (e.g., it does
+///   not exist), or if `path` names no files at all
+/// - `ApiError::SchemaError` if a file lacks the ICAO code or name column
+///   named in `options.columns`
+/// - `ApiError::CsvError` if a row cannot be read or parsed
+pub fn load_airports_with(
+    path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<Airport>, LoadStats), ApiError> {
+    let files = resolve_paths(path)?;
+    if files.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no CSV files found in {path}"),
+        )
+        .into());
+    }
+
+    let mut airports = Vec::new();
+    let mut stats = LoadStats::default();
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for file in &files {
+        // Opened separately so that a missing file surfaces as `ApiError::IoError`
+        // rather than as a CSV error
+        let rows = parse_rows(file, open_csv(file)?, options)?;
+        let before = airports.len();
+
+        for row in rows {
+            stats.rows += 1;
+            let Some(airport) = row else {
+                stats.empty_ident += 1;
+                continue;
+            };
+            if !is_valid_icao(&airport.icao) {
+                stats.malformed_icao += 1;
+                if options.strict_icao {
+                    continue;
+                }
+            }
+            if seen.insert(airport.lower_icao.clone()) {
+                if airport.latitude.is_none() || airport.longitude.is_none() {
+                    stats.missing_coordinates += 1;
+                }
+                airports.push(airport);
+            } else {
+                duplicates.push(airport.icao);
+            }
+        }
+        if files.len() > 1 {
+            info!(
+                "Loaded {} airports from {}",
+                airports.len() - before,
+                file.display()
+            );
+        }
+    }
+    stats.loaded = airports.len();
+    stats.duplicates = duplicates.len();
+
+    if !duplicates.is_empty() {
+        warn!(
+            "Dropped {} rows with duplicate ICAO codes, keeping first occurrences: {}",
+            duplicates.len(),
+            duplicates.join(", ")
+        );
+    }
+    let action = if options.strict_icao {
+        "skipped"
+    } else {
+        "kept"
+    };
+    info!(
+        "Loaded {} airports from {} rows in {} file(s): {} empty idents skipped, \
+         {} duplicates dropped, {} malformed ICAO codes {}, {} without coordinates",
+        stats.loaded,
+        stats.rows,
+        files.len(),
+        stats.empty_ident,
+        stats.duplicates,
+        stats.malformed_icao,
+        action,
+        stats.missing_coordinates
+    );
+    Ok((airports, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_fixture;
+
+    /// Tests that coordinates and elevations are parsed, and that bad values don't fail the load
+    #[actix_web::test]
+    async fn test_load_airports_coordinates() {
+        let path = write_fixture(
+            "coordinates.csv",
+            "ident,name,latitude_deg,longitude_deg,elevation_ft\n\
+             KJFK,John F. Kennedy International Airport,40.6398,-73.7789,13\n\
+             XXXX,Nowhere,,,-11\n\
+             YYYY,Garbage,north,west,high\n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(airports.len(), 3);
+        assert_eq!(airports[0].latitude, Some(40.6398));
+        assert_eq!(airports[0].longitude, Some(-73.7789));
+        assert_eq!(airports[1].latitude, None);
+        assert_eq!(airports[2].longitude, None);
+        assert_eq!(airports[0].elevation_ft, Some(13));
+        assert_eq!(airports[1].elevation_ft, Some(-11));
+        assert_eq!(airports[2].elevation_ft, None);
+    }
+
+    /// Tests that only the first row of a duplicated ICAO code survives
+    #[actix_web::test]
+    async fn test_load_airports_deduplicates() {
+        let path = write_fixture(
+            "duplicates.csv",
+            "ident,name\n\
+             KJFK,John F. Kennedy International Airport\n\
+             KLAX,Los Angeles International Airport\n\
+             KJFK,Duplicate Kennedy\n\
+             kjfk,Lowercase Kennedy\n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        let names: Vec<&str> = airports.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "John F. Kennedy International Airport",
+                "Los Angeles International Airport"
+            ]
+        );
+    }
+
+    /// Tests that malformed ICAO codes are kept by default and skipped in strict mode
+    #[actix_web::test]
+    async fn test_load_airports_strict_icao() {
+        let path = write_fixture(
+            "strict.csv",
+            "ident,name\n\
+             KJFK,John F. Kennedy International Airport\n\
+             00AA,Aero B Ranch Airport\n\
+             US-0001,Long Ident Heliport\n\
+             K-FK,Punctuated Field\n",
+        );
+        let path = path.to_str().unwrap();
+        let lenient = load_airports(path).unwrap();
+        let (strict, _) = load_airports_with(
+            path,
+            &LoadOptions {
+                strict_icao: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(lenient.len(), 4);
+        let icaos: Vec<&str> = strict.iter().map(|a| a.icao.as_str()).collect();
+        assert_eq!(icaos, ["KJFK", "00AA"]);
+    }
+
+    /// Tests the row counts reported for skipped, duplicate, and malformed rows
+    #[actix_web::test]
+    async fn test_load_stats() {
+        let path = write_fixture(
+            "stats.csv",
+            "ident,name,latitude_deg,longitude_deg\n\
+             KJFK,John F. Kennedy International Airport,40.6398,-73.7789\n\
+             ,Nameless,1.0,2.0\n\
+             KJFK,Duplicate Kennedy,40.6398,-73.7789\n\
+             US-0001,Long Ident Heliport,,\n\
+             EGLL,London Heathrow Airport,51.4706,west\n",
+        );
+        let path = path.to_str().unwrap();
+        let (_, lenient) = load_airports_with(path, &LoadOptions::default()).unwrap();
+        let (_, strict) = load_airports_with(
+            path,
+            &LoadOptions {
+                strict_icao: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            lenient,
+            LoadStats {
+                rows: 5,
+                loaded: 3,
+                empty_ident: 1,
+                duplicates: 1,
+                malformed_icao: 1,
+                missing_coordinates: 2,
+            }
+        );
+        assert_eq!((strict.loaded, strict.malformed_icao), (2, 1));
+        assert_eq!(strict.missing_coordinates, 1);
+    }
+
+    /// Tests that a missing file and a malformed file fail with distinct errors
+    #[actix_web::test]
+    async fn test_load_airports_errors() {
+        let err = load_airports("does/not/exist.csv").unwrap_err();
+        assert!(
+            matches!(&err, ApiError::IoError(e) if e.kind() == std::io::ErrorKind::NotFound),
+            "{err:?}"
+        );
+
+        let path = write_fixture("malformed.csv", "ident,name\nKJFK,Kennedy,extra\n");
+        let err = load_airports(path.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ApiError::CsvError(_)), "{err:?}");
+
+        // Every missing required column is named, even with no data rows
+        let path = write_fixture("no-columns.csv", "icao_code,type\n");
+        let err = load_airports(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "CSV schema error: {} is missing required column(s): ident, name",
+                path.display()
+            )
+        );
+
+        // Invalid UTF-8 on line 3 fails deserialization, and the extra column on
+        // line 4 fails reading; the earlier row is reported either way
+        let path = write_fixture("first-error.csv", "");
+        std::fs::write(
+            &path,
+            b"ident,name\nKJFK,Kennedy\nKLAX,Los \xff Angeles\nEGLL,Heathrow,extra\n",
+        )
+        .unwrap();
+        let err = load_airports(path.to_str().unwrap()).unwrap_err();
+        let ApiError::CsvError(err) = err else {
+            panic!("expected a CSV error, got {err:?}");
+        };
+        assert_eq!(err.position().map(|position| position.line()), Some(3));
+    }
+
+    /// Tests merging a directory and a comma-separated list, deduplicating across files
+    #[actix_web::test]
+    async fn test_load_airports_multiple_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("na.csv"),
+            "ident,name\nKJFK,John F. Kennedy International Airport\nKLAX,Los Angeles International Airport\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("eu.csv"),
+            "ident,name\nEGLL,London Heathrow Airport\nKJFK,Duplicate Kennedy\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.txt"), "not a CSV").unwrap();
+        let extra = write_fixture("extra.csv", "ident,name\nEHAM,Amsterdam Airport Schiphol\n");
+
+        let (airports, stats) =
+            load_airports_with(dir.to_str().unwrap(), &LoadOptions::default()).unwrap();
+        let icaos: Vec<&str> = airports.iter().map(|a| a.icao.as_str()).collect();
+        // Files merge in name order, so eu.csv's KJFK wins over na.csv's
+        assert_eq!(icaos, ["EGLL", "KJFK", "KLAX"]);
+        assert_eq!(airports[1].name, "Duplicate Kennedy");
+        assert_eq!((stats.rows, stats.duplicates), (4, 1));
+
+        let list = format!("{}, {}", extra.display(), dir.display());
+        let airports = load_airports(&list).unwrap();
+        assert_eq!(airports.len(), 4);
+        assert_eq!(airports[0].icao, "EHAM");
+    }
+
+    /// Tests that gzipped files load like plain ones, detected by content and in directories
+    #[actix_web::test]
+    async fn test_load_airports_gzip() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let csv = "ident,name\nKJFK,John F. Kennedy International Airport\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(csv.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let gz_path = dir.join("airports.csv.gz");
+        std::fs::write(&gz_path, &gzipped).unwrap();
+        // Detection goes by the magic bytes, not the extension
+        let misnamed = dir.join("misnamed.txt");
+        std::fs::write(&misnamed, &gzipped).unwrap();
+
+        for path in [gz_path.as_path(), misnamed.as_path(), dir] {
+            let airports = load_airports(path.to_str().unwrap()).unwrap();
+            assert_eq!(airports.len(), 1, "{}", path.display());
+            assert_eq!(airports[0].name, "John F. Kennedy International Airport");
+        }
+    }
+
+    /// Tests that municipalities are trimmed, and that blank ones become `None`
+    #[actix_web::test]
+    async fn test_load_airports_city() {
+        let path = write_fixture(
+            "city.csv",
+            "ident,name,municipality\n\
+             KJFK,John F. Kennedy International Airport, New York \n\
+             XXXX,Nowhere,\n\
+             YYYY,Somewhere,   \n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(airports[0].city.as_deref(), Some("New York"));
+        assert_eq!(airports[0].lower_city.as_deref(), Some("new york"));
+        assert_eq!(airports[1].city, None);
+        assert_eq!(airports[2].city, None);
+    }
+
+    /// Tests that only `yes` marks scheduled service, and anything else or nothing does not
+    #[actix_web::test]
+    async fn test_load_airports_scheduled_service() {
+        let path = write_fixture(
+            "scheduled.csv",
+            "ident,name,scheduled_service\n\
+             KJFK,John F. Kennedy International Airport,yes\n\
+             XXXX,Nowhere,no\n\
+             YYYY,Somewhere,\n\
+             ZZZZ,Elsewhere,maybe\n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        let scheduled: Vec<bool> = airports.iter().map(|a| a.scheduled_service).collect();
+        assert_eq!(scheduled, [true, false, false, false]);
+
+        let path = write_fixture("unscheduled.csv", "ident,name\nKJFK,Kennedy\n");
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+        assert!(!airports[0].scheduled_service);
+    }
+
+    /// Tests that Wikipedia and home page links are trimmed, and that blank ones become `None`
+    #[actix_web::test]
+    async fn test_load_airports_links() {
+        let path = write_fixture(
+            "links.csv",
+            "ident,name,wikipedia_link,home_link\n\
+             EGLL,London Heathrow Airport,https://en.wikipedia.org/wiki/Heathrow_Airport, https://www.heathrow.com/ \n\
+             XXXX,Nowhere,,  \n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            airports[0].wikipedia.as_deref(),
+            Some("https://en.wikipedia.org/wiki/Heathrow_Airport")
+        );
+        assert_eq!(
+            airports[0].website.as_deref(),
+            Some("https://www.heathrow.com/")
+        );
+        assert_eq!(
+            (&airports[1].wikipedia, &airports[1].website),
+            (&None, &None)
+        );
+    }
+
+    /// Tests that keywords are split on commas and trimmed, skipping blank entries
+    #[actix_web::test]
+    async fn test_load_airports_keywords() {
+        let path = write_fixture(
+            "keywords.csv",
+            "ident,name,keywords\n\
+             KJFK,John F. Kennedy International Airport,\"Idlewild, NYC,, \"\n\
+             XXXX,Nowhere,\n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(airports[0].keywords, ["Idlewild", "NYC"]);
+        assert_eq!(airports[0].lower_keywords, "idlewild\nnyc");
+        assert!(airports[1].keywords.is_empty());
+    }
+
+    /// Tests every parsed field, including the lowercase search fields, for a
+    /// file mixing full rows, blank idents, duplicates, and absent columns
+    #[actix_web::test]
+    async fn test_load_airports_parsed_fields() {
+        let path = write_fixture(
+            "parsed.csv",
+            "ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,\
+             iso_region,municipality,scheduled_service,iata_code,keywords\n\
+             LSZH,large_airport,Zürich Airport,47.4647,8.54917,1417,EU,CH,CH-ZH,Zürich,yes,ZRH,\"Kloten, Zürich-Flughafen\"\n\
+             ,small_airport,Nameless Strip,1.0,2.0,3,EU,CH,CH-ZH,,no,,\n\
+             lszh,closed,Duplicate Zürich,,,,EU,CH,CH-ZH,,no,,\n\
+             00AA,small_airport,Aero B Ranch Airport,,,,NA,US,US-KS,Leoti,no,,\n",
+        );
+        let airports = load_airports(path.to_str().unwrap()).unwrap();
+        assert_eq!(airports.len(), 2);
+
+        let zurich = &airports[0];
+        assert_eq!(
+            (
+                zurich.icao.as_str(),
+                zurich.name.as_str(),
+                zurich.kind.as_str()
+            ),
+            ("LSZH", "Zürich Airport", "large_airport")
+        );
+        assert_eq!(
+            (zurich.latitude, zurich.longitude, zurich.elevation_ft),
+            (Some(47.4647), Some(8.54917), Some(1417))
+        );
+        assert_eq!(
+            (
+                zurich.continent.as_str(),
+                zurich.country.as_str(),
+                zurich.region.as_str()
+            ),
+            ("EU", "CH", "CH-ZH")
+        );
+        assert_eq!(
+            (zurich.iata.as_deref(), zurich.city.as_deref()),
+            (Some("ZRH"), Some("Zürich"))
+        );
+        assert!(zurich.scheduled_service);
+        assert_eq!(zurich.keywords, ["Kloten", "Zürich-Flughafen"]);
+        assert_eq!(zurich.lower_icao, "lszh");
+        assert_eq!(zurich.lower_name, "zurich airport");
+        assert_eq!(zurich.lower_iata.as_deref(), Some("zrh"));
+        assert_eq!(zurich.lower_city.as_deref(), Some("zurich"));
+        assert_eq!(zurich.lower_keywords, "kloten\nzurich-flughafen");
+
+        // Columns the file omits take their defaults
+        let ranch = &airports[1];
+        assert_eq!(ranch.icao, "00AA");
+        assert_eq!((ranch.latitude, ranch.longitude), (None, None));
+        assert_eq!((&ranch.wikipedia, &ranch.website), (&None, &None));
+        assert_eq!((&ranch.iata, &ranch.lower_iata), (&None, &None));
+        assert!(ranch.keywords.is_empty() && ranch.lower_keywords.is_empty());
+
+        // `name` has no default, so a file without it cannot be loaded
+        let path = write_fixture("no-name.csv", "ident,type\nKJFK,large_airport\n");
+        let err = load_airports(path.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ApiError::SchemaError(_)), "{err:?}");
+    }
+
+    /// Tests loading a file with its own column names through a `ColumnMapping`
+    #[actix_web::test]
+    async fn test_load_airports_column_mapping() {
+        let path = write_fixture(
+            "mapped.csv",
+            "icao_code,airport_name,lat,lon,name\n\
+             KJFK,John F. Kennedy International Airport,40.6398,-73.7789,JFK Intl\n",
+        );
+        let path = path.to_str().unwrap();
+        let options = LoadOptions {
+            columns: ColumnMapping {
+                icao: "icao_code".into(),
+                name: "airport_name".into(),
+                latitude: "lat".into(),
+                longitude: "lon".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (airports, _) = load_airports_with(path, &options).unwrap();
+        let default_err = load_airports(path).unwrap_err();
+
+        // The file's own `name` column is ignored in favour of `airport_name`
+        assert_eq!(airports[0].icao, "KJFK");
+        assert_eq!(airports[0].lower_icao, "kjfk");
+        assert_eq!(airports[0].name, "John F. Kennedy International Airport");
+        assert_eq!(
+            (airports[0].latitude, airports[0].longitude),
+            (Some(40.6398), Some(-73.7789))
+        );
+        assert_eq!(
+            default_err.to_string(),
+            format!("CSV schema error: {path} is missing required column(s): ident")
+        );
+    }
+
+    /// Tests semicolon- and tab-separated files, configured and detected
+    #[actix_web::test]
+    async fn test_load_airports_delimiter() {
+        assert_eq!(";".parse(), Ok(Delimiter::Byte(b';')));
+        assert_eq!("tab".parse(), Ok(Delimiter::Byte(b'\t')));
+        assert_eq!("auto".parse(), Ok(Delimiter::Auto));
+        assert!("\"".parse::<Delimiter>().is_err());
+        assert!(";;".parse::<Delimiter>().is_err());
+
+        let semicolons = write_fixture(
+            "semicolons.csv",
+            "ident;name;latitude_deg;longitude_deg;keywords\n\
+             LFPG;Paris Charles de Gaulle Airport;49,0097;2,54778;\"Roissy, CDG\"\n",
+        );
+        let tabs = write_fixture("tabs.tsv", "ident\tname\nEDDF\tFrankfurt, am Main\n");
+        let options = |delimiter| LoadOptions {
+            delimiter,
+            ..Default::default()
+        };
+
+        let (airports, _) = load_airports_with(
+            semicolons.to_str().unwrap(),
+            &options(Delimiter::Byte(b';')),
+        )
+        .unwrap();
+        assert_eq!(airports[0].icao, "LFPG");
+        assert_eq!(airports[0].name, "Paris Charles de Gaulle Airport");
+        assert_eq!(airports[0].keywords, ["Roissy", "CDG"]);
+        // Decimal commas are not numbers, so the coordinates are dropped
+        assert_eq!(airports[0].latitude, None);
+
+        let (detected, _) =
+            load_airports_with(semicolons.to_str().unwrap(), &options(Delimiter::Auto)).unwrap();
+        assert_eq!(detected[0].name, airports[0].name);
+        let (detected, _) =
+            load_airports_with(tabs.to_str().unwrap(), &options(Delimiter::Auto)).unwrap();
+        assert_eq!(detected[0].name, "Frankfurt, am Main");
+
+        // With the default comma, a semicolon header is one unknown column
+        let err = load_airports(semicolons.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ApiError::SchemaError(_)), "{err:?}");
+    }
+}
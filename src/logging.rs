@@ -0,0 +1,159 @@
+//! Log output: env_logger's plain text by default, or one JSON object per line.
+
+use actix_web::middleware::Logger;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Target under which Actix's `Logger` middleware writes request lines
+const ACCESS_LOG_TARGET: &str = "actix_web::middleware::logger";
+
+/// `Logger` format for plain-text request lines: Actix's default followed by
+/// the `X-Request-Id` response header
+const TEXT_REQUEST_FORMAT: &str =
+    r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#;
+
+/// `Logger` format for JSON request lines. The `%{...}xi` placeholders are
+/// filled with JSON-encoded strings by `request_logger`; `%s` and `%D` are
+/// always numbers, and request IDs never need escaping, so every line is a
+/// valid JSON object.
+const JSON_REQUEST_FORMAT: &str = r#"{"method":%{method}xi,"path":%{path}xi,"status":%s,"duration_ms":%D,"remote_addr":%{remote_addr}xi,"request_id":"%{x-request-id}o"}"#;
+
+/// Shape of log lines, from `LOG_FORMAT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// env_logger's human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with request lines split into fields
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    /// Parses a `LOG_FORMAT` value: `text` or `json`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "invalid log format '{other}', expected one of: text, json"
+            )),
+        }
+    }
+}
+
+/// Installs the global logger, filtered by `RUST_LOG` like `env_logger::init`
+pub fn init(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_default_env();
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = json_line(&buf.timestamp().to_string(), record);
+            writeln!(buf, "{line}")
+        });
+    }
+    builder.init();
+}
+
+/// Builds the request logging middleware for `format`: Actix's default line
+/// plus the request ID for `Text`, or a JSON object with `method`, `path`,
+/// `status`, `duration_ms`, `remote_addr`, and `request_id` for `Json`
+pub fn request_logger(format: LogFormat) -> Logger {
+    match format {
+        LogFormat::Text => Logger::new(TEXT_REQUEST_FORMAT),
+        LogFormat::Json => Logger::new(JSON_REQUEST_FORMAT)
+            .custom_request_replace("method", |req| json_string(req.method().as_str()))
+            .custom_request_replace("path", |req| json_string(req.path()))
+            .custom_request_replace("remote_addr", |req| {
+                json_string(req.connection_info().realip_remote_addr().unwrap_or("-"))
+            }),
+    }
+}
+
+/// Encodes `value` as a JSON string literal, quotes included
+fn json_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// Renders a record as a JSON object with `timestamp`, `level`, and `target`.
+///
+/// Request lines from `request_logger` are already JSON objects and contribute
+/// their fields directly; any other message becomes a `message` string.
+fn json_line(timestamp: &str, record: &log::Record) -> serde_json::Value {
+    let mut line = serde_json::Map::new();
+    line.insert("timestamp".into(), timestamp.into());
+    line.insert("level".into(), record.level().as_str().into());
+    line.insert("target".into(), record.target().into());
+
+    let message = record.args().to_string();
+    match serde_json::from_str::<serde_json::Map<_, _>>(&message) {
+        Ok(fields) if record.target() == ACCESS_LOG_TARGET => line.extend(fields),
+        _ => {
+            line.insert("message".into(), message.into());
+        }
+    }
+    serde_json::Value::Object(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Tests `LOG_FORMAT` parsing and JSON rendering of plain and request lines
+    #[actix_web::test]
+    async fn test_json_line() {
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+
+        let line = json_line(
+            "2026-01-01T00:00:00Z",
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("icao_api")
+                .args(format_args!("reload failed: \"quoted\""))
+                .build(),
+        );
+        assert_eq!(
+            line,
+            json!({
+                "timestamp": "2026-01-01T00:00:00Z",
+                "level": "WARN",
+                "target": "icao_api",
+                "message": "reload failed: \"quoted\"",
+            })
+        );
+
+        // A request line as `request_logger` would format it
+        let request = JSON_REQUEST_FORMAT
+            .replace("%{method}xi", &json_string("GET"))
+            .replace("%{path}xi", &json_string("/airports/\"x\""))
+            .replace("%s", "200")
+            .replace("%D", "1.234")
+            .replace("%{remote_addr}xi", &json_string("127.0.0.1"))
+            .replace("%{x-request-id}o", "req-1");
+        let line = json_line(
+            "2026-01-01T00:00:00Z",
+            &log::Record::builder()
+                .level(log::Level::Info)
+                .target(ACCESS_LOG_TARGET)
+                .args(format_args!("{request}"))
+                .build(),
+        );
+        assert_eq!(
+            line,
+            json!({
+                "timestamp": "2026-01-01T00:00:00Z",
+                "level": "INFO",
+                "target": ACCESS_LOG_TARGET,
+                "method": "GET",
+                "path": "/airports/\"x\"",
+                "status": 200,
+                "duration_ms": 1.234,
+                "remote_addr": "127.0.0.1",
+                "request_id": "req-1",
+            })
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Prometheus collectors for request traffic and dataset size.
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+};
+
+/// Prometheus collectors for request traffic and dataset size, registered on a
+/// private registry exposed at GET /metrics.
+pub struct Metrics {
+    /// Registry holding every collector below
+    pub registry: Registry,
+    /// Requests received, labelled by route pattern and method
+    pub requests_total: IntCounterVec,
+    /// Responses sent, labelled by route pattern and status code
+    pub responses_total: IntCounterVec,
+    /// Request handling time in seconds, labelled by route pattern
+    pub request_duration: HistogramVec,
+    /// Number of airports in the currently served dataset
+    pub airports_loaded: IntGauge,
+    /// Search requests answered from the response cache
+    pub search_cache_hits: IntCounter,
+    /// Cacheable search requests that had to be computed
+    pub search_cache_misses: IntCounter,
+}
+
+impl Metrics {
+    /// Creates and registers all collectors
+    pub fn new() -> Self {
+        let requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total HTTP requests received"),
+            &["route", "method"],
+        )
+        .expect("valid requests_total metric");
+        let responses_total = IntCounterVec::new(
+            Opts::new(
+                "http_responses_total",
+                "Total HTTP responses by status code",
+            ),
+            &["route", "status"],
+        )
+        .expect("valid responses_total metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request handling time in seconds",
+            ),
+            &["route"],
+        )
+        .expect("valid request_duration metric");
+        let airports_loaded = IntGauge::new(
+            "airports_loaded_total",
+            "Number of airports in the served dataset",
+        )
+        .expect("valid airports_loaded metric");
+        let search_cache_hits = IntCounter::new(
+            "search_cache_hits_total",
+            "Search requests answered from the response cache",
+        )
+        .expect("valid search_cache_hits metric");
+        let search_cache_misses = IntCounter::new(
+            "search_cache_misses_total",
+            "Cacheable search requests computed without the response cache",
+        )
+        .expect("valid search_cache_misses metric");
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(requests_total.clone()))
+            .expect("register requests_total");
+        registry
+            .register(Box::new(responses_total.clone()))
+            .expect("register responses_total");
+        registry
+            .register(Box::new(request_duration.clone()))
+            .expect("register request_duration");
+        registry
+            .register(Box::new(airports_loaded.clone()))
+            .expect("register airports_loaded");
+        registry
+            .register(Box::new(search_cache_hits.clone()))
+            .expect("register search_cache_hits");
+        registry
+            .register(Box::new(search_cache_misses.clone()))
+            .expect("register search_cache_misses");
+
+        Metrics {
+            registry,
+            requests_total,
+            responses_total,
+            request_duration,
+            airports_loaded,
+            search_cache_hits,
+            search_cache_misses,
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
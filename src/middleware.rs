@@ -0,0 +1,1034 @@
+//! Request metrics, request IDs, rate limiting, query size limits, conditional
+//! GET, CORS, envelope unwrapping, pretty-printing, and response compression
+//! middleware.
+
+use crate::config::CorsOrigins;
+use crate::error::ApiError;
+use crate::format::prettify_json;
+use crate::sample::random_seed;
+use crate::state::AppState;
+use actix_cors::Cors;
+use actix_web::{
+    body::{self, BodySize, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::{
+        header::{
+            self, EntityTag, Header, HeaderName, HeaderValue, HttpDate, IfModifiedSince,
+            IfNoneMatch,
+        },
+        Method,
+    },
+    middleware::Next,
+    web, HttpMessage, HttpResponse,
+};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Responses with bodies smaller than this many bytes are sent uncompressed,
+/// since encoding overhead outweighs the savings.
+pub const COMPRESSION_MIN_BYTES: u64 = 1024;
+
+/// Middleware recording request counts, response statuses, and latency per route.
+///
+/// Requests are labelled by their matched route pattern (e.g., `/airports/{icao}`)
+/// rather than the raw path, keeping label cardinality bounded. Unrouted requests
+/// are labelled `unmatched`. Without the `metrics` feature it passes requests
+/// through untouched.
+#[cfg(feature = "metrics")]
+pub async fn track_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let res = next.call(req).await?;
+
+    if let Some(state) = state {
+        let metrics = &state.metrics;
+        metrics
+            .requests_total
+            .with_label_values(&[route.as_str(), method.as_str()])
+            .inc();
+        metrics
+            .responses_total
+            .with_label_values(&[route.as_str(), res.status().as_str()])
+            .inc();
+        metrics
+            .request_duration
+            .with_label_values(&[route.as_str()])
+            .observe(started.elapsed().as_secs_f64());
+    }
+    Ok(res)
+}
+
+/// Passthrough standing in for `track_metrics` without the `metrics` feature
+#[cfg(not(feature = "metrics"))]
+pub async fn track_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    next.call(req).await
+}
+
+/// Header carrying the request ID, read from requests and set on every response
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest inbound `X-Request-Id` kept; longer ones are replaced
+pub const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// ID of the current request, stored in the request extensions by `request_id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Checks that an inbound request ID is short and limited to ASCII letters,
+/// digits, and `-_.:`, so it is safe to echo in headers, logs, and JSON
+fn is_valid_request_id(id: &str) -> bool {
+    (1..=MAX_REQUEST_ID_LENGTH).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b))
+}
+
+/// Generates a random (version 4) UUID in its hyphenated lowercase form
+fn new_request_id() -> String {
+    let random = u128::from(random_seed()) << 64 | u128::from(random_seed());
+    let bits = (random & !(0xf << 76) | 0x4 << 76) & !(0b11 << 62) | 0b10 << 62;
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Middleware tagging every request with an ID for correlating clients and logs.
+///
+/// # Behavior
+/// - Keeps a valid inbound `X-Request-Id` (see `MAX_REQUEST_ID_LENGTH`), and
+///   otherwise generates a UUID
+/// - Stores the ID as a `RequestId` in the request extensions and echoes it in
+///   the `X-Request-Id` response header, where the request logger picks it up
+/// - Rebuilds `ApiError` responses from inner services and middleware so their
+///   JSON body carries it as `request_id`
+pub async fn request_id<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map_or_else(new_request_id, str::to_string);
+    req.extensions_mut().insert(RequestId(id.clone()));
+    // Only ever built from valid IDs, which are plain ASCII
+    let header_value = HeaderValue::from_str(&id).ok();
+
+    match next.call(req).await {
+        Ok(res) => {
+            let error = res
+                .response()
+                .error()
+                .and_then(|err| err.as_error::<ApiError>());
+            let mut res = match error.map(|err| err.response_for_request(Some(&id))) {
+                Some(response) => res.into_response(response).map_into_right_body(),
+                None => res.map_into_left_body(),
+            };
+            if let Some(value) = header_value {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }
+        Err(err) => {
+            let mut response = match err.as_error::<ApiError>() {
+                Some(api_error) => api_error.response_for_request(Some(&id)),
+                None => err.error_response(),
+            };
+            if let Some(value) = header_value {
+                response.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Err(InternalError::from_response(err.to_string(), response).into())
+        }
+    }
+}
+
+/// Paths whose responses reflect live process state or the API itself rather
+/// than the dataset, or differ between identical requests, and so never carry
+/// a dataset validator. `/stats` includes the load time, which a reload of
+/// unchanged data moves without changing the dataset version, and `/version`
+/// the server start time.
+pub const UNVERSIONED_PATHS: [&str; 8] = [
+    "/health",
+    "/ready",
+    "/metrics",
+    "/openapi.json",
+    "/docs",
+    "/airports/random",
+    "/stats",
+    "/version",
+];
+
+/// Weak entity tag identifying a dataset version.
+///
+/// Weak, because the same data is served in several encodings and formats.
+pub fn dataset_etag(version: u64) -> EntityTag {
+    EntityTag::new_weak(format!("{version:016x}"))
+}
+
+/// Truncates a timestamp to whole seconds, the resolution of HTTP dates
+fn to_http_seconds(time: SystemTime) -> SystemTime {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Middleware adding dataset validators to successful GET and HEAD responses
+/// and answering conditional revalidations.
+///
+/// # Validators
+/// - `ETag`: Weak tag for the current dataset version
+/// - `Last-Modified`: Source CSV modification time captured at load, when known
+///
+/// # Behavior
+/// - A matching `If-None-Match` (or `*`) is answered `304 Not Modified` without
+///   running the handler
+/// - Without `If-None-Match`, an `If-Modified-Since` at or after `Last-Modified`
+///   is answered the same way
+/// - Paths in `UNVERSIONED_PATHS` and other methods pass through untouched
+/// - A reload changes both validators, so clients refetch after it
+pub async fn conditional_get<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let versioned = matches!(*req.method(), Method::GET | Method::HEAD)
+        && !UNVERSIONED_PATHS.contains(&req.path());
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let dataset = match state {
+        Some(state) if versioned => state.snapshot(),
+        _ => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+    let etag = dataset_etag(dataset.version);
+    let last_modified = dataset.last_modified.map(to_http_seconds);
+
+    let fresh = if req.headers().contains_key(header::IF_NONE_MATCH) {
+        match IfNoneMatch::parse(req.request()) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+            Err(_) => false,
+        }
+    } else {
+        match (last_modified, IfModifiedSince::parse(req.request())) {
+            (Some(modified), Ok(IfModifiedSince(since))) => modified <= SystemTime::from(since),
+            _ => false,
+        }
+    };
+    let validators = |headers: &mut header::HeaderMap| {
+        headers.insert(
+            header::ETAG,
+            etag.to_string().parse().expect("valid ETag value"),
+        );
+        if let Some(modified) = last_modified {
+            headers.insert(
+                header::LAST_MODIFIED,
+                HttpDate::from(modified)
+                    .to_string()
+                    .parse()
+                    .expect("valid Last-Modified value"),
+            );
+        }
+    };
+
+    if fresh {
+        let mut not_modified = HttpResponse::NotModified().finish();
+        validators(not_modified.headers_mut());
+        return Ok(req.into_response(not_modified).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    if res.status().is_success() {
+        validators(res.headers_mut());
+    }
+    Ok(res.map_into_left_body())
+}
+
+/// Request headers browsers may send cross-origin, beyond the CORS-safelisted ones
+pub const CORS_ALLOWED_HEADERS: [header::HeaderName; 5] = [
+    header::ACCEPT,
+    header::CONTENT_TYPE,
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    REQUEST_ID_HEADER,
+];
+
+/// Response headers exposed to cross-origin scripts
+pub const CORS_EXPOSED_HEADERS: [header::HeaderName; 8] = [
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::LINK,
+    header::RETRY_AFTER,
+    REQUEST_ID_HEADER,
+    TOTAL_COUNT_HEADER,
+    HAS_MORE_HEADER,
+    NEXT_CURSOR_HEADER,
+];
+
+/// Seconds browsers may cache a preflight response
+pub const CORS_MAX_AGE_SECS: usize = 3600;
+
+/// Builds the CORS middleware for the read-only API.
+///
+/// Allows `GET`, `HEAD`, and `POST` for batch lookups and body searches (and answers their
+/// `OPTIONS` preflights) from the configured origins, with `CORS_ALLOWED_HEADERS` and `CORS_EXPOSED_HEADERS`.
+/// Credentials are not supported. Requests from other origins are rejected
+/// with `400 Bad Request`.
+pub fn cors(origins: &CorsOrigins) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods([Method::GET, Method::HEAD, Method::POST])
+        .allowed_headers(CORS_ALLOWED_HEADERS)
+        .expose_headers(CORS_EXPOSED_HEADERS)
+        .max_age(CORS_MAX_AGE_SECS);
+    match origins {
+        CorsOrigins::Any => cors.allow_any_origin().send_wildcard(),
+        CorsOrigins::List(list) => list
+            .iter()
+            .fold(cors, |cors, origin| cors.allowed_origin(origin)),
+    }
+}
+
+/// Longest query string accepted, in bytes of the raw (still percent-encoded) query
+pub const MAX_QUERY_STRING_BYTES: usize = 4096;
+
+/// Middleware rejecting requests whose query string exceeds `MAX_QUERY_STRING_BYTES`
+/// with `400 Bad Request`, before it is decoded or any handler runs.
+///
+/// No endpoint needs anywhere near this much, so the cap only ever stops
+/// accidental or malicious giant inputs.
+pub async fn limit_query_string<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let length = req.query_string().len();
+    if length > MAX_QUERY_STRING_BYTES {
+        let error = ApiError::BadRequest(format!(
+            "query string is {length} bytes, at most {MAX_QUERY_STRING_BYTES} are allowed"
+        ));
+        return Ok(req.error_response(error).map_into_right_body());
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// Paths that are never rate limited, so probes and scrapes keep working while
+/// a client is over its limit
+pub const RATE_LIMIT_EXEMPT_PATHS: [&str; 3] = ["/health", "/ready", "/metrics"];
+
+/// Resolves the client IP: the first `X-Forwarded-For` (or `Forwarded`) entry
+/// when present, otherwise the peer address
+fn client_ip(req: &ServiceRequest) -> Option<IpAddr> {
+    let info = req.connection_info();
+    let addr = info.realip_remote_addr()?;
+    addr.parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// Middleware enforcing `AppState::rate_limiter` per client IP.
+///
+/// # Behavior
+/// - Each request spends a token from its client's bucket; see `RateLimiter`
+/// - Over-limit requests are answered `429 Too Many Requests` with a
+///   `Retry-After` header, in whole seconds, without running the handler
+/// - Paths in `RATE_LIMIT_EXEMPT_PATHS`, requests whose client IP cannot be
+///   determined, and all requests when no limiter is configured pass through
+///
+/// `X-Forwarded-For` is trusted as sent, so the server should only be reachable
+/// through a proxy that sets it when the limit must not be bypassable.
+pub async fn rate_limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let limiter = state.as_ref().and_then(|state| state.rate_limiter.as_ref());
+    let client = client_ip(&req);
+    if let (Some(limiter), Some(client)) = (limiter, client) {
+        if !RATE_LIMIT_EXEMPT_PATHS.contains(&req.path()) {
+            if let Err(wait) = limiter.acquire(client, Instant::now()) {
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                let error = ApiError::TooManyRequests(retry_after);
+                return Ok(req.error_response(error).map_into_right_body());
+            }
+        }
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// Middleware opting small responses out of compression.
+///
+/// Must be registered inside (before) `Compress`. Bodies of known size below
+/// `COMPRESSION_MIN_BYTES` are marked `Content-Encoding: identity`, which
+/// `Compress` respects by leaving them untouched. Streaming bodies of unknown
+/// size are always eligible for compression.
+pub async fn skip_small_compression(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut res = next.call(req).await?;
+    if let BodySize::Sized(size) = res.response().body().size() {
+        if size < COMPRESSION_MIN_BYTES {
+            res.headers_mut().insert(
+                header::CONTENT_ENCODING,
+                header::HeaderValue::from_static("identity"),
+            );
+        }
+    }
+    Ok(res)
+}
+
+/// Whether the query string sets parameter `name` to exactly `value`
+fn query_flag(query: &str, name: &str, value: &str) -> bool {
+    web::Query::<Vec<(String, String)>>::from_query(query)
+        .is_ok_and(|pairs| pairs.iter().any(|pair| pair.0 == name && pair.1 == value))
+}
+
+/// Whether a response's `Content-Type` is one of `essences`, ignoring parameters
+fn has_content_type(res: &ServiceResponse<impl MessageBody>, essences: &[&str]) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|essence| essences.contains(&essence.trim()))
+}
+
+/// Total number of items across all pages, set on every offset page and from
+/// an unwrapped envelope's `total`
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// `true` or `false`, from an unwrapped envelope's `has_more`
+pub const HAS_MORE_HEADER: HeaderName = HeaderName::from_static("x-has-more");
+
+/// Cursor of the next page, from an unwrapped cursor page's `next_cursor`
+pub const NEXT_CURSOR_HEADER: HeaderName = HeaderName::from_static("x-next-cursor");
+
+/// Paginated response body as seen by `unwrap_envelope`; other fields are ignored
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    /// Page items, kept as their original bytes
+    #[serde(borrow)]
+    data: &'a RawValue,
+    /// Offset pages' total item count
+    total: Option<usize>,
+    /// Whether another page follows
+    has_more: Option<bool>,
+    /// Cursor pages' next cursor
+    next_cursor: Option<String>,
+}
+
+/// Middleware answering requests with `envelope=false` with the bare `data`
+/// array of paginated JSON responses.
+///
+/// # Behavior
+/// - Applies to successful `application/json` bodies of known size that are
+///   objects with a `data` field and a `total` or `has_more` field
+/// - Moves `total`, `has_more`, and `next_cursor` into the `X-Total-Count`,
+///   `X-Has-More`, and `X-Next-Cursor` headers; offset pages already carry
+///   `X-Total-Count`, and keep it and their `Link` header
+/// - Other responses, errors included, and any other `envelope` value pass
+///   through untouched
+///
+/// Must be registered inside `pretty_print`, so bare arrays are indented too,
+/// and `Compress`, which it cannot read through.
+pub async fn unwrap_envelope<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    if !query_flag(req.query_string(), "envelope", "false") {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    let res = next.call(req).await?;
+    if !res.status().is_success()
+        || !has_content_type(&res, &["application/json"])
+        || !matches!(res.response().body().size(), BodySize::Sized(_))
+    {
+        return Ok(res.map_into_left_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let bytes = body::to_bytes(body)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+    let envelope = serde_json::from_slice::<Envelope>(&bytes)
+        .ok()
+        .filter(|envelope| envelope.total.is_some() || envelope.has_more.is_some());
+    let Some(envelope) = envelope else {
+        let res = res.set_body(bytes).map_into_boxed_body();
+        return Ok(ServiceResponse::new(req, res).map_into_right_body());
+    };
+
+    let headers = res.headers_mut();
+    let metadata = [
+        (
+            TOTAL_COUNT_HEADER,
+            envelope.total.map(|total| total.to_string()),
+        ),
+        (
+            HAS_MORE_HEADER,
+            envelope.has_more.map(|more| more.to_string()),
+        ),
+        (NEXT_CURSOR_HEADER, envelope.next_cursor),
+    ];
+    for (name, value) in metadata {
+        if let Some(value) = value.and_then(|value| HeaderValue::from_str(&value).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    let data = envelope.data.get().as_bytes().to_vec();
+    let res = res.set_body(data).map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+/// Middleware re-indenting JSON responses for requests with `pretty=true`.
+///
+/// # Behavior
+/// - Applies to `application/json` and `application/geo+json` bodies of known
+///   size, errors included; streams and other formats pass through untouched
+/// - Keys keep their order, and the output matches `serde_json`'s pretty
+///   printer (two-space indent); see `prettify_json`
+/// - Any other `pretty` value leaves the default compact output
+///
+/// Must be registered outside `request_id`, so rebuilt error bodies are
+/// indented too, and inside `Compress`, which it cannot read through.
+pub async fn pretty_print<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    if !query_flag(req.query_string(), "pretty", "true") {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    let res = next.call(req).await?;
+    let json = has_content_type(&res, &["application/json", "application/geo+json"]);
+    if !json || !matches!(res.response().body().size(), BodySize::Sized(_)) {
+        return Ok(res.map_into_left_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let compact = body::to_bytes(body)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+    let res = res.set_body(prettify_json(&compact)).map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "metrics")]
+    use crate::handlers::get_metrics;
+    use crate::handlers::{configure, get_airport_by_icao, get_airports, health};
+    use crate::model::{Airport, CsvAirport};
+    use crate::pagination::DEFAULT_MAX_PAGE_LIMIT;
+    use crate::ratelimit::RateLimiter;
+    use crate::test_support::{create_test_state, write_fixture};
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::{from_fn, Compress};
+    use actix_web::{test, App};
+
+    /// Tests ETag headers, 304 revalidation, and that a reload changes the tag
+    #[actix_web::test]
+    async fn test_conditional_get() {
+        let path = write_fixture("etag.csv", "ident,name\nKJFK,Kennedy\n");
+        let airports = create_test_state().snapshot().airports.clone();
+        let state = web::Data::new(AppState::new(airports, path.to_str().unwrap()));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(conditional_get))
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(get_airport_by_icao)
+                .service(health),
+        )
+        .await;
+        let get = |uri: &str, tag: Option<&str>| {
+            let mut req = test::TestRequest::get().uri(uri);
+            if let Some(tag) = tag {
+                req = req.insert_header((header::IF_NONE_MATCH, tag));
+            }
+            req.to_request()
+        };
+
+        let resp = test::call_service(&app, get("/airports", None)).await;
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+
+        let resp = test::call_service(&app, get("/airports", Some(&etag))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag.as_str());
+        assert!(test::read_body(resp).await.is_empty());
+        for tag in [
+            "*",
+            &format!("\"other\", {}", etag.trim_start_matches("W/")),
+        ] {
+            let resp = test::call_service(&app, get("/airports/KJFK", Some(tag))).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED, "{tag}");
+        }
+
+        // Errors and live-state endpoints carry no validator
+        let resp = test::call_service(&app, get("/airports/ZZZZ", None)).await;
+        assert!(!resp.headers().contains_key(header::ETAG));
+        let resp = test::call_service(&app, get("/health", Some("*"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key(header::ETAG));
+
+        state.reload().unwrap();
+        let resp = test::call_service(&app, get("/airports", Some(&etag))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_ne!(resp.headers().get(header::ETAG).unwrap(), etag.as_str());
+    }
+
+    /// Tests `Last-Modified` from the CSV mtime, `If-Modified-Since`, and reload updates
+    #[actix_web::test]
+    async fn test_last_modified() {
+        let path = write_fixture("last-modified.csv", "ident,name\nKJFK,Kennedy\n");
+        let set_mtime = |secs: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        set_mtime(1_700_000_000);
+        let airports = create_test_state().snapshot().airports.clone();
+        let state = web::Data::new(AppState::new(airports, path.to_str().unwrap()));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(conditional_get))
+                .app_data(state.clone())
+                .service(get_airports),
+        )
+        .await;
+        let get = |headers: &[(header::HeaderName, &str)]| {
+            let mut req = test::TestRequest::get().uri("/airports");
+            for (name, value) in headers {
+                req = req.insert_header((name.clone(), *value));
+            }
+            req.to_request()
+        };
+        let loaded = "Tue, 14 Nov 2023 22:13:20 GMT";
+
+        let resp = test::call_service(&app, get(&[])).await;
+        assert_eq!(resp.headers().get(header::LAST_MODIFIED).unwrap(), loaded);
+
+        let since = |value| [(header::IF_MODIFIED_SINCE, value)];
+        let resp = test::call_service(&app, get(&since(loaded))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::LAST_MODIFIED).unwrap(), loaded);
+        let resp = test::call_service(&app, get(&since("Tue, 14 Nov 2023 22:13:19 GMT"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let both = [
+            (header::IF_NONE_MATCH, "\"stale\""),
+            (header::IF_MODIFIED_SINCE, loaded),
+        ];
+        let resp = test::call_service(&app, get(&both)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        set_mtime(1_700_000_600);
+        state.reload().unwrap();
+        let resp = test::call_service(&app, get(&since(loaded))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::LAST_MODIFIED).unwrap(),
+            "Tue, 14 Nov 2023 22:23:20 GMT"
+        );
+    }
+
+    /// Tests preflights and simple requests for wildcard and allowlisted origins
+    #[actix_web::test]
+    async fn test_cors() {
+        let state = create_test_state();
+        let any = test::init_service(
+            App::new()
+                .wrap(cors(&CorsOrigins::Any))
+                .app_data(state.clone())
+                .service(get_airports),
+        )
+        .await;
+        let listed = test::init_service(
+            App::new()
+                .wrap(cors(&CorsOrigins::List(vec!["https://app.example".into()])))
+                .app_data(state.clone())
+                .service(get_airports),
+        )
+        .await;
+        let get = |origin: &str| {
+            test::TestRequest::get()
+                .uri("/airports")
+                .insert_header((header::ORIGIN, origin))
+                .to_request()
+        };
+        let preflight = |origin: &str| {
+            test::TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri("/airports")
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "if-none-match"))
+                .to_request()
+        };
+        let allow_origin = |headers: &header::HeaderMap| {
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+
+        let resp = test::call_service(&any, get("https://other.example")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(allow_origin(resp.headers()).as_deref(), Some("*"));
+        let exposed = resp
+            .headers()
+            .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+            .unwrap();
+        assert!(exposed.to_str().unwrap().to_lowercase().contains("etag"));
+
+        let resp = test::call_service(&listed, preflight("https://app.example")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            allow_origin(resp.headers()).as_deref(),
+            Some("https://app.example")
+        );
+        let methods = resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+            .unwrap();
+        assert!(methods.to_str().unwrap().contains("GET"));
+
+        let resp = test::call_service(&listed, get("https://app.example")).await;
+        assert_eq!(
+            allow_origin(resp.headers()).as_deref(),
+            Some("https://app.example")
+        );
+        let resp = test::call_service(&listed, preflight("https://other.example")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(allow_origin(resp.headers()), None);
+    }
+
+    /// Tests 429 responses with `Retry-After`, per-client buckets, and exempt paths
+    #[actix_web::test]
+    async fn test_rate_limit() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let mut state = AppState::new(airports, "unused.csv");
+        state.rate_limiter = Some(RateLimiter::new(0.5, 2));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(rate_limit))
+                .app_data(web::Data::new(state))
+                .service(get_airports)
+                .service(health),
+        )
+        .await;
+        let get = |uri: &str, client: &str| {
+            test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("x-forwarded-for", client))
+                .to_request()
+        };
+
+        for _ in 0..2 {
+            let resp = test::call_service(&app, get("/airports", "192.0.2.1")).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = test::call_service(&app, get("/airports", "192.0.2.1, 10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "2");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("Too many requests"));
+
+        let resp = test::call_service(&app, get("/airports", "192.0.2.2")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, get("/health", "192.0.2.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    /// Tests that oversized query strings are rejected before reaching handlers
+    #[actix_web::test]
+    async fn test_limit_query_string() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(limit_query_string))
+                .app_data(create_test_state())
+                .service(get_airports),
+        )
+        .await;
+        let get = |query: String| {
+            test::TestRequest::get()
+                .uri(&format!("/airports?{query}"))
+                .to_request()
+        };
+
+        let padding = "x".repeat(MAX_QUERY_STRING_BYTES - "limit=1&pad=".len());
+        let resp = test::call_service(&app, get(format!("limit=1&pad={padding}"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, get(format!("limit=1&pad={padding}x"))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["error"].as_str().unwrap().contains("query string"));
+    }
+
+    /// Tests request ID generation, inbound IDs, and their echo in error bodies
+    #[actix_web::test]
+    async fn test_request_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(limit_query_string))
+                .wrap(from_fn(request_id))
+                .app_data(create_test_state())
+                .configure(crate::handlers::configure),
+        )
+        .await;
+        let get = |uri: &str, id: Option<&str>| {
+            let mut req = test::TestRequest::get().uri(uri);
+            if let Some(id) = id {
+                req = req.insert_header((REQUEST_ID_HEADER, id));
+            }
+            req.to_request()
+        };
+        let id_of = |headers: &header::HeaderMap| {
+            headers
+                .get(REQUEST_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let resp = test::call_service(&app, get("/airports/KJFK", None)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let generated = id_of(resp.headers());
+        assert_eq!(generated.len(), 36);
+        assert_eq!(&generated[14..15], "4", "{generated}");
+        assert!("89ab".contains(&generated[19..20]), "{generated}");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body.get("request_id").is_none());
+
+        let resp = test::call_service(&app, get("/airports/KJFK", None)).await;
+        assert_ne!(id_of(resp.headers()), generated);
+
+        let resp = test::call_service(&app, get("/airports/ZZZZ", Some("trace-42"))).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(id_of(resp.headers()), "trace-42");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["request_id"], "trace-42");
+        assert!(body["error"].as_str().unwrap().contains("ZZZZ"));
+
+        // Extractor errors and errors from inner middleware carry the ID too
+        for uri in [
+            "/airports?limit=abc".to_string(),
+            format!("/airports?pad={}", "x".repeat(MAX_QUERY_STRING_BYTES)),
+        ] {
+            let resp = test::call_service(&app, get(&uri, Some("trace-43"))).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["request_id"], "trace-43", "{}", &uri[..20]);
+        }
+
+        // Unsafe or oversized inbound IDs are replaced
+        let long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for id in ["has space", "quote\"", long.as_str()] {
+            let resp = test::call_service(&app, get("/airports/KJFK", Some(id))).await;
+            let echoed = id_of(resp.headers());
+            assert_ne!(echoed, id);
+            assert_eq!(echoed.len(), 36);
+        }
+    }
+
+    /// Tests that the metrics middleware records routes and the dataset gauge
+    #[cfg(feature = "metrics")]
+    #[actix_web::test]
+    async fn test_metrics() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(track_metrics))
+                .app_data(state.clone())
+                .service(get_airport_by_icao)
+                .service(get_metrics),
+        )
+        .await;
+
+        for uri in ["/airports/KJFK", "/airports/ZZZZ"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(r#"http_requests_total{method="GET",route="/airports/{icao}"} 2"#));
+        assert!(body.contains(r#"http_responses_total{route="/airports/{icao}",status="404"} 1"#));
+        assert!(body.contains("http_request_duration_seconds_bucket"));
+        assert!(body.contains("airports_loaded_total 3"));
+    }
+
+    /// Tests that `pretty=true` indents JSON bodies, errors included, in key order
+    #[actix_web::test]
+    async fn test_pretty_print() {
+        let state = create_test_state();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .wrap(from_fn(pretty_print))
+                .app_data(state.clone())
+                .service(get_airport_by_icao),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let airport = state.snapshot().find_by_icao("KJFK").unwrap().clone();
+        let body = test::call_and_read_body(&app, get("/airports/KJFK?pretty=true")).await;
+        assert_eq!(body, serde_json::to_vec_pretty(&airport).unwrap());
+        for uri in [
+            "/airports/KJFK",
+            "/airports/KJFK?pretty=false",
+            "/airports/KJFK?pretty=1",
+        ] {
+            let body = test::call_and_read_body(&app, get(uri)).await;
+            assert_eq!(body, serde_json::to_vec(&airport).unwrap(), "{uri}");
+        }
+
+        let resp = test::call_service(&app, get("/airports/ZZZZ?pretty=true")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body = test::read_body(resp).await;
+        let text = std::str::from_utf8(&body).unwrap();
+        assert!(text.starts_with("{\n  \"error\": "), "{text}");
+        assert!(text.contains("\n  \"request_id\": "), "{text}");
+    }
+
+    /// Tests that `envelope=false` serves bare `data` arrays with pagination
+    /// headers, and leaves other responses untouched
+    #[actix_web::test]
+    async fn test_unwrap_envelope() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .wrap(from_fn(unwrap_envelope))
+                .wrap(from_fn(pretty_print))
+                .app_data(create_test_state())
+                .configure(configure),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let enveloped = test::call_and_read_body(&app, get("/airports?limit=2")).await;
+        let resp = test::call_service(&app, get("/airports?limit=2&envelope=false")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let headers = resp.headers().clone();
+        assert_eq!(headers.get(TOTAL_COUNT_HEADER).unwrap(), "3");
+        assert_eq!(headers.get(HAS_MORE_HEADER).unwrap(), "true");
+        assert!(headers.get(header::LINK).is_some());
+        assert!(headers.get(NEXT_CURSOR_HEADER).is_none());
+        let bare = test::read_body(resp).await;
+        // `data` is the envelope's last field, copied byte for byte
+        assert!(bare.starts_with(b"[{\"icao\":"));
+        assert!(enveloped.ends_with(&[&bare[..], b"}"].concat()));
+
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, get("/airports/cursor?limit=2")).await;
+        let resp = test::call_service(&app, get("/airports/cursor?limit=2&envelope=false")).await;
+        assert_eq!(resp.headers().get(HAS_MORE_HEADER).unwrap(), "true");
+        assert_eq!(
+            resp.headers().get(NEXT_CURSOR_HEADER).unwrap(),
+            page["next_cursor"].as_str().unwrap()
+        );
+        assert!(resp.headers().get(TOTAL_COUNT_HEADER).is_none());
+        let bare: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(bare, page["data"]);
+
+        let body =
+            test::call_and_read_body(&app, get("/airports?envelope=false&pretty=true")).await;
+        assert!(body.starts_with(b"[\n  {\n    \"icao\": "));
+
+        for uri in [
+            "/airports/KJFK?envelope=false",
+            "/airports?limit=2&envelope=true",
+            "/airports?limit=2&envelope=no",
+        ] {
+            let resp = test::call_service(&app, get(uri)).await;
+            assert!(resp.headers().get(HAS_MORE_HEADER).is_none(), "{uri}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body.is_object(), "{uri}");
+        }
+        let resp = test::call_service(&app, get("/airports?limit=0&envelope=false")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["error"].is_string());
+    }
+
+    /// Tests that large responses are compressed and small ones are not
+    #[actix_web::test]
+    async fn test_compression_threshold() {
+        let airports: Vec<Airport> = (0..DEFAULT_MAX_PAGE_LIMIT)
+            .map(|i| {
+                Airport::from(CsvAirport {
+                    ident: format!("X{i:03}"),
+                    name: format!("Regional Airfield Number {i}"),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let state = web::Data::new(AppState::new(airports, "airports.csv"));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(skip_small_compression))
+                .wrap(Compress::default())
+                .app_data(state.clone())
+                .service(get_airports)
+                .service(health),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/airports")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/health")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "identity"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(&body[..], br#"{"status":"ok"}"#);
+    }
+}
@@ -0,0 +1,182 @@
+//! Airport records and the raw CSV rows they are built from.
+
+use crate::geo::{geohash, GEOHASH_PRECISION};
+use crate::search::{fold_text, soundex_words};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Separates keywords in `Airport::lower_keywords`. A newline never occurs in a
+/// keyword or, since queries are split on whitespace, in a query word.
+pub(crate) const KEYWORD_SEPARATOR: &str = "\n";
+
+/// Represents airport information with precomputed lowercase fields
+/// for efficient case-insensitive searching.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct Airport {
+    /// Official ICAO code (e.g., "KJFK")
+    pub icao: String,
+    /// Full airport name (e.g., "John F. Kennedy International Airport")
+    pub name: String,
+    /// Latitude in decimal degrees, if known
+    pub latitude: Option<f64>,
+    /// Longitude in decimal degrees, if known
+    pub longitude: Option<f64>,
+    /// Geohash of the coordinates (`GEOHASH_PRECISION` characters), if known
+    pub geohash: Option<String>,
+    /// IANA timezone (e.g., "America/New_York") resolved from the coordinates
+    /// and country; `None` without coordinates or the `timezone` feature
+    pub timezone: Option<String>,
+    /// Elevation above mean sea level in feet, negative below it, if known
+    pub elevation_ft: Option<i32>,
+    /// IATA code (e.g., "JFK"), if assigned
+    pub iata: Option<String>,
+    /// Facility type (e.g., "large_airport", "heliport", "closed")
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// ISO 3166-1 alpha-2 country code (e.g., "US")
+    pub country: String,
+    /// ISO 3166-2 region code (e.g., "US-NY")
+    pub region: String,
+    /// Continent code (e.g., "NA", "EU", "AS")
+    pub continent: String,
+    /// City or municipality served (e.g., "New York"), if known
+    pub city: Option<String>,
+    /// Whether the airport has scheduled commercial service
+    pub scheduled_service: bool,
+    /// Wikipedia article URL, if known
+    pub wikipedia: Option<String>,
+    /// Airport home page URL, if known
+    pub website: Option<String>,
+    /// Alternate, former, and local names (e.g., "Idlewild"); empty if none
+    pub keywords: Vec<String>,
+
+    /// Lowercase version of ICAO code for efficient searching
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) lower_icao: String,
+    /// Lowercase, diacritic-folded (`fold_text`) version of name for efficient searching
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) lower_name: String,
+    /// Lowercase version of IATA code for efficient searching
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) lower_iata: Option<String>,
+    /// Lowercase, diacritic-folded (`fold_text`) version of city for efficient searching
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) lower_city: Option<String>,
+    /// Folded (`fold_text`) keywords joined by `KEYWORD_SEPARATOR`, searched as one string
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) lower_keywords: String,
+    /// `soundex` codes of the words of `lower_name`, for phonetic search
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) name_soundex: Vec<[u8; 4]>,
+}
+
+/// Converts a raw CSV row into an `Airport`, precomputing the lowercase search fields,
+/// normalizing blank optional text columns to `None`, splitting `keywords` on commas,
+/// and reading `scheduled_service` as true only for `yes`.
+impl From<CsvAirport> for Airport {
+    fn from(record: CsvAirport) -> Self {
+        let iata = non_blank(record.iata_code);
+        let city = non_blank(record.municipality);
+        let keywords: Vec<String> = record
+            .keywords
+            .iter()
+            .flat_map(|keywords| keywords.split(','))
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let lower_name = fold_text(&record.name);
+        let point = record.latitude_deg.zip(record.longitude_deg);
+        Airport {
+            lower_icao: record.ident.to_lowercase(),
+            name_soundex: soundex_words(&lower_name),
+            lower_name,
+            lower_iata: iata.as_ref().map(|code| code.to_lowercase()),
+            lower_city: city.as_deref().map(fold_text),
+            lower_keywords: keywords
+                .iter()
+                .map(|keyword| fold_text(keyword))
+                .collect::<Vec<_>>()
+                .join(KEYWORD_SEPARATOR),
+            icao: record.ident,
+            name: record.name,
+            geohash: point.map(|point| geohash(point, GEOHASH_PRECISION)),
+            #[cfg(feature = "timezone")]
+            timezone: point.and_then(crate::timezone::resolve).map(str::to_string),
+            #[cfg(not(feature = "timezone"))]
+            timezone: None,
+            latitude: record.latitude_deg,
+            longitude: record.longitude_deg,
+            elevation_ft: record.elevation_ft,
+            iata,
+            kind: record.kind,
+            country: record.iso_country,
+            region: record.iso_region,
+            continent: record.continent,
+            city,
+            scheduled_service: record
+                .scheduled_service
+                .is_some_and(|flag| flag.trim().eq_ignore_ascii_case("yes")),
+            wikipedia: non_blank(record.wikipedia_link),
+            website: non_blank(record.home_link),
+            keywords,
+        }
+    }
+}
+
+/// Trims an optional CSV text value, mapping blank values to `None`
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Intermediate structure for CSV deserialization that matches
+/// the source CSV format's field names.
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct CsvAirport {
+    /// ICAO identifier from CSV file
+    pub(crate) ident: String,
+    /// Airport name from CSV file
+    pub(crate) name: String,
+    /// Latitude from CSV file; empty or unparseable values become `None`
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    pub(crate) latitude_deg: Option<f64>,
+    /// Longitude from CSV file; empty or unparseable values become `None`
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    pub(crate) longitude_deg: Option<f64>,
+    /// Elevation in feet from CSV file; empty or unparseable values become `None`
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    pub(crate) elevation_ft: Option<i32>,
+    /// IATA code from CSV file; often blank for small fields
+    #[serde(default)]
+    pub(crate) iata_code: Option<String>,
+    /// Facility type from CSV file's `type` column
+    #[serde(rename = "type", default)]
+    pub(crate) kind: String,
+    /// ISO country code from CSV file
+    #[serde(default)]
+    pub(crate) iso_country: String,
+    /// ISO region code from CSV file
+    #[serde(default)]
+    pub(crate) iso_region: String,
+    /// Continent code from CSV file
+    #[serde(default)]
+    pub(crate) continent: String,
+    /// City served from CSV file's `municipality` column; often blank
+    #[serde(default)]
+    pub(crate) municipality: Option<String>,
+    /// `yes` or `no` from CSV file; anything else is treated as `no`
+    #[serde(default)]
+    pub(crate) scheduled_service: Option<String>,
+    /// Wikipedia article URL from CSV file; often blank
+    #[serde(default)]
+    pub(crate) wikipedia_link: Option<String>,
+    /// Home page URL from CSV file; often blank
+    #[serde(default)]
+    pub(crate) home_link: Option<String>,
+    /// Comma-separated alternate names from CSV file; often blank
+    #[serde(default)]
+    pub(crate) keywords: Option<String>,
+}
@@ -0,0 +1,155 @@
+//! OpenAPI 3 description of the HTTP API, generated from handler annotations.
+
+use crate::error::ErrorBody;
+use crate::handlers;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// OpenAPI document served at `/openapi.json`.
+///
+/// Paths, parameters, and schemas come from the `#[utoipa::path]` annotations
+/// on the handlers and the `IntoParams`/`ToSchema` derives on the parameter and
+/// response types, so the document follows the code. The version is taken
+/// from the crate manifest. Endpoints behind Cargo features are described by
+/// their own documents, merged in by `FeaturePaths` when the feature is on.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ICAO API",
+        description = "Read-only lookup, search, and export of airports by ICAO code."
+    ),
+    paths(
+        handlers::get_airports,
+        handlers::search_airports,
+        handlers::search_airports_by_body,
+        handlers::get_airports_geojson,
+        handlers::get_airports_by_cursor,
+        handlers::autocomplete_airports,
+        handlers::stream_airports,
+        handlers::airport_distance,
+        handlers::airports_within,
+        handlers::random_airports,
+        handlers::batch_airports,
+        handlers::get_airport_by_iata,
+        handlers::get_airport_by_icao,
+        handlers::get_countries,
+        handlers::get_continents,
+        handlers::get_types,
+        handlers::get_stats,
+        handlers::get_version,
+        handlers::admin_reload,
+        handlers::health,
+        handlers::ready,
+    ),
+    components(schemas(ErrorBody, handlers::IcaoList)),
+    modifiers(&AdminTokenScheme, &OmitEmptyLicense, &FeaturePaths),
+    tags(
+        (name = "airports", description = "Airport listing, lookup, and search"),
+        (name = "aggregates", description = "Airport counts per group"),
+        (name = "operations", description = "Probes, metrics, and administration"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Endpoints of the `spatial` feature
+#[cfg(feature = "spatial")]
+#[derive(OpenApi)]
+#[openapi(paths(
+    handlers::nearest_airports,
+    handlers::airports_in_radius,
+    handlers::airports_by_geohash,
+))]
+struct SpatialApi;
+
+/// Endpoints of the `fuzzy` feature
+#[cfg(feature = "fuzzy")]
+#[derive(OpenApi)]
+#[openapi(paths(handlers::fuzzy_search_airports))]
+struct FuzzyApi;
+
+/// Endpoints of the `timezone` feature
+#[cfg(feature = "timezone")]
+#[derive(OpenApi)]
+#[openapi(paths(handlers::get_airport_timezone))]
+struct TimezoneApi;
+
+/// Endpoints of the `metrics` feature
+#[cfg(feature = "metrics")]
+#[derive(OpenApi)]
+#[openapi(paths(handlers::get_metrics))]
+struct MetricsApi;
+
+/// Merges the documents of the enabled features' endpoints, with their schemas
+struct FeaturePaths;
+
+impl Modify for FeaturePaths {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let features: &[fn() -> utoipa::openapi::OpenApi] = &[
+            #[cfg(feature = "spatial")]
+            SpatialApi::openapi,
+            #[cfg(feature = "fuzzy")]
+            FuzzyApi::openapi,
+            #[cfg(feature = "timezone")]
+            TimezoneApi::openapi,
+            #[cfg(feature = "metrics")]
+            MetricsApi::openapi,
+        ];
+        for feature in features {
+            openapi.merge(feature());
+        }
+    }
+}
+
+/// Registers the `admin_token` bearer scheme referenced by admin endpoints
+struct AdminTokenScheme;
+
+impl Modify for AdminTokenScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("The configured `ADMIN_TOKEN`"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Drops the license object, which would otherwise carry the manifest's empty
+/// `license` field
+struct OmitEmptyLicense;
+
+impl Modify for OmitEmptyLicense {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if openapi
+            .info
+            .license
+            .as_ref()
+            .is_some_and(|license| license.name.is_empty())
+        {
+            openapi.info.license = None;
+        }
+    }
+}
+
+/// Swagger UI page served at `/docs`, loading its assets from the unpkg CDN and
+/// the document from `/openapi.json`
+pub const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>ICAO API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
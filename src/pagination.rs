@@ -0,0 +1,417 @@
+//! Zero-copy pagination over slices.
+
+use crate::error::ApiError;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Maximum number of items in a single page when `MAX_PAGE_LIMIT` is not set.
+/// Requests specifying a higher limit are clamped to the configured maximum.
+pub const DEFAULT_MAX_PAGE_LIMIT: usize = 50;
+
+/// Highest maximum page size that `MAX_PAGE_LIMIT` may configure
+pub const MAX_PAGE_LIMIT_CEILING: usize = 1000;
+
+/// Number of items in a page when the request gives no `limit` and
+/// `DEFAULT_PAGE_LIMIT` is not set.
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+
+/// Page sizes applied to requests: `default` when no `limit` is given, and
+/// `max` as the upper bound for any `limit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    /// Page size used when the request gives no `limit`
+    pub default: usize,
+    /// Largest page size returned, whatever `limit` asks for
+    pub max: usize,
+}
+
+impl PageLimits {
+    /// Creates limits with the given default and maximum, lowering `default`
+    /// to `max` if it is larger
+    pub fn new(default: usize, max: usize) -> Self {
+        PageLimits {
+            default: default.min(max),
+            max,
+        }
+    }
+
+    /// Page size for a requested `limit`: `default` when absent, clamped to `max`
+    pub fn resolve(&self, limit: Option<usize>) -> usize {
+        limit.unwrap_or(self.default).min(self.max)
+    }
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        PageLimits::new(DEFAULT_PAGE_LIMIT, DEFAULT_MAX_PAGE_LIMIT)
+    }
+}
+
+/// Generic structure for paginated API responses with lifetime parameters
+/// enabling zero-copy data access through slice operations.
+///
+/// # Type Parameters
+/// - `'a`: Lifetime parameter ensuring data references remain valid
+/// - `T`: Type of the items being paginated
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaginatedResponse<'a, T> {
+    /// Total number of elements available across all pages
+    pub total: usize,
+    /// Flag indicating if more results are available beyond current page
+    pub has_more: bool,
+    /// Number of elements remaining after current page
+    pub remaining: usize,
+    /// Starting index actually applied, after clamping to `total`
+    pub offset: usize,
+    /// Page size actually applied, after clamping to the maximum page size
+    pub limit: usize,
+    /// 1-based number of the page containing `offset`, in pages of `limit` items
+    pub page: usize,
+    /// Number of `limit`-sized pages needed to cover `total`; 0 when `limit` is 0
+    pub total_pages: usize,
+    /// Slice containing the current page's data
+    pub data: &'a [T],
+}
+
+impl<T> PaginatedResponse<'_, T> {
+    /// Returns the same page metadata over different data, such as the page's
+    /// items converted to another representation
+    pub fn with_data<'b, U>(&self, data: &'b [U]) -> PaginatedResponse<'b, U> {
+        PaginatedResponse {
+            total: self.total,
+            has_more: self.has_more,
+            remaining: self.remaining,
+            offset: self.offset,
+            limit: self.limit,
+            page: self.page,
+            total_pages: self.total_pages,
+            data,
+        }
+    }
+
+    /// Returns where this page sits, enough to link its neighbours and report
+    /// the total
+    pub fn position(&self) -> PagePosition {
+        PagePosition {
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+            has_more: self.has_more,
+        }
+    }
+}
+
+/// Window of an offset page within its sequence, as applied after clamping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PagePosition {
+    /// Number of items across all pages
+    pub total: usize,
+    /// Starting index of the page
+    pub offset: usize,
+    /// Page size
+    pub limit: usize,
+    /// Whether items follow the page
+    pub has_more: bool,
+}
+
+impl PagePosition {
+    /// Builds an RFC 8288 `Link` header value with `next` and `prev` relations.
+    ///
+    /// # Parameters
+    /// - `path`: Request path the links point at
+    /// - `query`: Raw request query string; its `offset` and `limit` pairs are
+    ///   replaced, everything else is kept as sent
+    ///
+    /// # Returns
+    /// - `None` on a lone page, which has neither neighbour, or when `limit` is 0
+    /// - Otherwise the links, `prev` omitted on the first page and `next` on the
+    ///   last. `prev` starts `limit` items earlier, at 0 at the latest; from an
+    ///   empty page past the end it points at the last page holding items
+    pub fn link_header(&self, path: &str, query: &str) -> Option<String> {
+        if self.limit == 0 {
+            return None;
+        }
+        let kept: Vec<&str> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .filter(|pair| {
+                let key = pair.split('=').next().unwrap_or_default();
+                key != "offset" && key != "limit"
+            })
+            .collect();
+        let link = |offset: usize, rel: &str| {
+            let mut target = format!("{path}?");
+            for pair in &kept {
+                target.push_str(pair);
+                target.push('&');
+            }
+            format!(
+                "<{target}offset={offset}&limit={}>; rel=\"{rel}\"",
+                self.limit
+            )
+        };
+
+        let mut links = Vec::with_capacity(2);
+        if self.has_more {
+            links.push(link(self.offset + self.limit, "next"));
+        }
+        if self.offset > 0 {
+            let prev = if self.offset >= self.total {
+                self.total.saturating_sub(1) / self.limit * self.limit
+            } else {
+                self.offset.saturating_sub(self.limit)
+            };
+            links.push(link(prev, "prev"));
+        }
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+/// Efficiently paginates a dataset using slice operations without data copying.
+///
+/// # Parameters
+/// - `data`: The complete dataset to paginate
+/// - `offset`: Optional starting index (0-based, clamped to data length)
+/// - `limit`: Optional maximum items per page
+/// - `limits`: Default and maximum page sizes, normally `AppState::page_limits`
+///
+/// # Returns
+/// `PaginatedResponse` containing:
+/// - Calculated pagination metadata, including the effective `offset` and `limit`
+/// - Slice reference to the requested data page
+///
+/// # Behavior
+/// - Offset defaults to 0 if not specified
+/// - Limit defaults to `limits.default` if not specified
+/// - Automatically clamps values to valid ranges and `limits.max`
+pub fn paginate<T>(
+    data: &[T],
+    offset: Option<usize>,
+    limit: Option<usize>,
+    limits: PageLimits,
+) -> PaginatedResponse<'_, T> {
+    let total = data.len();
+    let start = offset.unwrap_or(0).min(total);
+    let limit = limits.resolve(limit);
+    let end = (start + limit).min(total);
+
+    PaginatedResponse {
+        total,
+        has_more: end < total,
+        remaining: total.saturating_sub(end),
+        offset: start,
+        limit,
+        page: start.checked_div(limit).unwrap_or(0) + 1,
+        total_pages: total_pages(total, limit),
+        data: &data[start..end],
+    }
+}
+
+/// Number of pages of `limit` items needed to cover `total` items
+fn total_pages(total: usize, limit: usize) -> usize {
+    if limit == 0 {
+        0
+    } else {
+        total.div_ceil(limit)
+    }
+}
+
+/// Paginates the leading items of a larger, already ordered sequence.
+///
+/// # Parameters
+/// - `head`: The first `min(total, offset + limit)` items of the full sequence
+/// - `total`: Length of the full sequence
+/// - `offset`, `limit`, `limits`: Pagination parameters, interpreted as in `paginate`
+///
+/// # Returns
+/// The same response `paginate` would produce over the full sequence
+pub fn paginate_head<T>(
+    head: &[T],
+    total: usize,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    limits: PageLimits,
+) -> PaginatedResponse<'_, T> {
+    let page = paginate(head, offset, limit, limits);
+    let end = head.len() - page.remaining;
+
+    PaginatedResponse {
+        total,
+        has_more: end < total,
+        remaining: total.saturating_sub(end),
+        total_pages: total_pages(total, page.limit),
+        ..page
+    }
+}
+
+/// Page of results for cursor-based pagination.
+///
+/// Unlike offset pages, a cursor names the last airport returned rather than a
+/// position, so continuing after a reload neither skips nor repeats records
+/// that were inserted or removed elsewhere in the dataset.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CursorPage<T> {
+    /// Items in the current page
+    pub data: Vec<T>,
+    /// Flag indicating if more results follow this page
+    pub has_more: bool,
+    /// Opaque cursor to pass as `cursor` for the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Encodes an ICAO code as an opaque, URL-safe cursor
+pub fn encode_cursor(icao: &str) -> String {
+    URL_SAFE_NO_PAD.encode(icao)
+}
+
+/// Decodes a cursor produced by `encode_cursor` back into an ICAO code.
+///
+/// # Errors
+/// `ApiError::BadRequest` if the cursor is not valid unpadded URL-safe base64,
+/// does not decode to UTF-8, or decodes to an empty string
+pub fn decode_cursor(cursor: &str) -> Result<String, ApiError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .filter(|icao| !icao.is_empty())
+        .ok_or_else(|| ApiError::BadRequest(format!("invalid cursor '{cursor}'")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests a page from the middle of the data, echoing the applied window
+    #[actix_web::test]
+    async fn test_paginate_middle_page() {
+        let data: Vec<usize> = (0..100).collect();
+        let page = paginate(&data, Some(10), Some(5), PageLimits::default());
+        assert_eq!(page.data, [10, 11, 12, 13, 14]);
+        assert_eq!((page.total, page.has_more, page.remaining), (100, true, 85));
+        assert_eq!((page.offset, page.limit), (10, 5));
+        assert_eq!((page.page, page.total_pages), (3, 20));
+    }
+
+    /// Tests that an offset past the end yields an empty final page
+    #[actix_web::test]
+    async fn test_paginate_offset_beyond_length() {
+        let data: Vec<usize> = (0..10).collect();
+        let page = paginate(&data, Some(200), None, PageLimits::default());
+        assert!(page.data.is_empty());
+        assert_eq!((page.has_more, page.remaining), (false, 0));
+        assert_eq!((page.offset, page.limit), (10, DEFAULT_PAGE_LIMIT));
+        assert_eq!((page.page, page.total_pages), (1, 1));
+    }
+
+    /// Tests that a zero limit returns no items but reports what remains
+    #[actix_web::test]
+    async fn test_paginate_zero_limit() {
+        let data: Vec<usize> = (0..10).collect();
+        let page = paginate(&data, Some(3), Some(0), PageLimits::default());
+        assert!(page.data.is_empty());
+        assert_eq!((page.has_more, page.remaining), (true, 7));
+        assert_eq!((page.offset, page.limit), (3, 0));
+        assert_eq!((page.page, page.total_pages), (1, 0));
+    }
+
+    /// Tests that limits above the maximum are clamped and absent ones take the default
+    #[actix_web::test]
+    async fn test_paginate_limit_clamped() {
+        let data: Vec<usize> = (0..100).collect();
+        let page = paginate(&data, None, Some(500), PageLimits::default());
+        assert_eq!(page.data.len(), DEFAULT_MAX_PAGE_LIMIT);
+        assert_eq!(page.limit, DEFAULT_MAX_PAGE_LIMIT);
+        assert_eq!(page.remaining, 100 - DEFAULT_MAX_PAGE_LIMIT);
+
+        let page = paginate(&data, None, None, PageLimits::default());
+        assert_eq!(page.data.len(), DEFAULT_PAGE_LIMIT);
+        assert_eq!((page.limit, page.total_pages), (DEFAULT_PAGE_LIMIT, 5));
+
+        let limits = PageLimits::new(80, 30);
+        assert_eq!(limits.default, 30);
+        assert_eq!(paginate(&data, None, None, limits).limit, 30);
+        assert_eq!(paginate(&data, None, Some(10), limits).limit, 10);
+    }
+
+    /// Tests that empty input yields an empty page with zeroed metadata
+    #[actix_web::test]
+    async fn test_paginate_empty_input() {
+        let data: [usize; 0] = [];
+        let page = paginate(&data, Some(5), Some(10), PageLimits::default());
+        assert!(page.data.is_empty());
+        assert_eq!((page.total, page.has_more, page.remaining), (0, false, 0));
+        assert_eq!(page.offset, 0);
+        assert_eq!((page.page, page.total_pages), (1, 0));
+    }
+
+    /// Tests `Link` relations on first, middle, last, and past-the-end pages,
+    /// keeping other parameters
+    #[actix_web::test]
+    async fn test_link_header() {
+        let data: Vec<usize> = (0..25).collect();
+        let links = |offset, query| {
+            paginate(&data, Some(offset), Some(10), PageLimits::default())
+                .position()
+                .link_header("/airports", query)
+        };
+
+        assert_eq!(
+            links(0, "type=large_airport&limit=10").as_deref(),
+            Some("</airports?type=large_airport&offset=10&limit=10>; rel=\"next\"")
+        );
+        assert_eq!(
+            links(12, "offset=12&limit=10&q=new%20york").as_deref(),
+            Some(
+                "</airports?q=new%20york&offset=22&limit=10>; rel=\"next\", \
+                 </airports?q=new%20york&offset=2&limit=10>; rel=\"prev\""
+            )
+        );
+        assert_eq!(
+            links(20, "").as_deref(),
+            Some("</airports?offset=10&limit=10>; rel=\"prev\"")
+        );
+        for offset in [25, 40] {
+            assert_eq!(
+                links(offset, "").as_deref(),
+                Some("</airports?offset=20&limit=10>; rel=\"prev\"")
+            );
+        }
+        assert_eq!(
+            paginate(&data, None, Some(25), PageLimits::default())
+                .position()
+                .link_header("/airports", ""),
+            None
+        );
+    }
+
+    /// Tests that paginating a head matches paginating the full sequence
+    #[actix_web::test]
+    async fn test_paginate_head() {
+        let data: Vec<usize> = (0..100).collect();
+        let page = paginate_head(&data[..15], 100, Some(10), Some(5), PageLimits::default());
+        let full = paginate(&data, Some(10), Some(5), PageLimits::default());
+        assert_eq!(
+            serde_json::to_value(&page).unwrap(),
+            serde_json::to_value(&full).unwrap()
+        );
+    }
+
+    /// Tests that cursors round-trip and that malformed ones are rejected
+    #[actix_web::test]
+    async fn test_cursor_round_trip() {
+        for icao in ["KJFK", "00AA", "X-1"] {
+            let cursor = encode_cursor(icao);
+            assert!(!cursor.contains(['+', '/', '=']));
+            assert_eq!(decode_cursor(&cursor).unwrap(), icao);
+        }
+
+        for cursor in ["", "not base64!", "_w"] {
+            assert!(
+                matches!(decode_cursor(cursor), Err(ApiError::BadRequest(_))),
+                "{cursor}"
+            );
+        }
+    }
+}
@@ -0,0 +1,738 @@
+//! Query string parameters accepted by the HTTP endpoints.
+
+use crate::error::ApiError;
+use crate::geo::{validate_point, BoundingBox};
+use crate::search::{parse_items, parse_list, AirportFilter, Origin, SearchQuery};
+use actix_web::error::QueryPayloadError;
+use actix_web::web;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+/// Longest free-text `q` accepted, in bytes before trimming. Longer values are
+/// rejected before any lowercasing or scanning.
+pub const MAX_QUERY_LENGTH: usize = 256;
+
+/// Kind of value accepted by a non-string query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamKind {
+    /// Unsigned integer, e.g. `limit`
+    Unsigned,
+    /// Signed integer, e.g. `min_elevation`
+    Signed,
+    /// Decimal number, e.g. `lat`
+    Number,
+    /// `true` or `false`
+    Boolean,
+}
+
+impl ParamKind {
+    /// Checks whether `value` parses as this kind
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            ParamKind::Unsigned => value.parse::<u64>().is_ok(),
+            ParamKind::Signed => value.parse::<i32>().is_ok(),
+            ParamKind::Number => value.parse::<f64>().is_ok(),
+            ParamKind::Boolean => value.parse::<bool>().is_ok(),
+        }
+    }
+
+    /// Describes the accepted values for error messages
+    fn expected(self) -> &'static str {
+        match self {
+            ParamKind::Unsigned => "a non-negative integer",
+            ParamKind::Signed => "an integer",
+            ParamKind::Number => "a number",
+            ParamKind::Boolean => "true or false",
+        }
+    }
+}
+
+/// Every non-string query parameter across the parameter structs. `count` is
+/// a boolean on search but a number on `/airports/random`; the deserializer's
+/// message tells the two apart.
+const TYPED_PARAMS: &[(&str, ParamKind)] = &[
+    ("limit", ParamKind::Unsigned),
+    ("offset", ParamKind::Unsigned),
+    ("max_distance", ParamKind::Unsigned),
+    ("count", ParamKind::Unsigned),
+    ("seed", ParamKind::Unsigned),
+    ("min_elevation", ParamKind::Signed),
+    ("max_elevation", ParamKind::Signed),
+    ("lat", ParamKind::Number),
+    ("lon", ParamKind::Number),
+    ("radius_km", ParamKind::Number),
+    ("min_lat", ParamKind::Number),
+    ("min_lon", ParamKind::Number),
+    ("max_lat", ParamKind::Number),
+    ("max_lon", ParamKind::Number),
+    ("scheduled", ParamKind::Boolean),
+    ("count", ParamKind::Boolean),
+    ("debug", ParamKind::Boolean),
+    ("highlight", ParamKind::Boolean),
+    ("require_coordinates", ParamKind::Boolean),
+];
+
+/// Converts a query string that failed to deserialize into an
+/// `ApiError::BadRequest` naming the offending parameter.
+///
+/// The deserializer's message only names missing fields, so for malformed
+/// values it is used to find the kind of value that failed to parse, and the
+/// first parameter in `query` of that kind whose value does not parse is
+/// reported together with what it accepts. Anything else keeps the
+/// deserializer's own message.
+pub fn query_error(err: &QueryPayloadError, query: &str) -> ApiError {
+    let QueryPayloadError::Deserialize(err) = err else {
+        return ApiError::BadRequest(err.to_string());
+    };
+    let message = err.to_string();
+    if let Some(field) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        return ApiError::BadRequest(format!("missing required parameter '{field}'"));
+    }
+
+    let kinds: &[ParamKind] = if message.contains("integer")
+        || message.contains("digit")
+        || message.starts_with("number too")
+    {
+        &[ParamKind::Unsigned, ParamKind::Signed]
+    } else if message.contains("float") {
+        &[ParamKind::Number]
+    } else if message.contains("`true` or `false`") {
+        &[ParamKind::Boolean]
+    } else {
+        &[]
+    };
+    let pairs = web::Query::<Vec<(String, String)>>::from_query(query)
+        .map(web::Query::into_inner)
+        .unwrap_or_default();
+    pairs
+        .iter()
+        .find_map(|(name, value)| {
+            TYPED_PARAMS
+                .iter()
+                .find(|(param, kind)| param == name && kinds.contains(kind) && !kind.accepts(value))
+                .map(|(_, kind)| {
+                    ApiError::BadRequest(format!(
+                        "invalid {name} '{value}', expected {}",
+                        kind.expected()
+                    ))
+                })
+        })
+        .unwrap_or_else(|| ApiError::BadRequest(format!("invalid query string: {message}")))
+}
+
+/// Query parameters for pagination controls
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PaginationParams {
+    /// Maximum number of items to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for pagination (default: 0)
+    pub offset: Option<usize>,
+    /// Comma-separated ICAO codes to return in request order instead of a page
+    /// (e.g., `KJFK,KLAX,EGLL`); on `/airports` only
+    pub icaos: Option<String>,
+    /// Comma-separated airport fields to include in JSON output (e.g.,
+    /// `icao,name`; default: all); on `/airports` pages only
+    pub select: Option<String>,
+    /// Comma-separated facility types to include (e.g., `large_airport,medium_airport`)
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Comma-separated ISO country codes to include (e.g., `US,CA`)
+    pub country: Option<String>,
+    /// Comma-separated ISO 3166-2 region codes to include (e.g., `US-NY,US-NJ`)
+    pub region: Option<String>,
+    /// Comma-separated continent codes to include (e.g., `EU,NA`)
+    pub continent: Option<String>,
+    /// Comma-separated city names to include (e.g., `New York,Boston`)
+    pub city: Option<String>,
+    /// Only airports with (`true`) or without (`false`) scheduled service
+    pub scheduled: Option<bool>,
+    /// Lowest elevation in feet to include; may be negative
+    pub min_elevation: Option<i32>,
+    /// Highest elevation in feet to include; may be negative
+    pub max_elevation: Option<i32>,
+}
+
+/// Query parameters for search operations
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    /// Search query string (case-insensitive partial matches, surrounding whitespace ignored);
+    /// `-word` excludes airports containing `word`
+    pub q: String,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+    /// Result ordering: `relevance` (default without `lat`/`lon`), `name`,
+    /// `icao`, or `distance` (default with them, and requires them)
+    pub sort: Option<String>,
+    /// Matching strategy: `contains` (default), `exact`, `prefix`, or
+    /// `phonetic` (name words that sound alike)
+    pub mode: Option<String>,
+    /// Whether `all` (default) or `any` of the words in `q` must match, in
+    /// `contains` and `phonetic` mode
+    #[serde(rename = "match")]
+    pub token_match: Option<String>,
+    /// Comma-separated fields to match `q` against: `icao`, `name`, `iata`,
+    /// `city`, `keywords` (default: all)
+    pub fields: Option<String>,
+    /// Comma-separated facility types to include (e.g., `large_airport,medium_airport`)
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Comma-separated ISO country codes to include (e.g., `US,CA`)
+    pub country: Option<String>,
+    /// Comma-separated ISO 3166-2 region codes to include (e.g., `US-NY,US-NJ`)
+    pub region: Option<String>,
+    /// Comma-separated continent codes to include (e.g., `EU,NA`)
+    pub continent: Option<String>,
+    /// Comma-separated city names to include (e.g., `New York,Boston`)
+    pub city: Option<String>,
+    /// Only airports with (`true`) or without (`false`) scheduled service
+    pub scheduled: Option<bool>,
+    /// Latitude of a reference point in decimal degrees (-90 to 90); with
+    /// `lon`, adds each result's `distance_km` to JSON output and makes
+    /// `distance` the default `sort`
+    pub lat: Option<f64>,
+    /// Longitude of the reference point in decimal degrees (-180 to 180)
+    pub lon: Option<f64>,
+    /// Drops airports without coordinates instead of ranking them last by
+    /// distance (default: false)
+    pub require_coordinates: Option<bool>,
+    /// Forces materializing every match before paginating (default: false)
+    pub count: Option<bool>,
+    /// Output format: `json`, `csv`, or `geojson`; overrides `Accept` negotiation
+    pub format: Option<String>,
+    /// Adds each result's relevance `score` to JSON output (default: false)
+    pub debug: Option<bool>,
+    /// Adds each result's matched `name` and `icao` byte ranges to JSON output
+    /// (default: false)
+    pub highlight: Option<bool>,
+    /// Answers 404 instead of an empty page when nothing matches (default: false)
+    pub strict: Option<bool>,
+    /// Comma-separated airport fields to include in JSON output (e.g.,
+    /// `icao,name`; default: all); unlike `fields`, does not affect matching
+    pub select: Option<String>,
+}
+
+/// Query parameters for single-airport lookups
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SelectParams {
+    /// Comma-separated airport fields to include (e.g., `icao,name`; default: all)
+    pub select: Option<String>,
+}
+
+/// Rejects a `limit` of zero, which can only ever produce an empty page
+fn validate_limit(limit: Option<usize>) -> Result<(), ApiError> {
+    if limit == Some(0) {
+        return Err(ApiError::BadRequest(
+            "limit must be at least 1 (omit it for the default page size)".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects a `q` longer than `MAX_QUERY_LENGTH` bytes. Runs before any other
+/// check, so oversized input is never trimmed, folded, or counted.
+fn validate_query_length(q: &str) -> Result<(), ApiError> {
+    if q.len() > MAX_QUERY_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "q must be at most {MAX_QUERY_LENGTH} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects `region` entries that are not shaped like an ISO 3166-2 code: two
+/// letters, a hyphen, and one to three letters or digits (e.g., `US-NY`, `GB-ENG`)
+fn validate_regions<'a>(codes: impl IntoIterator<Item = &'a str>) -> Result<(), ApiError> {
+    let malformed = codes
+        .into_iter()
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .find(|code| {
+            let Some((country, subdivision)) = code.split_once('-') else {
+                return true;
+            };
+            country.len() != 2
+                || !country.chars().all(|c| c.is_ascii_alphabetic())
+                || !(1..=3).contains(&subdivision.len())
+                || !subdivision.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    match malformed {
+        Some(code) => Err(ApiError::BadRequest(format!(
+            "invalid region '{code}', expected an ISO 3166-2 code like US-NY"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Rejects an elevation range whose lower bound exceeds its upper bound
+fn validate_elevation(min: Option<i32>, max: Option<i32>) -> Result<(), ApiError> {
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(ApiError::BadRequest(format!(
+                "min_elevation ({min}) must not exceed max_elevation ({max})"
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl PaginationParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_elevation(self.min_elevation, self.max_elevation)?;
+        validate_regions(self.region.iter().flat_map(|value| value.split(',')))?;
+        validate_limit(self.limit)
+    }
+}
+
+impl SearchParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    /// or a `q` that is empty after trimming or made only of `-word` exclusions,
+    /// either of which would match every airport.
+    ///
+    /// Queries shorter than `min_query_length` characters after trimming are
+    /// rejected too, since they match large parts of the dataset, as are queries
+    /// longer than `MAX_QUERY_LENGTH` bytes.
+    pub fn validate(&self, min_query_length: usize) -> Result<(), ApiError> {
+        validate_query_length(&self.q)?;
+        let q = self.q.trim();
+        if q.is_empty() {
+            return Err(ApiError::BadRequest("q must not be empty".into()));
+        }
+        if SearchQuery::new(q).text.is_empty() {
+            return Err(ApiError::BadRequest(
+                "q must contain at least one word that is not a -word exclusion".into(),
+            ));
+        }
+        if q.chars().count() < min_query_length {
+            return Err(ApiError::BadRequest(format!(
+                "q must be at least {min_query_length} characters"
+            )));
+        }
+        validate_regions(self.region.iter().flat_map(|value| value.split(',')))?;
+        self.origin()?;
+        validate_limit(self.limit)
+    }
+
+    /// The reference point given by `lat` and `lon`, if any
+    ///
+    /// # Errors
+    /// `ApiError::BadRequest` (400) if only one of `lat` and `lon` is given, or
+    /// the point is out of range
+    pub fn origin(&self) -> Result<Option<Origin>, ApiError> {
+        match (self.lat, self.lon) {
+            (Some(lat), Some(lon)) => {
+                let (lat, lon) = validate_point(lat, lon)?;
+                Ok(Some(Origin { lat, lon }))
+            }
+            (None, None) => Ok(None),
+            _ => Err(ApiError::BadRequest(
+                "lat and lon must be given together".into(),
+            )),
+        }
+    }
+}
+
+/// JSON body of POST /airports/search: the parameters of GET /airports/search
+/// with lists as arrays, plus elevation bounds and a bounding box
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SearchBody {
+    /// Search query string (case-insensitive partial matches, surrounding whitespace ignored);
+    /// `-word` excludes airports containing `word`
+    pub q: String,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+    /// Result ordering: `relevance` (default without `lat`/`lon`), `name`,
+    /// `icao`, or `distance` (default with them, and requires them)
+    pub sort: Option<String>,
+    /// Matching strategy: `contains` (default), `exact`, `prefix`, or `phonetic`
+    pub mode: Option<String>,
+    /// Whether `all` (default) or `any` of the words in `q` must match
+    #[serde(rename = "match")]
+    pub token_match: Option<String>,
+    /// Fields to match `q` against: `icao`, `name`, `iata`, `city`, `keywords`
+    /// (default: all)
+    pub fields: Option<Vec<String>>,
+    /// Facility types to include (e.g., `["large_airport", "medium_airport"]`)
+    #[serde(rename = "type")]
+    pub kind: Option<Vec<String>>,
+    /// ISO country codes to include (e.g., `["US", "CA"]`)
+    pub country: Option<Vec<String>>,
+    /// ISO 3166-2 region codes to include (e.g., `["US-NY", "US-NJ"]`)
+    pub region: Option<Vec<String>>,
+    /// Continent codes to include (e.g., `["EU", "NA"]`)
+    pub continent: Option<Vec<String>>,
+    /// City names to include, which may contain commas (e.g., `["Washington, D.C."]`)
+    pub city: Option<Vec<String>>,
+    /// Only airports with (`true`) or without (`false`) scheduled service
+    pub scheduled: Option<bool>,
+    /// Latitude of a reference point in decimal degrees (-90 to 90); with
+    /// `lon`, adds each result's `distance_km` and makes `distance` the
+    /// default `sort`
+    pub lat: Option<f64>,
+    /// Longitude of the reference point in decimal degrees (-180 to 180)
+    pub lon: Option<f64>,
+    /// Drops airports without coordinates instead of ranking them last by
+    /// distance (default: false)
+    pub require_coordinates: Option<bool>,
+    /// Lowest elevation in feet to include
+    pub min_elevation: Option<i32>,
+    /// Highest elevation in feet to include
+    pub max_elevation: Option<i32>,
+    /// Box the airport's coordinates must lie in; `min_lon > max_lon` crosses
+    /// the antimeridian
+    pub bbox: Option<BoundingBox>,
+    /// Forces materializing every match before paginating (default: false)
+    pub count: Option<bool>,
+    /// Output format: `json`, `csv`, or `geojson`; overrides `Accept` negotiation
+    pub format: Option<String>,
+    /// Adds each result's relevance `score` to JSON output (default: false)
+    pub debug: Option<bool>,
+    /// Adds each result's matched `name` and `icao` byte ranges to JSON output
+    /// (default: false)
+    pub highlight: Option<bool>,
+    /// Answers 404 instead of an empty page when nothing matches (default: false)
+    pub strict: Option<bool>,
+    /// Airport fields to include in JSON output (default: all); unlike
+    /// `fields`, does not affect matching
+    pub select: Option<Vec<String>>,
+}
+
+impl SearchBody {
+    /// Checks the values `SearchParams::validate` checks, plus the elevation
+    /// range and the box's latitude order
+    pub fn validate(&self, min_query_length: usize) -> Result<(), ApiError> {
+        validate_elevation(self.min_elevation, self.max_elevation)?;
+        if self.bbox.is_some_and(|bbox| bbox.min_lat > bbox.max_lat) {
+            return Err(ApiError::BadRequest(
+                "bbox.min_lat must not be greater than bbox.max_lat".into(),
+            ));
+        }
+        validate_regions(self.region.iter().flatten().map(String::as_str))?;
+        self.options().validate(min_query_length)
+    }
+
+    /// Splits the body into the equivalent `SearchParams` without list
+    /// filters, and the `AirportFilter` built from all of its filters
+    pub fn into_search(self) -> (SearchParams, AirportFilter) {
+        let items = |values: &Option<Vec<String>>| {
+            values
+                .as_ref()
+                .and_then(|values| parse_items(values.iter().map(String::as_str)))
+        };
+        let filter = AirportFilter {
+            kinds: items(&self.kind),
+            countries: items(&self.country),
+            cities: items(&self.city),
+            min_elevation: self.min_elevation,
+            max_elevation: self.max_elevation,
+            continents: items(&self.continent),
+            regions: items(&self.region),
+            scheduled: self.scheduled,
+            bbox: self.bbox,
+            require_coordinates: self.require_coordinates.unwrap_or(false),
+        };
+        (self.options(), filter)
+    }
+
+    /// The body's query and output options as `SearchParams`, with `fields`
+    /// and `select` joined into their comma-separated query forms
+    fn options(&self) -> SearchParams {
+        SearchParams {
+            q: self.q.clone(),
+            limit: self.limit,
+            offset: self.offset,
+            sort: self.sort.clone(),
+            mode: self.mode.clone(),
+            token_match: self.token_match.clone(),
+            fields: self.fields.as_ref().map(|fields| fields.join(",")),
+            kind: None,
+            country: None,
+            region: None,
+            continent: None,
+            city: None,
+            scheduled: self.scheduled,
+            lat: self.lat,
+            lon: self.lon,
+            require_coordinates: self.require_coordinates,
+            count: self.count,
+            format: self.format.clone(),
+            debug: self.debug,
+            highlight: self.highlight,
+            strict: self.strict,
+            select: self.select.as_ref().map(|select| select.join(",")),
+        }
+    }
+}
+
+impl AutocompleteParams {
+    /// Checks for a blank or overlong prefix, and `limit=0`. A blank prefix
+    /// would match every airport.
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_query_length(&self.q)?;
+        if self.q.trim().is_empty() {
+            return Err(ApiError::BadRequest("q must not be empty".into()));
+        }
+        validate_limit(self.limit)
+    }
+}
+
+impl From<&PaginationParams> for AirportFilter {
+    fn from(params: &PaginationParams) -> Self {
+        AirportFilter {
+            kinds: parse_list(params.kind.as_deref()),
+            countries: parse_list(params.country.as_deref()),
+            cities: parse_list(params.city.as_deref()),
+            continents: parse_list(params.continent.as_deref()),
+            regions: parse_list(params.region.as_deref()),
+            scheduled: params.scheduled,
+            min_elevation: params.min_elevation,
+            max_elevation: params.max_elevation,
+            bbox: None,
+            require_coordinates: false,
+        }
+    }
+}
+
+impl From<&SearchParams> for AirportFilter {
+    fn from(params: &SearchParams) -> Self {
+        AirportFilter {
+            kinds: parse_list(params.kind.as_deref()),
+            countries: parse_list(params.country.as_deref()),
+            cities: parse_list(params.city.as_deref()),
+            continents: parse_list(params.continent.as_deref()),
+            regions: parse_list(params.region.as_deref()),
+            scheduled: params.scheduled,
+            require_coordinates: params.require_coordinates.unwrap_or(false),
+            ..AirportFilter::default()
+        }
+    }
+}
+
+/// Query parameters for cursor-based listing
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CursorParams {
+    /// Opaque cursor from a previous page's `next_cursor`; absent for the first page
+    pub cursor: Option<String>,
+    /// Maximum number of items to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Comma-separated facility types to include (e.g., `large_airport,medium_airport`)
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Comma-separated ISO country codes to include (e.g., `US,CA`)
+    pub country: Option<String>,
+    /// Comma-separated ISO 3166-2 region codes to include (e.g., `US-NY,US-NJ`)
+    pub region: Option<String>,
+    /// Comma-separated continent codes to include (e.g., `EU,NA`)
+    pub continent: Option<String>,
+    /// Comma-separated city names to include (e.g., `New York,Boston`)
+    pub city: Option<String>,
+    /// Only airports with (`true`) or without (`false`) scheduled service
+    pub scheduled: Option<bool>,
+}
+
+impl CursorParams {
+    /// Checks filter values that deserialize but are malformed, e.g. `region=NY`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_regions(self.region.iter().flat_map(|value| value.split(',')))
+    }
+}
+
+impl From<&CursorParams> for AirportFilter {
+    fn from(params: &CursorParams) -> Self {
+        AirportFilter {
+            kinds: parse_list(params.kind.as_deref()),
+            countries: parse_list(params.country.as_deref()),
+            cities: parse_list(params.city.as_deref()),
+            continents: parse_list(params.continent.as_deref()),
+            regions: parse_list(params.region.as_deref()),
+            scheduled: params.scheduled,
+            ..AirportFilter::default()
+        }
+    }
+}
+
+/// Query parameters for autocomplete lookups
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AutocompleteParams {
+    /// Prefix typed so far (case-insensitive, surrounding whitespace ignored)
+    pub q: String,
+    /// Maximum number of suggestions to return (1 to `MAX_PAGE_LIMIT`, default: 10)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for nearest-airport lookups
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NearestParams {
+    /// Latitude of the reference point in decimal degrees (-90 to 90)
+    pub lat: f64,
+    /// Longitude of the reference point in decimal degrees (-180 to 180)
+    pub lon: f64,
+    /// Unit of the reported `distance`: `km`, `nm`, or `mi` (default: `km`)
+    pub unit: Option<String>,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+}
+
+impl NearestParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_limit(self.limit)
+    }
+}
+
+/// Query parameters for radius searches
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RadiusParams {
+    /// Latitude of the circle's center in decimal degrees (-90 to 90)
+    pub lat: f64,
+    /// Longitude of the circle's center in decimal degrees (-180 to 180)
+    pub lon: f64,
+    /// Radius of the circle in kilometers (non-negative)
+    pub radius_km: f64,
+    /// Unit of the reported `distance`: `km`, `nm`, or `mi` (default: `km`)
+    pub unit: Option<String>,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+}
+
+impl RadiusParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_limit(self.limit)
+    }
+}
+
+/// Query parameters for the distance between two airports
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DistanceParams {
+    /// ICAO code of the departure airport (case-insensitive)
+    pub from: String,
+    /// ICAO code of the arrival airport (case-insensitive)
+    pub to: String,
+    /// Unit of the reported `distance`: `km`, `nm`, or `mi` (default: `km`)
+    pub unit: Option<String>,
+}
+
+/// Query parameters for geohash prefix lookups
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GeohashParams {
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+}
+
+impl GeohashParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_limit(self.limit)
+    }
+}
+
+/// Query parameters for bounding-box filtering
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BoundingBoxParams {
+    /// Southern edge of the box in decimal degrees
+    pub min_lat: f64,
+    /// Western edge of the box in decimal degrees
+    pub min_lon: f64,
+    /// Northern edge of the box in decimal degrees
+    pub max_lat: f64,
+    /// Eastern edge of the box in decimal degrees
+    pub max_lon: f64,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+}
+
+impl BoundingBoxParams {
+    /// Checks parameter values that deserialize but make no sense, e.g. `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_limit(self.limit)
+    }
+
+    /// Returns the box the parameters describe
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox {
+            min_lat: self.min_lat,
+            min_lon: self.min_lon,
+            max_lat: self.max_lat,
+            max_lon: self.max_lon,
+        }
+    }
+
+    /// Checks whether a point lies inside the box (edges inclusive).
+    ///
+    /// When `min_lon > max_lon` the box is treated as crossing the antimeridian,
+    /// covering longitudes from `min_lon` east to 180 and from -180 east to `max_lon`.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        self.bounds().contains(lat, lon)
+    }
+}
+
+/// Query parameters for fuzzy search
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FuzzyParams {
+    /// Search query string (case-insensitive, typo-tolerant)
+    pub q: String,
+    /// Maximum edit distance for a result to be included (default: 2)
+    pub max_distance: Option<usize>,
+    /// Maximum number of results to return (1 to `MAX_PAGE_LIMIT`, default: `DEFAULT_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Starting offset for paginated results (default: 0)
+    pub offset: Option<usize>,
+}
+
+impl FuzzyParams {
+    /// Checks for a `q` longer than `MAX_QUERY_LENGTH` bytes, which would make
+    /// every edit distance computation proportionally slower, and `limit=0`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_query_length(&self.q)?;
+        validate_limit(self.limit)
+    }
+}
+
+/// Query parameters for random sampling
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RandomParams {
+    /// Number of distinct airports to return (1 to `MAX_PAGE_LIMIT`, default: 1)
+    pub count: Option<usize>,
+    /// Seed making the selection reproducible for the same dataset (default: random)
+    pub seed: Option<u64>,
+}
+
+impl RandomParams {
+    /// Checks for `count=0`, which can only ever produce an empty sample
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.count == Some(0) {
+            return Err(ApiError::BadRequest("count must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
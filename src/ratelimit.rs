@@ -0,0 +1,116 @@
+//! Per-client token-bucket rate limiting.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Number of client buckets tracked before idle ones are pruned
+pub const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket of a single client
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Requests the client may still make right away
+    tokens: f64,
+    /// When `tokens` was last brought up to date
+    updated: Instant,
+}
+
+/// Token-bucket rate limiter keyed by client IP address.
+///
+/// Each client starts with `burst` tokens. Every request spends one, and tokens
+/// refill continuously at `rate` per second up to `burst`. A request finding
+/// less than one token is rejected along with the time until one is available.
+///
+/// # Memory
+/// One bucket (about 40 bytes plus hash table overhead) per client seen. Once
+/// more than `MAX_TRACKED_CLIENTS` are tracked, buckets that have refilled
+/// completely are dropped, since they behave exactly like fresh ones.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `rate` requests per second per client, with
+    /// bursts of up to `burst` requests.
+    ///
+    /// # Panics
+    /// If `rate` is not positive, or `burst` is less than 1
+    pub fn new(rate: f64, burst: u32) -> Self {
+        assert!(rate > 0.0, "rate limit must be positive");
+        assert!(burst >= 1, "rate limit burst must be at least 1");
+        RateLimiter {
+            rate,
+            burst: f64::from(burst),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Spends one token from `client`'s bucket as of `now`.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the request is allowed
+    /// - `Err(wait)` with the time until the next token if it is over the limit
+    pub fn acquire(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Tokens a bucket holds at `now`, without updating it
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests bursts, refill over time, and that clients have separate buckets
+    #[actix_web::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+        let alice: IpAddr = "192.0.2.1".parse().unwrap();
+        let bob: IpAddr = "2001:db8::1".parse().unwrap();
+
+        for _ in 0..3 {
+            assert!(limiter.acquire(alice, start).is_ok());
+        }
+        let wait = limiter.acquire(alice, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter.acquire(bob, start).is_ok());
+
+        // Half a second at 2/s refills exactly one token
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.acquire(alice, later).is_ok());
+        assert!(limiter.acquire(alice, later).is_err());
+
+        // Refill is capped at the burst size
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.acquire(alice, much_later).is_ok());
+        }
+        assert!(limiter.acquire(alice, much_later).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Seeded random sampling without replacement.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// SplitMix64 pseudo-random generator.
+///
+/// Not cryptographically secure, but fast, well distributed, and fully
+/// specified by its seed, so a given seed yields the same sequence on every
+/// platform and release.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Creates a generator producing the sequence identified by `seed`
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    /// Returns the next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random value in `0..=max`, using the widening multiply method
+    fn up_to(&mut self, max: usize) -> usize {
+        let range = max as u128 + 1;
+        ((u128::from(self.next_u64()) * range) >> 64) as usize
+    }
+}
+
+/// Returns a seed that differs between calls and processes
+pub fn random_seed() -> u64 {
+    // Every `RandomState` is keyed from OS randomness, advanced per instance
+    RandomState::new().build_hasher().finish()
+}
+
+/// Picks `count` distinct indices from `0..len` in random order.
+///
+/// Uses Floyd's algorithm followed by a Fisher-Yates shuffle, so every ordered
+/// selection is equally likely and the work is `O(count²)` regardless of `len`.
+/// `count` is clamped to `len`.
+pub fn sample_indices(len: usize, count: usize, seed: u64) -> Vec<usize> {
+    let count = count.min(len);
+    let mut rng = SplitMix64::new(seed);
+    let mut picked = Vec::with_capacity(count);
+    for upper in len - count..len {
+        let candidate = rng.up_to(upper);
+        picked.push(if picked.contains(&candidate) {
+            upper
+        } else {
+            candidate
+        });
+    }
+    for i in (1..picked.len()).rev() {
+        picked.swap(i, rng.up_to(i));
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that samples are distinct, in range, clamped, and reproducible per seed
+    #[actix_web::test]
+    async fn test_sample_indices() {
+        let sample = sample_indices(100, 10, 42);
+        assert_eq!(sample.len(), 10);
+        assert!(sample.iter().all(|&index| index < 100));
+        let mut unique = sample.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 10);
+
+        assert_eq!(sample_indices(100, 10, 42), sample);
+        assert_ne!(sample_indices(100, 10, 43), sample);
+
+        let mut all = sample_indices(5, 50, 7);
+        all.sort_unstable();
+        assert_eq!(all, [0, 1, 2, 3, 4]);
+        assert!(sample_indices(0, 3, 7).is_empty());
+    }
+}
@@ -0,0 +1,1370 @@
+//! Text search, attribute filtering, and aggregation over airports.
+
+use crate::error::ApiError;
+use crate::geo::{haversine_km, BoundingBox};
+use crate::model::{Airport, KEYWORD_SEPARATOR};
+#[cfg(feature = "fuzzy")]
+use crate::trigram::TrigramIndex;
+use icu_normalizer::DecomposingNormalizer;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Selects the `k` smallest items from a parallel iterator without collecting
+/// all of them, using per-thread bounded max-heaps merged in the reduce step.
+///
+/// # Returns
+/// - The selected items in ascending order
+/// - The total number of items the iterator produced
+///
+/// Items are compared as whole tuples, so callers pairing a sort key with a
+/// source index get the same order as a stable sort by key.
+pub fn smallest_k<K, I>(items: I, k: usize) -> (Vec<K>, usize)
+where
+    K: Ord + Send,
+    I: ParallelIterator<Item = K>,
+{
+    let (heap, count) = items
+        .fold(
+            || (BinaryHeap::new(), 0),
+            |(mut heap, count), item| {
+                heap.push(item);
+                if heap.len() > k {
+                    heap.pop();
+                }
+                (heap, count + 1)
+            },
+        )
+        .reduce(
+            || (BinaryHeap::new(), 0),
+            |(mut left, left_count), (right, right_count)| {
+                for item in right {
+                    left.push(item);
+                    if left.len() > k {
+                        left.pop();
+                    }
+                }
+                (left, left_count + right_count)
+            },
+        );
+    (heap.into_sorted_vec(), count)
+}
+
+/// Computes the Levenshtein edit distance between two strings.
+///
+/// Operates on Unicode scalar values rather than bytes, so multibyte characters
+/// count as a single edit. Uses a two-row dynamic programming table.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut curr = vec![0; b_chars.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b_chars.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b_chars.len()]
+}
+
+/// American Soundex code of a word: its first letter, uppercased, followed by
+/// three digits, e.g. `R163` for both "robert" and "rupert".
+///
+/// Consonants that sound alike share a digit, vowels and repeated digits are
+/// dropped, and `h` and `w` do not separate letters with the same digit. Only
+/// ASCII letters are considered, so words should be folded with `fold_text`
+/// first. Returns `None` for words without any letter, such as numbers.
+pub fn soundex(word: &str) -> Option<[u8; 4]> {
+    fn digit(letter: u8) -> Option<u8> {
+        match letter {
+            b'b' | b'f' | b'p' | b'v' => Some(b'1'),
+            b'c' | b'g' | b'j' | b'k' | b'q' | b's' | b'x' | b'z' => Some(b'2'),
+            b'd' | b't' => Some(b'3'),
+            b'l' => Some(b'4'),
+            b'm' | b'n' => Some(b'5'),
+            b'r' => Some(b'6'),
+            _ => None,
+        }
+    }
+
+    let mut letters = word
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|letter| letter.to_ascii_lowercase());
+    let first = letters.next()?;
+    let mut code = [first.to_ascii_uppercase(), b'0', b'0', b'0'];
+    let mut len = 1;
+    let mut last = digit(first);
+    for letter in letters {
+        match digit(letter) {
+            Some(current) if last != Some(current) => {
+                code[len] = current;
+                len += 1;
+                if len == code.len() {
+                    break;
+                }
+                last = Some(current);
+            }
+            Some(_) => {}
+            None if matches!(letter, b'h' | b'w') => {}
+            None => last = None,
+        }
+    }
+    Some(code)
+}
+
+/// Soundex codes of the whitespace-separated words of folded `text`, skipping
+/// words without one
+pub(crate) fn soundex_words(text: &str) -> Vec<[u8; 4]> {
+    text.split_whitespace().filter_map(soundex).collect()
+}
+
+/// Lowercases text and strips diacritics, so that "Zürich" and "zurich" compare equal.
+///
+/// Characters are decomposed to NFD and their combining marks dropped. Letters that
+/// carry no decomposable accent, such as "ø" or "ß", are mapped to their usual ASCII
+/// spelling. ASCII input is only lowercased.
+pub fn fold_text(text: &str) -> String {
+    let lower = text.to_lowercase();
+    if lower.is_ascii() {
+        return lower;
+    }
+
+    let mut folded = String::with_capacity(lower.len());
+    for c in DecomposingNormalizer::new_nfd().normalize_iter(lower.chars()) {
+        match c {
+            '\u{0300}'..='\u{036f}'
+            | '\u{1ab0}'..='\u{1aff}'
+            | '\u{1dc0}'..='\u{1dff}'
+            | '\u{20d0}'..='\u{20ff}'
+            | '\u{fe20}'..='\u{fe2f}' => {}
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'þ' => folded.push_str("th"),
+            'ø' => folded.push('o'),
+            'ł' => folded.push('l'),
+            'đ' | 'ð' => folded.push('d'),
+            'ı' => folded.push('i'),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Splits a comma-separated query value into trimmed entries folded with `fold_text`.
+///
+/// Returns `None` when the value is absent or contains no non-empty entries,
+/// so that such filters are treated as inactive.
+pub fn parse_list(value: Option<&str>) -> Option<Vec<String>> {
+    parse_items(value?.split(','))
+}
+
+/// Folds each of `items` with `fold_text` after trimming, dropping blank ones;
+/// `None` when none remain.
+pub fn parse_items<'a>(items: impl IntoIterator<Item = &'a str>) -> Option<Vec<String>> {
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|item| fold_text(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+/// Checks a field against an optional list of lowercase values, case-insensitively.
+/// An absent list matches everything.
+fn matches_any(values: &Option<Vec<String>>, field: &str) -> bool {
+    values
+        .as_ref()
+        .is_none_or(|values| values.iter().any(|value| value.eq_ignore_ascii_case(field)))
+}
+
+/// Attribute filters shared by the list and search endpoints.
+///
+/// Each populated field narrows the result set; within a field, any listed value matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AirportFilter {
+    /// Lowercase facility types to include
+    pub kinds: Option<Vec<String>>,
+    /// Lowercase ISO country codes to include
+    pub countries: Option<Vec<String>>,
+    /// Lowercase city names to include
+    pub cities: Option<Vec<String>>,
+    /// Lowest elevation in feet to include
+    pub min_elevation: Option<i32>,
+    /// Highest elevation in feet to include
+    pub max_elevation: Option<i32>,
+    /// Lowercase continent codes to include
+    pub continents: Option<Vec<String>>,
+    /// Lowercase ISO 3166-2 region codes to include
+    pub regions: Option<Vec<String>>,
+    /// Required `scheduled_service` value
+    pub scheduled: Option<bool>,
+    /// Box the airport's coordinates must lie in
+    pub bbox: Option<BoundingBox>,
+    /// Whether airports without coordinates are dropped
+    pub require_coordinates: bool,
+}
+
+impl AirportFilter {
+    /// Returns true if at least one filter is set
+    pub fn is_active(&self) -> bool {
+        self.kinds.is_some()
+            || self.regions.is_some()
+            || self.continents.is_some()
+            || self.countries.is_some()
+            || self.cities.is_some()
+            || self.min_elevation.is_some()
+            || self.max_elevation.is_some()
+            || self.scheduled.is_some()
+            || self.bbox.is_some()
+            || self.require_coordinates
+    }
+
+    /// Checks whether an airport satisfies every populated filter.
+    ///
+    /// Airports without a known elevation are excluded whenever either elevation
+    /// bound is set, and airports without coordinates whenever `bbox` or
+    /// `require_coordinates` is.
+    pub fn matches(&self, airport: &Airport) -> bool {
+        matches_any(&self.kinds, &airport.kind)
+            && matches_any(&self.countries, &airport.country)
+            && matches_any(&self.cities, airport.lower_city.as_deref().unwrap_or(""))
+            && self.matches_elevation(airport.elevation_ft)
+            && matches_any(&self.continents, &airport.continent)
+            && matches_any(&self.regions, &airport.region)
+            && self
+                .scheduled
+                .is_none_or(|scheduled| airport.scheduled_service == scheduled)
+            && self
+                .bbox
+                .is_none_or(|bbox| match (airport.latitude, airport.longitude) {
+                    (Some(lat), Some(lon)) => bbox.contains(lat, lon),
+                    _ => false,
+                })
+            && (!self.require_coordinates
+                || (airport.latitude.is_some() && airport.longitude.is_some()))
+    }
+
+    /// Checks an elevation against the inclusive `min_elevation..=max_elevation` range
+    fn matches_elevation(&self, elevation_ft: Option<i32>) -> bool {
+        if self.min_elevation.is_none() && self.max_elevation.is_none() {
+            return true;
+        }
+        elevation_ft.is_some_and(|elevation| {
+            self.min_elevation.is_none_or(|min| elevation >= min)
+                && self.max_elevation.is_none_or(|max| elevation <= max)
+        })
+    }
+}
+
+/// Strategy used to match the search query against airport fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MatchMode {
+    /// Field contains the query anywhere
+    #[default]
+    Contains,
+    /// Field equals the query
+    Exact,
+    /// Field starts with the query
+    Prefix,
+    /// A word of the name sounds like the query word, by `soundex` code
+    Phonetic,
+}
+
+impl MatchMode {
+    /// Checks a lowercase field against a lowercase query using this strategy.
+    ///
+    /// `Phonetic` requires every word of the query to share its `soundex`
+    /// code with some word of the field.
+    pub fn matches(self, field: &str, query: &str) -> bool {
+        match self {
+            MatchMode::Contains => field.contains(query),
+            MatchMode::Exact => field == query,
+            MatchMode::Prefix => field.starts_with(query),
+            MatchMode::Phonetic => {
+                let codes = soundex_words(field);
+                query
+                    .split_whitespace()
+                    .all(|word| soundex(word).is_some_and(|code| codes.contains(&code)))
+            }
+        }
+    }
+
+    /// Checks a lowercase query against whichever of an airport's ICAO code,
+    /// name, IATA code, city, and keywords `fields` selects, keywords one by one.
+    ///
+    /// `Phonetic` only checks a single query word against the name's Soundex
+    /// codes computed at load.
+    fn matches_airport(self, airport: &Airport, query: &str, fields: SearchFields) -> bool {
+        if self == MatchMode::Phonetic {
+            return fields.name
+                && soundex(query).is_some_and(|code| airport.name_soundex.contains(&code));
+        }
+        (fields.icao && self.matches(&airport.lower_icao, query))
+            || (fields.name && self.matches(&airport.lower_name, query))
+            || (fields.iata
+                && airport
+                    .lower_iata
+                    .as_deref()
+                    .is_some_and(|iata| self.matches(iata, query)))
+            || (fields.city
+                && airport
+                    .lower_city
+                    .as_deref()
+                    .is_some_and(|city| self.matches(city, query)))
+            || (fields.keywords
+                && airport
+                    .lower_keywords
+                    .split(KEYWORD_SEPARATOR)
+                    .any(|keyword| self.matches(keyword, query)))
+    }
+}
+
+/// Airport fields the query text is matched against. Defaults to all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SearchFields {
+    /// ICAO code
+    pub icao: bool,
+    /// Airport name
+    pub name: bool,
+    /// IATA code, where assigned
+    pub iata: bool,
+    /// Served city, where known
+    pub city: bool,
+    /// Each search keyword
+    pub keywords: bool,
+}
+
+impl SearchFields {
+    /// Selects no field, as a starting point for building a mask
+    const NONE: SearchFields = SearchFields {
+        icao: false,
+        name: false,
+        iata: false,
+        city: false,
+        keywords: false,
+    };
+}
+
+impl Default for SearchFields {
+    fn default() -> Self {
+        SearchFields {
+            icao: true,
+            name: true,
+            iata: true,
+            city: true,
+            keywords: true,
+        }
+    }
+}
+
+impl FromStr for SearchFields {
+    type Err = ApiError;
+
+    /// Parses a comma-separated `fields` query value, rejecting unknown fields
+    /// with `ApiError::BadRequest`. An empty list selects every field.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut fields = SearchFields::NONE;
+        for field in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match field {
+                "icao" => fields.icao = true,
+                "name" => fields.name = true,
+                "iata" => fields.iata = true,
+                "city" => fields.city = true,
+                "keywords" => fields.keywords = true,
+                other => {
+                    return Err(ApiError::BadRequest(format!(
+                        "invalid field '{other}', expected one of: icao, name, iata, city, keywords"
+                    )))
+                }
+            }
+        }
+        Ok(if fields == SearchFields::NONE {
+            SearchFields::default()
+        } else {
+            fields
+        })
+    }
+}
+
+impl FromStr for MatchMode {
+    type Err = ApiError;
+
+    /// Parses a `mode` query value, rejecting unknown strategies with `ApiError::BadRequest`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "contains" => Ok(MatchMode::Contains),
+            "exact" => Ok(MatchMode::Exact),
+            "prefix" => Ok(MatchMode::Prefix),
+            "phonetic" => Ok(MatchMode::Phonetic),
+            other => Err(ApiError::BadRequest(format!(
+                "invalid mode '{other}', expected one of: contains, exact, prefix, phonetic"
+            ))),
+        }
+    }
+}
+
+/// How many words of a multi-word `Contains` query an airport must match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TokenMatch {
+    /// Every word must match
+    #[default]
+    All,
+    /// At least one word must match
+    Any,
+}
+
+impl FromStr for TokenMatch {
+    type Err = ApiError;
+
+    /// Parses a `match` query value, rejecting unknown semantics with `ApiError::BadRequest`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "all" => Ok(TokenMatch::All),
+            "any" => Ok(TokenMatch::Any),
+            other => Err(ApiError::BadRequest(format!(
+                "invalid match '{other}', expected one of: all, any"
+            ))),
+        }
+    }
+}
+
+/// Ordering applied to search results before pagination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SortOrder {
+    /// Highest `relevance_score` first, then earliest match position of any
+    /// query word, file order on ties
+    #[default]
+    Relevance,
+    /// Alphabetical by lowercase name
+    Name,
+    /// Alphabetical by lowercase ICAO code
+    Icao,
+    /// Nearest to the search's `origin` first, airports without coordinates
+    /// last, file order on ties
+    Distance,
+}
+
+/// Score for a query word equal to the ICAO or IATA code
+pub const SCORE_EXACT_CODE: u32 = 100;
+
+/// Score for a query word starting the ICAO code, IATA code, name, or a name,
+/// city, or keyword word
+pub const SCORE_PREFIX: u32 = 10;
+
+/// Score for a query word found anywhere else in those fields, the city, or the keywords
+pub const SCORE_SUBSTRING: u32 = 1;
+
+/// Scores how well an airport matches a lowercase query.
+///
+/// Each whitespace-separated query word earns the weight of its strongest
+/// match — `SCORE_EXACT_CODE`, `SCORE_PREFIX`, or `SCORE_SUBSTRING` — and the
+/// word scores are summed. Words that do not match contribute nothing.
+pub fn relevance_score(airport: &Airport, query: &str) -> u32 {
+    query
+        .split_whitespace()
+        .map(|token| token_score(airport, token))
+        .sum()
+}
+
+/// Scores a single query word against an airport
+fn token_score(airport: &Airport, token: &str) -> u32 {
+    let codes = std::iter::once(airport.lower_icao.as_str()).chain(airport.lower_iata.as_deref());
+    let city = airport.lower_city.as_deref().unwrap_or("");
+    let mut words = airport
+        .lower_name
+        .split(|c: char| !c.is_alphanumeric())
+        .chain(city.split(|c: char| !c.is_alphanumeric()))
+        .chain(airport.lower_keywords.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty());
+
+    if codes.clone().any(|code| code == token) {
+        SCORE_EXACT_CODE
+    } else if codes.clone().any(|code| code.starts_with(token))
+        || airport.lower_name.starts_with(token)
+        || words.any(|word| word.starts_with(token))
+    {
+        SCORE_PREFIX
+    } else if codes.clone().any(|code| code.contains(token))
+        || airport.lower_name.contains(token)
+        || city.contains(token)
+        || airport.lower_keywords.contains(token)
+    {
+        SCORE_SUBSTRING
+    } else {
+        0
+    }
+}
+
+/// Comparable key produced by a `SortOrder` for a single airport
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum SortKey<'a> {
+    /// Highest score first, then earliest match position; a `None` position
+    /// only occurs for non-matches
+    Relevance(Reverse<u32>, Option<usize>),
+    /// Lowercase text field
+    Text(&'a str),
+    /// Whether the distance is unknown, then the `f64::to_bits` of the
+    /// distance, which orders non-negative distances like their values
+    Distance(bool, u64),
+}
+
+impl SortOrder {
+    /// Computes the sort key for an airport matched against a lowercase query.
+    ///
+    /// Relevance orders by `relevance_score`, breaking ties by the earliest
+    /// position at which any whitespace-separated word of the query occurs.
+    /// Distance orders by `Origin::distance_km`, treating every distance as
+    /// unknown without an origin.
+    pub(crate) fn key<'a>(
+        self,
+        airport: &'a Airport,
+        query: &str,
+        origin: Option<Origin>,
+    ) -> SortKey<'a> {
+        match self {
+            SortOrder::Relevance => SortKey::Relevance(
+                Reverse(relevance_score(airport, query)),
+                query
+                    .split_whitespace()
+                    .flat_map(|token| {
+                        [
+                            airport.lower_icao.find(token),
+                            airport.lower_name.find(token),
+                            airport
+                                .lower_iata
+                                .as_deref()
+                                .and_then(|iata| iata.find(token)),
+                            airport
+                                .lower_city
+                                .as_deref()
+                                .and_then(|city| city.find(token)),
+                            airport.lower_keywords.find(token),
+                        ]
+                    })
+                    .flatten()
+                    .min(),
+            ),
+            SortOrder::Name => SortKey::Text(&airport.lower_name),
+            SortOrder::Icao => SortKey::Text(&airport.lower_icao),
+            SortOrder::Distance => {
+                let distance = origin.and_then(|origin| origin.distance_km(airport));
+                SortKey::Distance(distance.is_none(), distance.map_or(0, f64::to_bits))
+            }
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = ApiError;
+
+    /// Parses a `sort` query value, rejecting unknown orderings with `ApiError::BadRequest`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "relevance" => Ok(SortOrder::Relevance),
+            "name" => Ok(SortOrder::Name),
+            "icao" => Ok(SortOrder::Icao),
+            "distance" => Ok(SortOrder::Distance),
+            other => Err(ApiError::BadRequest(format!(
+                "invalid sort '{other}', expected one of: relevance, name, icao, distance"
+            ))),
+        }
+    }
+}
+
+/// Folds `text` like `fold_text`, one character at a time, recording for each
+/// folded byte the byte range of the original character it came from.
+///
+/// Characters folding to nothing, such as combining marks, are attributed to
+/// the preceding character, so a range never ends between a letter and its accent.
+fn fold_with_origins(text: &str) -> (String, Vec<(usize, usize)>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    let mut buf = [0; 4];
+    for (start, c) in text.char_indices() {
+        let end = start + c.len_utf8();
+        let piece = fold_text(c.encode_utf8(&mut buf));
+        if piece.is_empty() {
+            let owner = origins.last().map(|&(owner, _)| owner);
+            for origin in origins.iter_mut().rev() {
+                if Some(origin.0) != owner {
+                    break;
+                }
+                origin.1 = end;
+            }
+        }
+        origins.extend(std::iter::repeat_n((start, end), piece.len()));
+        folded.push_str(&piece);
+    }
+    (folded, origins)
+}
+
+/// Finds where a folded query matches `text`, as sorted, non-overlapping
+/// `[start, end)` byte ranges into `text` itself.
+///
+/// The match runs on folded text, as search does, and each range is widened to
+/// whole characters of `text`, so both ends are always UTF-8 char boundaries.
+/// In `Contains` mode every occurrence of every whitespace-separated word is
+/// found; `Exact` and `Prefix` match the whole query at the start only.
+/// `Phonetic` finds the whole words of `text` that sound like a query word.
+pub fn match_ranges(text: &str, query: &str, mode: MatchMode) -> Vec<[usize; 2]> {
+    if mode == MatchMode::Phonetic {
+        let codes = soundex_words(query);
+        return text
+            .split_whitespace()
+            .filter(|word| soundex(&fold_text(word)).is_some_and(|code| codes.contains(&code)))
+            .map(|word| {
+                let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                [start, start + word.len()]
+            })
+            .collect();
+    }
+    let (folded, origins) = fold_with_origins(text);
+    let mut hits: Vec<(usize, usize)> = match mode {
+        MatchMode::Contains => query
+            .split_whitespace()
+            .flat_map(|word| {
+                folded
+                    .match_indices(word)
+                    .map(move |(start, _)| (start, start + word.len()))
+            })
+            .collect(),
+        mode if !query.is_empty() && mode.matches(&folded, query) => vec![(0, query.len())],
+        _ => Vec::new(),
+    };
+    hits.sort_unstable();
+
+    let mut ranges: Vec<[usize; 2]> = Vec::with_capacity(hits.len());
+    for (start, end) in hits {
+        let range = [origins[start].0, origins[end - 1].1];
+        match ranges.last_mut() {
+            Some(last) if range[0] <= last[1] => last[1] = last[1].max(range[1]),
+            _ => ranges.push(range),
+        }
+    }
+    ranges
+}
+
+/// Where the query matched an airport's `name` and `icao`, as byte ranges from
+/// `match_ranges`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Highlight {
+    /// `[start, end)` byte ranges into `name`
+    pub name: Vec<[usize; 2]>,
+    /// `[start, end)` byte ranges into `icao`
+    pub icao: Vec<[usize; 2]>,
+}
+
+/// Airport with optional per-query annotations, for `debug=true` and
+/// `highlight=true` search output. Serializes as the airport's fields plus
+/// whichever of `score` and `highlight` are present.
+#[derive(Debug, Serialize)]
+pub struct AnnotatedAirport<'a> {
+    /// Referenced airport record
+    #[serde(flatten)]
+    pub airport: &'a Airport,
+    /// `relevance_score` of the airport for the query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
+    /// Matched spans of the airport's name and ICAO code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<Highlight>,
+    /// Great-circle distance in kilometers from the search's `origin`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_km: Option<f64>,
+}
+
+impl Borrow<Airport> for AnnotatedAirport<'_> {
+    fn borrow(&self) -> &Airport {
+        self.airport
+    }
+}
+
+/// Reference point of a search whose results carry `distance_km` and may be
+/// ordered by `SortOrder::Distance`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Origin {
+    /// Latitude in decimal degrees
+    pub lat: f64,
+    /// Longitude in decimal degrees
+    pub lon: f64,
+}
+
+impl Origin {
+    /// Great-circle distance in kilometers to an airport, or `None` if it has
+    /// no coordinates
+    pub fn distance_km(self, airport: &Airport) -> Option<f64> {
+        let point = (airport.latitude?, airport.longitude?);
+        Some(haversine_km((self.lat, self.lon), point))
+    }
+}
+
+/// Origins are validated query values, never NaN, so equality is total and
+/// origins can key the search cache
+impl Eq for Origin {}
+
+impl Hash for Origin {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // `+ 0.0` maps -0.0 to 0.0, which compares equal to it
+        (self.lat + 0.0).to_bits().hash(state);
+        (self.lon + 0.0).to_bits().hash(state);
+    }
+}
+
+/// A text search over airports: the query matched against ICAO codes, IATA
+/// codes, and names, narrowed by attribute filters and ordered by `sort`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SearchQuery {
+    /// Trimmed query text, folded with `fold_text`, without excluded words
+    pub text: String,
+    /// Folded words, given as `-word`, that no result may contain
+    pub excluded: Vec<String>,
+    /// Matching strategy applied to each searchable field
+    pub mode: MatchMode,
+    /// Fields the query text is matched against
+    pub fields: SearchFields,
+    /// Whether every query word or any one must match, in `Contains` mode
+    pub tokens: TokenMatch,
+    /// Ordering of the results
+    pub sort: SortOrder,
+    /// Attribute filters every result must satisfy
+    pub filter: AirportFilter,
+    /// Point results are measured from, for `distance_km` and `Distance` order
+    pub origin: Option<Origin>,
+}
+
+impl SearchQuery {
+    /// Creates a substring search for `text` (case- and diacritic-insensitive,
+    /// surrounding whitespace ignored) with relevance ordering and no attribute filters.
+    ///
+    /// Words prefixed with `-`, such as `-heliport`, become `excluded` rather
+    /// than part of `text`; a lone `-` is an ordinary word.
+    pub fn new(text: &str) -> Self {
+        let folded = fold_text(text.trim());
+        let (excluded, included): (Vec<&str>, Vec<&str>) = folded
+            .split_whitespace()
+            .partition(|word| word.len() > 1 && word.starts_with('-'));
+        if excluded.is_empty() {
+            return SearchQuery {
+                text: folded,
+                ..Default::default()
+            };
+        }
+        SearchQuery {
+            text: included.join(" "),
+            excluded: excluded.iter().map(|word| word[1..].to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks whether an airport passes the filters and matches the query text.
+    ///
+    /// In `Contains` mode the query is split on whitespace and every word must
+    /// occur in the ICAO code, IATA code, name, city, or keywords, in any order, so
+    /// `international kennedy` finds "John F. Kennedy International Airport".
+    /// With `TokenMatch::Any`, one matching word is enough, so
+    /// `heathrow gatwick` finds both airports. `Exact` and `Prefix` compare the
+    /// whole query against each field. Only the fields selected by `fields` are
+    /// checked; ranking is unaffected.
+    ///
+    /// In every mode, airports with an `excluded` word anywhere in those fields
+    /// are dropped.
+    pub fn matches(&self, airport: &Airport) -> bool {
+        if !self.filter.matches(airport)
+            || self
+                .excluded
+                .iter()
+                .any(|word| MatchMode::Contains.matches_airport(airport, word, self.fields))
+        {
+            return false;
+        }
+        let token_matches = |token| self.mode.matches_airport(airport, token, self.fields);
+        match (self.mode, self.tokens) {
+            (MatchMode::Contains | MatchMode::Phonetic, TokenMatch::All) => {
+                self.text.split_whitespace().all(token_matches)
+            }
+            (MatchMode::Contains | MatchMode::Phonetic, TokenMatch::Any) => {
+                self.text.split_whitespace().any(token_matches)
+            }
+            (mode, _) => mode.matches_airport(airport, &self.text, self.fields),
+        }
+    }
+
+    /// Computes the `relevance_score` of an airport for this query
+    pub fn score(&self, airport: &Airport) -> u32 {
+        relevance_score(airport, &self.text)
+    }
+
+    /// Finds where this query matched an airport's name and ICAO code.
+    /// Phonetic queries only ever match the name.
+    pub fn highlight(&self, airport: &Airport) -> Highlight {
+        Highlight {
+            name: match_ranges(&airport.name, &self.text, self.mode),
+            icao: match self.mode {
+                MatchMode::Phonetic => Vec::new(),
+                mode => match_ranges(&airport.icao, &self.text, mode),
+            },
+        }
+    }
+
+    /// Pairs airports with their scores when `score` is set, their
+    /// highlights when `highlight` is set, and their distances from `origin`
+    /// when there is one
+    pub fn annotated<'a>(
+        &self,
+        airports: &[&'a Airport],
+        score: bool,
+        highlight: bool,
+    ) -> Vec<AnnotatedAirport<'a>> {
+        airports
+            .iter()
+            .map(|&airport| AnnotatedAirport {
+                airport,
+                score: score.then(|| self.score(airport)),
+                highlight: highlight.then(|| self.highlight(airport)),
+                distance_km: self.origin.and_then(|origin| origin.distance_km(airport)),
+            })
+            .collect()
+    }
+
+    /// Returns every matching airport in `sort` order.
+    ///
+    /// Filtering runs in parallel with Rayon; the stable sort keeps file order
+    /// among equal keys for deterministic pages.
+    pub fn run<'a>(&self, airports: &'a [Airport]) -> Vec<&'a Airport> {
+        self.run_indices(airports)
+            .into_iter()
+            .map(|index| &airports[index])
+            .collect()
+    }
+
+    /// Same as `run`, but returns the matches as positions in `airports`, for
+    /// callers that cannot hold a borrow of it, such as `offload_search`
+    pub fn run_indices(&self, airports: &[Airport]) -> Vec<usize> {
+        let mut matches: Vec<usize> = airports
+            .par_iter()
+            .enumerate()
+            .filter(|(_, airport)| self.matches(airport))
+            .map(|(index, _)| index)
+            .collect();
+        matches.par_sort_by_cached_key(|&index| {
+            self.sort.key(&airports[index], &self.text, self.origin)
+        });
+        matches
+    }
+
+    /// Returns the first `window` matches in `sort` order together with the
+    /// total number of matches.
+    ///
+    /// Only the leading matches are kept, in bounded heaps, while the rest are
+    /// counted, so large match sets are neither collected nor sorted. The
+    /// result is identical to the prefix of `run`.
+    pub fn run_head<'a>(
+        &self,
+        airports: &'a [Airport],
+        window: usize,
+    ) -> (Vec<&'a Airport>, usize) {
+        let (head, total) = self.run_head_indices(airports, window);
+        let head = head.into_iter().map(|index| &airports[index]).collect();
+        (head, total)
+    }
+
+    /// Same as `run_head`, but returns the leading matches as positions in
+    /// `airports`, like `run_indices`
+    pub fn run_head_indices(&self, airports: &[Airport], window: usize) -> (Vec<usize>, usize) {
+        let (head, total) = smallest_k(
+            airports
+                .par_iter()
+                .enumerate()
+                .filter(|(_, airport)| self.matches(airport))
+                .map(|(index, airport)| (self.sort.key(airport, &self.text, self.origin), index)),
+            window,
+        );
+        (head.into_iter().map(|(_, index)| index).collect(), total)
+    }
+}
+
+/// Airport paired with its fuzzy-match edit distance.
+/// Serializes as the airport's fields plus `distance`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FuzzyMatch<'a> {
+    /// Referenced airport record
+    #[serde(flatten)]
+    pub airport: &'a Airport,
+    /// Smallest edit distance between the query and the airport's ICAO code, name, or name words
+    pub distance: usize,
+}
+
+/// Ranks airports by edit distance to a possibly misspelled query.
+///
+/// The query is trimmed and folded with `fold_text`. Each airport is scored in
+/// parallel as the smallest Levenshtein distance between the query and its
+/// lowercase ICAO code, full name, or any single name word.
+///
+/// # Returns
+/// Airports scoring at most `max_distance`, by ascending distance with ties
+/// broken by name for deterministic output
+pub fn fuzzy_search<'a>(
+    airports: &'a [Airport],
+    query: &str,
+    max_distance: usize,
+) -> Vec<FuzzyMatch<'a>> {
+    fuzzy_matches(airports, &fuzzy_rank(airports, query, max_distance))
+}
+
+/// Same as `fuzzy_search`, but returns `(position in airports, distance)`
+/// pairs, for callers that cannot hold a borrow of `airports`
+pub fn fuzzy_rank(airports: &[Airport], query: &str, max_distance: usize) -> Vec<(usize, usize)> {
+    let query = fold_text(query.trim());
+    let matches = airports
+        .par_iter()
+        .enumerate()
+        .filter_map(|(index, airport)| {
+            fuzzy_distance(airport, &query, max_distance).map(|distance| (index, distance))
+        })
+        .collect();
+    rank_fuzzy(airports, matches)
+}
+
+/// Same as `fuzzy_search`, but computes edit distances only for the airports
+/// `index` shortlists, falling back to every airport for queries too short to
+/// shortlist; see `TrigramIndex::candidates`. `index` must be built from
+/// `airports`.
+#[cfg(feature = "fuzzy")]
+pub fn fuzzy_search_indexed<'a>(
+    airports: &'a [Airport],
+    index: &TrigramIndex,
+    query: &str,
+    max_distance: usize,
+) -> Vec<FuzzyMatch<'a>> {
+    fuzzy_matches(
+        airports,
+        &fuzzy_rank_indexed(airports, index, query, max_distance),
+    )
+}
+
+/// Same as `fuzzy_search_indexed`, but returns `(position in airports,
+/// distance)` pairs like `fuzzy_rank`
+#[cfg(feature = "fuzzy")]
+pub fn fuzzy_rank_indexed(
+    airports: &[Airport],
+    index: &TrigramIndex,
+    query: &str,
+    max_distance: usize,
+) -> Vec<(usize, usize)> {
+    let Some(candidates) = index.candidates(query, max_distance) else {
+        return fuzzy_rank(airports, query, max_distance);
+    };
+    let query = fold_text(query.trim());
+    let matches = candidates
+        .par_iter()
+        .filter_map(|&candidate| {
+            fuzzy_distance(&airports[candidate], &query, max_distance)
+                .map(|distance| (candidate, distance))
+        })
+        .collect();
+    rank_fuzzy(airports, matches)
+}
+
+/// Pairs ranked `(position, distance)` results with the airports they refer to
+pub fn fuzzy_matches<'a>(
+    airports: &'a [Airport],
+    ranked: &[(usize, usize)],
+) -> Vec<FuzzyMatch<'a>> {
+    ranked
+        .iter()
+        .map(|&(index, distance)| FuzzyMatch {
+            airport: &airports[index],
+            distance,
+        })
+        .collect()
+}
+
+/// Scores an airport against a folded query for `fuzzy_search`, keeping it
+/// only within `max_distance`
+fn fuzzy_distance(airport: &Airport, query: &str, max_distance: usize) -> Option<usize> {
+    let distance = airport
+        .lower_name
+        .split_whitespace()
+        .map(|word| levenshtein(query, word))
+        .chain([
+            levenshtein(query, &airport.lower_icao),
+            levenshtein(query, &airport.lower_name),
+        ])
+        .min()?;
+    (distance <= max_distance).then_some(distance)
+}
+
+/// Orders `(position, distance)` fuzzy matches by ascending distance,
+/// breaking ties by name
+fn rank_fuzzy(airports: &[Airport], mut matches: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    matches.par_sort_by(|a, b| {
+        a.1.cmp(&b.1)
+            .then_with(|| airports[a.0].lower_name.cmp(&airports[b.0].lower_name))
+    });
+    matches
+}
+
+/// Counts airports per distinct key using a parallel Rayon fold/reduce.
+///
+/// # Returns
+/// `(key, count)` pairs sorted by descending count, with ties broken by key
+/// ascending for deterministic output
+pub fn count_by<'a, F>(airports: &'a [Airport], key: F) -> Vec<(&'a str, usize)>
+where
+    F: Fn(&'a Airport) -> &'a str + Sync,
+{
+    let counts = airports
+        .par_iter()
+        .fold(HashMap::new, |mut counts: HashMap<&str, usize>, airport| {
+            *counts.entry(key(airport)).or_default() += 1;
+            counts
+        })
+        .reduce(HashMap::new, |mut left, right| {
+            for (key, count) in right {
+                *left.entry(key).or_default() += count;
+            }
+            left
+        });
+
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::create_test_state;
+
+    /// Tests the Levenshtein helper, including multibyte characters
+    #[actix_web::test]
+    async fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("heathro", "heathrow"), 1);
+        assert_eq!(levenshtein("zurich", "zürich"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    /// Tests bounded selection order, tie-breaking, and counting
+    #[actix_web::test]
+    async fn test_smallest_k() {
+        let items = vec![(3, 0), (1, 1), (2, 2), (1, 3), (5, 4)];
+        let (head, count) = smallest_k(items.clone().into_par_iter(), 3);
+        assert_eq!(head, [(1, 1), (1, 3), (2, 2)]);
+        assert_eq!(count, 5);
+
+        let (head, count) = smallest_k(items.into_par_iter(), 0);
+        assert!(head.is_empty());
+        assert_eq!(count, 5);
+    }
+
+    /// Tests library-level search filtering, ordering, and the bounded head
+    #[actix_web::test]
+    async fn test_search_query() {
+        let airports = create_test_state().snapshot().airports.clone();
+
+        let search = SearchQuery::new("International");
+        let icaos: Vec<&str> = search
+            .run(&airports)
+            .iter()
+            .map(|a| a.icao.as_str())
+            .collect();
+        assert_eq!(icaos, ["KLAX", "KJFK"]);
+
+        let search = SearchQuery {
+            sort: SortOrder::Icao,
+            filter: AirportFilter {
+                countries: parse_list(Some("us")),
+                ..Default::default()
+            },
+            ..SearchQuery::new("a")
+        };
+        let (head, total) = search.run_head(&airports, 1);
+        assert_eq!(total, 2);
+        assert_eq!(head.len(), 1);
+        assert_eq!(head[0].icao, search.run(&airports)[0].icao);
+    }
+
+    /// Tests that multi-word queries match words in any order, each required
+    #[actix_web::test]
+    async fn test_search_query_tokens() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let icaos = |search: SearchQuery| -> Vec<String> {
+            search
+                .run(&airports)
+                .into_iter()
+                .map(|a| a.icao.clone())
+                .collect()
+        };
+
+        assert_eq!(icaos(SearchQuery::new("international kennedy")), ["KJFK"]);
+        assert_eq!(icaos(SearchQuery::new("kennedy  international")), ["KJFK"]);
+        // Words may match different fields
+        assert_eq!(icaos(SearchQuery::new("lax angeles")), ["KLAX"]);
+        assert!(icaos(SearchQuery::new("kennedy heathrow")).is_empty());
+        // City words match even when absent from the name
+        assert_eq!(icaos(SearchQuery::new("new york")), ["KJFK"]);
+
+        // Exact mode still compares the whole query
+        let exact = SearchQuery {
+            mode: MatchMode::Exact,
+            ..SearchQuery::new("london heathrow airport")
+        };
+        assert_eq!(icaos(exact), ["EGLL"]);
+        let exact = SearchQuery {
+            mode: MatchMode::Exact,
+            ..SearchQuery::new("heathrow london airport")
+        };
+        assert!(icaos(exact).is_empty());
+    }
+
+    /// Tests Soundex codes and phonetic matching of misspelled name words
+    #[actix_web::test]
+    async fn test_search_query_phonetic() {
+        let code = |word| soundex(word).map(|code| String::from_utf8(code.to_vec()).unwrap());
+        for (word, expected) in [
+            ("robert", "R163"),
+            ("rupert", "R163"),
+            ("ashcraft", "A261"),
+            ("tymczak", "T522"),
+            ("pfister", "P236"),
+            ("honeyman", "H555"),
+            ("lee", "L000"),
+        ] {
+            assert_eq!(code(word).as_deref(), Some(expected), "{word}");
+        }
+        assert_eq!(code("123"), None);
+
+        let airports = create_test_state().snapshot().airports.clone();
+        let icaos = |text: &str, tokens| -> Vec<String> {
+            SearchQuery {
+                mode: MatchMode::Phonetic,
+                tokens,
+                ..SearchQuery::new(text)
+            }
+            .run(&airports)
+            .into_iter()
+            .map(|a| a.icao.clone())
+            .collect()
+        };
+        assert_eq!(icaos("loos angelees", TokenMatch::All), ["KLAX"]);
+        assert_eq!(icaos("kenedy", TokenMatch::All), ["KJFK"]);
+        assert_eq!(icaos("heethrow kenedy", TokenMatch::Any), ["KJFK", "EGLL"]);
+        // Only names are matched, and every word must sound alike
+        assert!(icaos("kjfk", TokenMatch::All).is_empty());
+        assert!(icaos("loos kenedy", TokenMatch::All).is_empty());
+
+        assert_eq!(
+            match_ranges(
+                "Los Angeles International",
+                "loos angelees",
+                MatchMode::Phonetic
+            ),
+            [[0, 3], [4, 11]]
+        );
+        assert!(MatchMode::Phonetic.matches("london heathrow airport", "lundon"));
+        assert_eq!(
+            "phonetic".parse::<MatchMode>().unwrap(),
+            MatchMode::Phonetic
+        );
+    }
+
+    /// Tests parsing `-word` exclusions and dropping airports that contain them
+    #[actix_web::test]
+    async fn test_search_query_exclusions() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let icaos = |search: SearchQuery| -> Vec<String> {
+            search
+                .run(&airports)
+                .into_iter()
+                .map(|a| a.icao.clone())
+                .collect()
+        };
+
+        let search = SearchQuery::new(" Airport  -Heathrow ");
+        assert_eq!(search.text, "airport");
+        assert_eq!(search.excluded, ["heathrow"]);
+        assert_eq!(icaos(search), ["KLAX", "KJFK"]);
+        let search = SearchQuery::new("dallas - fort");
+        assert_eq!(search.text, "dallas - fort");
+        assert!(search.excluded.is_empty());
+
+        // Exclusions match any searchable field, here JFK's city and a keyword
+        assert_eq!(icaos(SearchQuery::new("international -york")), ["KLAX"]);
+        assert_eq!(icaos(SearchQuery::new("international -idle")), ["KLAX"]);
+        let any = SearchQuery {
+            tokens: TokenMatch::Any,
+            ..SearchQuery::new("heathrow kennedy -london")
+        };
+        assert_eq!(icaos(any), ["KJFK"]);
+        let exact = SearchQuery {
+            mode: MatchMode::Exact,
+            ..SearchQuery::new("klax -los")
+        };
+        assert!(icaos(exact).is_empty());
+    }
+
+    /// Tests case and diacritic folding, including letters without a decomposition
+    #[actix_web::test]
+    async fn test_fold_text() {
+        assert_eq!(fold_text("Zürich"), "zurich");
+        assert_eq!(fold_text("São Paulo"), "sao paulo");
+        assert_eq!(fold_text("Málaga–Costa del Sol"), "malaga–costa del sol");
+        assert_eq!(fold_text("Łódź"), "lodz");
+        assert_eq!(fold_text("Tromsø Straße"), "tromso strasse");
+        assert_eq!(fold_text("KJFK"), "kjfk");
+    }
+
+    /// Tests that unaccented queries and filters find accented names and cities
+    #[actix_web::test]
+    async fn test_search_query_diacritics() {
+        let airports: Vec<Airport> = [
+            ("LSZH", "Zürich Airport", "Zürich"),
+            (
+                "SBGR",
+                "São Paulo–Guarulhos International Airport",
+                "São Paulo",
+            ),
+        ]
+        .into_iter()
+        .map(|(ident, name, city)| {
+            Airport::from(crate::model::CsvAirport {
+                ident: ident.into(),
+                name: name.into(),
+                municipality: Some(city.into()),
+                ..Default::default()
+            })
+        })
+        .collect();
+        let icaos = |search: SearchQuery| -> Vec<String> {
+            search
+                .run(&airports)
+                .into_iter()
+                .map(|a| a.icao.clone())
+                .collect()
+        };
+
+        assert_eq!(icaos(SearchQuery::new("zurich")), ["LSZH"]);
+        assert_eq!(icaos(SearchQuery::new("ZÜRICH")), ["LSZH"]);
+        assert_eq!(icaos(SearchQuery::new("sao paulo")), ["SBGR"]);
+        let filtered = SearchQuery {
+            filter: AirportFilter {
+                cities: parse_list(Some("Sao Paulo")),
+                ..Default::default()
+            },
+            ..SearchQuery::new("airport")
+        };
+        assert_eq!(icaos(filtered), ["SBGR"]);
+    }
+
+    /// Tests that match ranges land on char boundaries of the original, unfolded text
+    #[actix_web::test]
+    async fn test_match_ranges() {
+        let ranges = |text: &str, query: &str, mode| match_ranges(text, &fold_text(query), mode);
+
+        assert_eq!(
+            ranges(
+                "John F. Kennedy International Airport",
+                "kennedy intl",
+                MatchMode::Contains
+            ),
+            [[8, 15]]
+        );
+        assert_eq!(
+            ranges("Aéroport de Zürich", "zurich aero", MatchMode::Contains),
+            [[0, 5], [13, 20]]
+        );
+        // A decomposed accent stays with its letter
+        assert_eq!(
+            ranges("Zu\u{308}rich", "zur", MatchMode::Contains),
+            [[0, 5]]
+        );
+        // "ß" folds to "ss", so either half selects the whole character
+        let text = "Flugplatz Straße";
+        assert_eq!(ranges(text, "s", MatchMode::Contains), [[10, 11], [14, 16]]);
+        assert_eq!(ranges(text, "strasse", MatchMode::Contains), [[10, 17]]);
+        assert!(text.is_char_boundary(14) && text.is_char_boundary(16));
+
+        assert_eq!(ranges("anna", "an na", MatchMode::Contains), [[0, 4]]);
+        assert_eq!(ranges("KJFK", "kjfk", MatchMode::Exact), [[0, 4]]);
+        assert!(ranges("KJFK", "kjf", MatchMode::Exact).is_empty());
+        assert_eq!(ranges("KJFK", "kj", MatchMode::Prefix), [[0, 2]]);
+        assert!(ranges("KJFK", "fk", MatchMode::Prefix).is_empty());
+        assert!(ranges("KJFK", "", MatchMode::Contains).is_empty());
+    }
+
+    /// Tests that keywords such as former names are matched, scored, and folded
+    #[actix_web::test]
+    async fn test_search_query_keywords() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let icaos = |search: SearchQuery| -> Vec<String> {
+            search
+                .run(&airports)
+                .into_iter()
+                .map(|a| a.icao.clone())
+                .collect()
+        };
+
+        assert_eq!(icaos(SearchQuery::new("idlewild")), ["KJFK"]);
+        assert_eq!(icaos(SearchQuery::new("LONDRES")), ["EGLL"]);
+        assert_eq!(icaos(SearchQuery::new("nyc kennedy")), ["KJFK"]);
+        assert_eq!(
+            relevance_score(&airports[0], "idle"),
+            SCORE_PREFIX,
+            "prefix of a keyword word"
+        );
+
+        // Exact mode compares the whole query against each keyword separately
+        let exact = |text| SearchQuery {
+            mode: MatchMode::Exact,
+            ..SearchQuery::new(text)
+        };
+        assert_eq!(icaos(exact("new york city")), ["KJFK"]);
+        assert!(icaos(exact("nyc, idlewild")).is_empty());
+    }
+
+    /// Tests score tiers and that exact code matches outrank name matches
+    #[actix_web::test]
+    async fn test_relevance_score() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.insert(
+            0,
+            Airport::from(crate::model::CsvAirport {
+                ident: "EGXX".into(),
+                name: "Flaxby Airfield".into(),
+                ..Default::default()
+            }),
+        );
+
+        let klax = &airports[2];
+        assert_eq!(relevance_score(klax, "lax"), SCORE_EXACT_CODE);
+        assert_eq!(relevance_score(klax, "los"), SCORE_PREFIX);
+        assert_eq!(relevance_score(klax, "geles"), SCORE_SUBSTRING);
+        assert_eq!(
+            relevance_score(klax, "lax los"),
+            SCORE_EXACT_CODE + SCORE_PREFIX
+        );
+        assert_eq!(relevance_score(klax, "angeles"), SCORE_PREFIX);
+        assert_eq!(relevance_score(&airports[1], "york"), SCORE_PREFIX);
+        assert_eq!(relevance_score(&airports[1], "ork"), SCORE_SUBSTRING);
+        assert_eq!(relevance_score(klax, "heathrow"), 0);
+
+        let icaos: Vec<&str> = SearchQuery::new("lax")
+            .run(&airports)
+            .iter()
+            .map(|a| a.icao.as_str())
+            .collect();
+        assert_eq!(icaos, ["KLAX", "EGXX"]);
+    }
+}
@@ -0,0 +1,458 @@
+//! k-d tree over airport coordinates answering nearest and radius queries, and
+//! the geohash order answering prefix lookups.
+
+use crate::geo::{haversine_km, EARTH_RADIUS_KM};
+use crate::model::Airport;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Slices at least this long are split on separate Rayon tasks while building
+const PARALLEL_BUILD_LEN: usize = 4096;
+
+/// Allowance for rounding between the chord bound and `haversine_km`, so a
+/// subtree is never skipped over a last-bit difference
+const SLACK_KM: f64 = 1e-6;
+
+/// Indexed airport position
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// Position on the unit sphere
+    point: [f64; 3],
+    /// Latitude and longitude in decimal degrees, as `haversine_km` takes them
+    coordinates: (f64, f64),
+    /// Index of the airport in the dataset
+    index: u32,
+}
+
+/// Airport met during a query, ordered by distance and then dataset position
+/// so ties rank in file order, as a stable sort by distance would leave them
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance_km: f64,
+    index: u32,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_km
+            .total_cmp(&other.distance_km)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+/// Converts `(latitude, longitude)` in decimal degrees to a unit vector
+fn unit_vector((lat, lon): (f64, f64)) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Lower bound on the great-circle distance to any point at least
+/// `offsets[axis]` away from the query along each axis of the unit vector space
+fn min_distance_km(offsets: [f64; 3]) -> f64 {
+    let chord = offsets
+        .iter()
+        .map(|offset| offset * offset)
+        .sum::<f64>()
+        .sqrt();
+    2.0 * EARTH_RADIUS_KM * (chord / 2.0).min(1.0).asin() - SLACK_KM
+}
+
+/// `offsets` with the one along `axis` replaced by `gap`, bounding the
+/// subtree on the far side of a splitting plane `gap` away
+fn beyond(mut offsets: [f64; 3], axis: usize, gap: f64) -> [f64; 3] {
+    offsets[axis] = gap.abs();
+    offsets
+}
+
+/// Arranges `nodes` into an implicit k-d tree: the median along `depth % 3`
+/// sits in the middle, with lower points before it and higher points after
+fn build(nodes: &mut [Node], depth: usize) {
+    if nodes.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = nodes.len() / 2;
+    nodes.select_nth_unstable_by(mid, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    let (lower, rest) = nodes.split_at_mut(mid);
+    let upper = &mut rest[1..];
+    if lower.len() >= PARALLEL_BUILD_LEN {
+        rayon::join(|| build(lower, depth + 1), || build(upper, depth + 1));
+    } else {
+        build(lower, depth + 1);
+        build(upper, depth + 1);
+    }
+}
+
+/// k-d tree built once per dataset load over the unit-sphere positions of the
+/// airports with coordinates.
+///
+/// # Queries
+/// Straight-line (chord) distance between unit vectors grows with great-circle
+/// distance, so a subtree whose region (bounded by the splitting planes on the
+/// way down) lies further from the query than the current cutoff cannot hold a
+/// closer airport and is skipped. Every
+/// visited airport is measured with `haversine_km`, so results and their order
+/// match a full scan exactly. Queries touch roughly O(log N) airports plus the
+/// ones returned, instead of all N.
+///
+/// # Memory
+/// 56 bytes per airport with coordinates, a few megabytes for the full
+/// OurAirports dataset.
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    /// Implicit tree: each slice's middle node splits the rest of it
+    nodes: Vec<Node>,
+}
+
+impl SpatialIndex {
+    /// Indexes every airport with both a latitude and a longitude
+    pub fn new(airports: &[Airport]) -> Self {
+        let mut nodes: Vec<Node> = airports
+            .iter()
+            .enumerate()
+            .filter_map(|(index, airport)| {
+                let coordinates = (airport.latitude?, airport.longitude?);
+                Some(Node {
+                    point: unit_vector(coordinates),
+                    coordinates,
+                    index: index as u32,
+                })
+            })
+            .collect();
+        build(&mut nodes, 0);
+        SpatialIndex { nodes }
+    }
+
+    /// Number of airports indexed, i.e. those with coordinates
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether no airport has coordinates
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Finds the `count` airports nearest to `origin` (`(latitude, longitude)`
+    /// in decimal degrees).
+    ///
+    /// # Returns
+    /// Dataset indexes of up to `count` airports by ascending distance, ties
+    /// in dataset order
+    pub fn nearest(&self, origin: (f64, f64), count: usize) -> Vec<usize> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let mut best = BinaryHeap::with_capacity(count.min(self.nodes.len()) + 1);
+        Self::nearest_in(
+            &self.nodes,
+            0,
+            origin,
+            unit_vector(origin),
+            [0.0; 3],
+            count,
+            &mut best,
+        );
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|candidate| candidate.index as usize)
+            .collect()
+    }
+
+    /// Finds every airport within `radius_km` of `origin` (`(latitude,
+    /// longitude)` in decimal degrees), edge inclusive.
+    ///
+    /// # Returns
+    /// Dataset indexes of the airports by ascending distance, ties in dataset
+    /// order
+    pub fn within(&self, origin: (f64, f64), radius_km: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        Self::within_in(
+            &self.nodes,
+            0,
+            origin,
+            unit_vector(origin),
+            [0.0; 3],
+            radius_km,
+            &mut found,
+        );
+        found.par_sort_unstable();
+        found
+            .into_iter()
+            .map(|candidate| candidate.index as usize)
+            .collect()
+    }
+
+    /// Collects into `best`, a max-heap, the `count` closest nodes of the
+    /// subtree `nodes` split on `depth % 3`, whose points lie at least
+    /// `offsets` from the query along each axis
+    fn nearest_in(
+        nodes: &[Node],
+        depth: usize,
+        origin: (f64, f64),
+        query: [f64; 3],
+        offsets: [f64; 3],
+        count: usize,
+        best: &mut BinaryHeap<Candidate>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let node = &nodes[mid];
+        let candidate = Candidate {
+            distance_km: haversine_km(origin, node.coordinates),
+            index: node.index,
+        };
+        if best.len() < count {
+            best.push(candidate);
+        } else if best.peek().is_some_and(|worst| candidate < *worst) {
+            best.pop();
+            best.push(candidate);
+        }
+
+        let axis = depth % 3;
+        let gap = query[axis] - node.point[axis];
+        let (near, far) = if gap <= 0.0 {
+            (&nodes[..mid], &nodes[mid + 1..])
+        } else {
+            (&nodes[mid + 1..], &nodes[..mid])
+        };
+        Self::nearest_in(near, depth + 1, origin, query, offsets, count, best);
+        let offsets = beyond(offsets, axis, gap);
+        let reachable = best.len() < count
+            || best
+                .peek()
+                .is_some_and(|worst| min_distance_km(offsets) <= worst.distance_km);
+        if reachable {
+            Self::nearest_in(far, depth + 1, origin, query, offsets, count, best);
+        }
+    }
+
+    /// Collects into `found` the nodes of the subtree `nodes` split on
+    /// `depth % 3`, whose points lie at least `offsets` from the query along
+    /// each axis, that lie within `radius_km`
+    fn within_in(
+        nodes: &[Node],
+        depth: usize,
+        origin: (f64, f64),
+        query: [f64; 3],
+        offsets: [f64; 3],
+        radius_km: f64,
+        found: &mut Vec<Candidate>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let node = &nodes[mid];
+        let distance_km = haversine_km(origin, node.coordinates);
+        if distance_km <= radius_km {
+            found.push(Candidate {
+                distance_km,
+                index: node.index,
+            });
+        }
+
+        let axis = depth % 3;
+        let gap = query[axis] - node.point[axis];
+        let (near, far) = if gap <= 0.0 {
+            (&nodes[..mid], &nodes[mid + 1..])
+        } else {
+            (&nodes[mid + 1..], &nodes[..mid])
+        };
+        Self::within_in(near, depth + 1, origin, query, offsets, radius_km, found);
+        let offsets = beyond(offsets, axis, gap);
+        if min_distance_km(offsets) <= radius_km {
+            Self::within_in(far, depth + 1, origin, query, offsets, radius_km, found);
+        }
+    }
+}
+
+/// Airports with coordinates sorted by `Airport::geohash`, built once per
+/// dataset load.
+///
+/// Airports sharing a geohash prefix lie in the same cell, and sort next to
+/// each other, so a prefix lookup is two binary searches returning one
+/// contiguous run.
+///
+/// # Memory
+/// One `u32` per airport with coordinates.
+#[derive(Debug, Default)]
+pub struct GeohashIndex {
+    /// Dataset indexes by ascending geohash, ties in dataset order
+    sorted: Vec<u32>,
+}
+
+impl GeohashIndex {
+    /// Indexes every airport with a geohash
+    pub fn new(airports: &[Airport]) -> Self {
+        let mut sorted: Vec<u32> = airports
+            .iter()
+            .enumerate()
+            .filter(|(_, airport)| airport.geohash.is_some())
+            .map(|(index, _)| index as u32)
+            .collect();
+        sorted.par_sort_by(|&a, &b| {
+            airports[a as usize]
+                .geohash
+                .cmp(&airports[b as usize].geohash)
+        });
+        GeohashIndex { sorted }
+    }
+
+    /// Dataset indexes of the airports in `airports` (the list the index was
+    /// built from) whose geohash starts with `prefix`, by ascending geohash
+    pub fn prefixed(&self, airports: &[Airport], prefix: &str) -> &[u32] {
+        let hash = |index: u32| airports[index as usize].geohash.as_deref().unwrap_or("");
+        let start = self.sorted.partition_point(|&index| hash(index) < prefix);
+        let len = self.sorted[start..].partition_point(|&index| hash(index).starts_with(prefix));
+        &self.sorted[start..start + len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::CsvAirport;
+
+    /// Airports scattered by a fixed linear congruential generator, with
+    /// repeated positions, both poles, the antimeridian, and some without
+    /// coordinates
+    fn scattered_airports() -> Vec<Airport> {
+        let mut seed: u64 = 42;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let mut coordinates: Vec<Option<(f64, f64)>> = (0..2000)
+            .map(|_| Some((next() * 180.0 - 90.0, next() * 360.0 - 180.0)))
+            .collect();
+        coordinates.extend([
+            Some((90.0, 0.0)),
+            Some((-90.0, 45.0)),
+            Some((10.0, 180.0)),
+            Some((10.0, -180.0)),
+            Some((51.5, -0.12)),
+            Some((51.5, -0.12)),
+            None,
+            None,
+        ]);
+        coordinates
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                Airport::from(CsvAirport {
+                    ident: format!("X{i:04}"),
+                    name: format!("Airport {i}"),
+                    latitude_deg: point.map(|(lat, _)| lat),
+                    longitude_deg: point.map(|(_, lon)| lon),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Full scan ranking the airports with coordinates by distance, stably
+    fn brute_force(airports: &[Airport], origin: (f64, f64)) -> Vec<(usize, f64)> {
+        let mut ranked: Vec<(usize, f64)> = airports
+            .iter()
+            .enumerate()
+            .filter_map(|(index, airport)| {
+                let point = (airport.latitude?, airport.longitude?);
+                Some((index, haversine_km(origin, point)))
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked
+    }
+
+    /// Tests that prefix lookups return exactly the airports whose geohash
+    /// starts with the prefix, in geohash order
+    #[actix_web::test]
+    async fn test_geohash_index_prefixed() {
+        let airports = scattered_airports();
+        let index = GeohashIndex::new(&airports);
+        assert_eq!(index.prefixed(&airports, "").len(), airports.len() - 2);
+
+        for prefix in ["g", "gc", "gcpu", "u4pru", "0", "z", "zzzzzz"] {
+            let mut expected: Vec<usize> = (0..airports.len())
+                .filter(|&i| {
+                    airports[i]
+                        .geohash
+                        .as_deref()
+                        .is_some_and(|hash| hash.starts_with(prefix))
+                })
+                .collect();
+            expected.sort_by_key(|&i| airports[i].geohash.clone());
+            let found: Vec<usize> = index
+                .prefixed(&airports, prefix)
+                .iter()
+                .map(|&i| i as usize)
+                .collect();
+            assert_eq!(found, expected, "{prefix}");
+        }
+        // The two airports at the same point in London share a geohash
+        assert_eq!(index.prefixed(&airports, "gcpuvr295zcd").len(), 2);
+    }
+
+    /// Tests that nearest and radius queries return exactly what a full scan
+    /// does, in the same order, including ties and queries at the poles and
+    /// across the antimeridian
+    #[actix_web::test]
+    async fn test_spatial_index_matches_brute_force() {
+        let airports = scattered_airports();
+        let index = SpatialIndex::new(&airports);
+        assert_eq!(index.len(), airports.len() - 2);
+        assert!(SpatialIndex::new(&[]).nearest((0.0, 0.0), 5).is_empty());
+
+        for origin in [
+            (51.5, -0.12),
+            (0.0, 0.0),
+            (89.9, 10.0),
+            (-90.0, 0.0),
+            (10.0, 179.9),
+            (-33.9, 151.2),
+        ] {
+            let ranked = brute_force(&airports, origin);
+            for count in [0, 1, 2, 7, 50, 5000] {
+                let expected: Vec<usize> =
+                    ranked.iter().take(count).map(|&(index, _)| index).collect();
+                assert_eq!(
+                    index.nearest(origin, count),
+                    expected,
+                    "{origin:?} x{count}"
+                );
+            }
+            for radius_km in [0.0, 150.0, 1500.0, 20_100.0] {
+                let expected: Vec<usize> = ranked
+                    .iter()
+                    .filter(|&&(_, distance)| distance <= radius_km)
+                    .map(|&(index, _)| index)
+                    .collect();
+                assert_eq!(
+                    index.within(origin, radius_km),
+                    expected,
+                    "{origin:?} {radius_km}"
+                );
+            }
+        }
+    }
+}
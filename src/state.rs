@@ -0,0 +1,394 @@
+//! Shared application state and the hot-reloadable airport dataset.
+
+use crate::autocomplete::PrefixIndex;
+use crate::cache::{LruCache, SearchCacheKey};
+use crate::config::{DEFAULT_MIN_QUERY_LENGTH, DEFAULT_SEARCH_CACHE_CAPACITY};
+use crate::error::ApiError;
+use crate::format::PrerenderedAirports;
+use crate::loader::{load_airports_with, source_modified, LoadOptions};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::model::Airport;
+use crate::pagination::{PageLimits, PagePosition};
+use crate::ratelimit::RateLimiter;
+use crate::search::count_by;
+#[cfg(feature = "spatial")]
+use crate::spatial::{GeohashIndex, SpatialIndex};
+use crate::stats::DatasetStats;
+#[cfg(feature = "fuzzy")]
+use crate::trigram::TrigramIndex;
+use actix_web::web::{self, Bytes};
+use arc_swap::ArcSwap;
+use log::{error, info, warn};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Forwards written bytes into a hasher, so a serialization can be hashed
+/// without buffering it
+struct HashWriter<'a, H: Hasher>(&'a mut H);
+
+impl<H: Hasher> std::io::Write for HashWriter<'_, H> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes the serialized form of every airport, yielding a version that changes
+/// whenever any served field changes
+fn dataset_version(airports: &[Airport]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_writer(HashWriter(&mut hasher), airports).expect("airports serialize to JSON");
+    hasher.finish()
+}
+
+/// Immutable snapshot of loaded airport data together with its derived indexes.
+///
+/// # Fields
+/// - `airports`: Preloaded list of airports with search-optimized fields
+/// - `by_icao`: Lowercase ICAO code → index into `airports`, for O(1) lookups
+/// - `prefixes`: Sorted name-word and ICAO prefix index for autocomplete
+/// - `version`: Hash of the served airport data, used as the HTTP `ETag`
+/// - `last_modified`: Modification time of the source CSV captured at load, used
+///   as the HTTP `Last-Modified`; `None` when unknown
+/// - `stats`: Summary counts served by `/stats`, computed when the dataset is built
+/// - `json`: Every airport pre-serialized, for unfiltered JSON pages
+/// - `type_counts`: Distinct facility types with their airport counts, by
+///   descending count then type, served by `/types`
+/// - `trigrams`: Trigram index shortlisting `/airports/fuzzy` candidates
+///   (`fuzzy` feature)
+/// - `spatial`: k-d tree over coordinates answering `/airports/nearest` and
+///   `/airports/radius` (`spatial` feature)
+/// - `geohashes`: Airports in geohash order, answering `/airports/geohash/{prefix}`
+///   (`spatial` feature)
+///
+/// # Memory
+/// The ICAO index stores an owned copy of each lowercase ICAO code plus a `usize`,
+/// roughly 40-50 bytes per airport including hash table overhead. For the full
+/// OurAirports dataset this is a few megabytes, traded for constant-time lookups.
+/// `json` adds roughly the size of the full JSON export; see `PrerenderedAirports`.
+/// `trigrams` adds a few tens of megabytes more; see `TrigramIndex`. `spatial`
+/// adds a few megabytes and `geohashes` a `u32` per airport; see `SpatialIndex`
+/// and `GeohashIndex`.
+pub struct Dataset {
+    pub airports: Vec<Airport>,
+    pub by_icao: HashMap<String, usize>,
+    pub prefixes: PrefixIndex,
+    pub version: u64,
+    pub last_modified: Option<SystemTime>,
+    pub stats: DatasetStats,
+    pub json: PrerenderedAirports,
+    pub type_counts: Vec<(String, usize)>,
+    #[cfg(feature = "fuzzy")]
+    pub trigrams: TrigramIndex,
+    #[cfg(feature = "spatial")]
+    pub spatial: SpatialIndex,
+    #[cfg(feature = "spatial")]
+    pub geohashes: GeohashIndex,
+}
+
+impl Dataset {
+    /// Builds a dataset from loaded airports, constructing the ICAO, prefix,
+    /// and (with their features) trigram, spatial, and geohash indexes, hashing
+    /// the data into `version`, counting `stats` and `type_counts`, and
+    /// pre-serializing `json`.
+    ///
+    /// Duplicate ICAO codes are logged as warnings; the index points at the first
+    /// occurrence.
+    pub fn new(airports: Vec<Airport>) -> Self {
+        let mut by_icao = HashMap::with_capacity(airports.len());
+        for (index, airport) in airports.iter().enumerate() {
+            if by_icao.contains_key(&airport.lower_icao) {
+                warn!(
+                    "Duplicate ICAO code {} at row {}, keeping first occurrence",
+                    airport.icao, index
+                );
+            } else {
+                by_icao.insert(airport.lower_icao.clone(), index);
+            }
+        }
+        let prefixes = PrefixIndex::new(&airports);
+        let version = dataset_version(&airports);
+        let stats = DatasetStats::new(&airports);
+        let json = PrerenderedAirports::new(&airports);
+        let type_counts = count_by(&airports, |airport| &airport.kind)
+            .into_iter()
+            .map(|(kind, count)| (kind.to_string(), count))
+            .collect();
+        Dataset {
+            by_icao,
+            prefixes,
+            version,
+            last_modified: None,
+            stats,
+            json,
+            type_counts,
+            #[cfg(feature = "fuzzy")]
+            trigrams: TrigramIndex::new(&airports),
+            #[cfg(feature = "spatial")]
+            spatial: SpatialIndex::new(&airports),
+            #[cfg(feature = "spatial")]
+            geohashes: GeohashIndex::new(&airports),
+            airports,
+        }
+    }
+
+    /// Looks up an airport by ICAO code (case-insensitive, surrounding whitespace ignored)
+    pub fn find_by_icao(&self, icao: &str) -> Option<&Airport> {
+        self.by_icao
+            .get(&icao.trim().to_lowercase())
+            .map(|&index| &self.airports[index])
+    }
+}
+
+/// Application state shared across all requests.
+///
+/// # Fields
+/// - `dataset`: Current airport snapshot, atomically replaceable on reload
+/// - `csv_path`: Source CSV path used when reloading
+/// - `admin_token`: Bearer token for admin endpoints; `None` disables them
+/// - `min_query_length`: Shortest search query accepted, in characters after trimming
+/// - `page_limits`: Page size used without a `limit`, and the largest any endpoint returns
+/// - `load_options`: Column mapping and row validation applied when reloading `csv_path`
+/// - `search_cache`: Rendered JSON search responses with their page positions,
+///   cleared on every reload
+/// - `rate_limiter`: Per-client request limits applied by `rate_limit`; `None` disables them
+/// - `metrics`: Prometheus collectors updated by `track_metrics` and reloads
+///   (`metrics` feature)
+/// - `started_at`: Unix time in seconds when the state was built, at server start
+/// - `search_pool`: Dedicated Rayon pool filtering and ranking run on; `None`
+///   uses the global pool
+///
+/// Handlers take a `snapshot()` once per request so that a concurrent reload
+/// never changes the data underneath them mid-request.
+pub struct AppState {
+    pub dataset: ArcSwap<Dataset>,
+    pub csv_path: String,
+    pub admin_token: Option<String>,
+    pub min_query_length: usize,
+    pub page_limits: PageLimits,
+    pub load_options: LoadOptions,
+    pub search_cache: LruCache<SearchCacheKey, (Bytes, PagePosition)>,
+    pub rate_limiter: Option<RateLimiter>,
+    #[cfg(feature = "metrics")]
+    pub metrics: Metrics,
+    pub started_at: u64,
+    pub search_pool: Option<ThreadPool>,
+}
+
+impl AppState {
+    /// Builds application state from loaded airports and the path they came from,
+    /// recording the path's current modification time as the dataset's `last_modified`
+    pub fn new(airports: Vec<Airport>, csv_path: impl Into<String>) -> Self {
+        #[cfg(feature = "metrics")]
+        let metrics = Metrics::new();
+        #[cfg(feature = "metrics")]
+        metrics.airports_loaded.set(airports.len() as i64);
+        let csv_path = csv_path.into();
+        let mut dataset = Dataset::new(airports);
+        dataset.last_modified = source_modified(&csv_path);
+
+        AppState {
+            dataset: ArcSwap::from_pointee(dataset),
+            csv_path,
+            admin_token: None,
+            min_query_length: DEFAULT_MIN_QUERY_LENGTH,
+            page_limits: PageLimits::default(),
+            load_options: LoadOptions::default(),
+            search_cache: LruCache::new(DEFAULT_SEARCH_CACHE_CAPACITY),
+            rate_limiter: None,
+            #[cfg(feature = "metrics")]
+            metrics,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            search_pool: None,
+        }
+    }
+
+    /// Runs `op` in `search_pool`, so parallel iterators inside it use the
+    /// search threads, or in the global Rayon pool when there is none
+    pub fn in_search_pool<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.search_pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Number of threads `in_search_pool` runs parallel work on
+    pub fn search_threads(&self) -> usize {
+        self.search_pool
+            .as_ref()
+            .map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads)
+    }
+
+    /// Returns the current dataset, kept alive for as long as the caller holds it
+    pub fn snapshot(&self) -> Arc<Dataset> {
+        self.dataset.load_full()
+    }
+
+    /// Reloads airports from `csv_path` with `load_options`, atomically swaps in
+    /// the new dataset with the file's current `last_modified`, and clears `search_cache`.
+    ///
+    /// # Returns
+    /// - Number of airports in the new dataset
+    /// - The load error if reading or parsing fails, in which case the current
+    ///   dataset is left untouched
+    pub fn reload(&self) -> Result<usize, ApiError> {
+        // Captured before reading, so a write racing the load is never stamped onto
+        // data read before it
+        let last_modified = source_modified(&self.csv_path);
+        let (airports, _) = load_airports_with(&self.csv_path, &self.load_options)?;
+        let count = airports.len();
+        let mut dataset = Dataset::new(airports);
+        dataset.last_modified = last_modified;
+        self.dataset.store(Arc::new(dataset));
+        // Cleared after the swap, so responses computed from the old data are
+        // either dropped here or rejected by the cache generation check
+        self.search_cache.clear();
+        #[cfg(feature = "metrics")]
+        self.metrics.airports_loaded.set(count as i64);
+        Ok(count)
+    }
+}
+
+/// Runs `op` against `dataset` in the search pool, from Actix's blocking
+/// thread pool.
+///
+/// A scan or sort run directly in a handler occupies its worker thread, and
+/// every other request on that worker's connections waits until it finishes.
+/// Awaiting this instead lets the worker keep serving them. `op` cannot
+/// borrow from the handler, so results referring to airports are returned as
+/// positions in `airports`, such as those from `SearchQuery::run_indices`.
+///
+/// # Errors
+/// `ApiError::InternalError` if the blocking task panics or is cancelled
+pub async fn offload_search<R, F>(
+    state: &web::Data<AppState>,
+    dataset: &Arc<Dataset>,
+    op: F,
+) -> Result<R, ApiError>
+where
+    R: Send + 'static,
+    F: FnOnce(&Dataset) -> R + Send + 'static,
+{
+    let state = state.clone();
+    let dataset = dataset.clone();
+    web::block(move || state.in_search_pool(|| op(&dataset)))
+        .await
+        .map_err(|_| ApiError::InternalError)
+}
+
+/// Builds the dedicated Rayon pool for `AppState::search_pool`, with threads
+/// named `search-N`.
+///
+/// `threads` of 0 sizes it like the global pool: `RAYON_NUM_THREADS` if set,
+/// else one per available CPU.
+pub fn build_search_pool(threads: usize) -> std::io::Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("search-{index}"))
+        .build()
+        .map_err(std::io::Error::other)
+}
+
+/// Installs a SIGHUP handler that reloads the airport CSV without a restart.
+///
+/// Each signal re-runs `load_airports` on a blocking thread and swaps in the new
+/// dataset. If the reload fails, the error is logged and the previous data keeps
+/// being served.
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(state: web::Data<AppState>) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    actix_web::rt::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading {}", state.csv_path);
+            let state = state.clone();
+            match web::block(move || state.reload()).await {
+                Ok(Ok(count)) => info!("Reloaded {} airports", count),
+                Ok(Err(e)) => error!("Reload failed, keeping previous data: {}", e),
+                Err(e) => error!("Reload task failed, keeping previous data: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_state, write_fixture};
+
+    /// Tests ICAO index construction, keeping the first of duplicate codes
+    #[actix_web::test]
+    async fn test_dataset_icao_index() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        let mut duplicate = airports[0].clone();
+        duplicate.name = "Duplicate Kennedy".into();
+        airports.push(duplicate);
+
+        let state = Dataset::new(airports);
+        assert_eq!(state.by_icao.len(), 3);
+        assert_eq!(
+            state.find_by_icao(" kjfk ").unwrap().name,
+            "John F. Kennedy International Airport"
+        );
+        assert!(state.find_by_icao("ZZZZ").is_none());
+    }
+
+    /// Tests that reload swaps in new data, and keeps old data when loading fails
+    #[actix_web::test]
+    async fn test_app_state_reload() {
+        let path = write_fixture(
+            "reload.csv",
+            "ident,name\nKJFK,John F. Kennedy International Airport\n",
+        );
+        let state = AppState::new(Vec::new(), path.to_str().unwrap());
+        let before = state.snapshot();
+
+        assert_eq!(state.reload().unwrap(), 1);
+        assert_eq!(state.snapshot().airports[0].icao, "KJFK");
+        // Snapshots taken before the reload stay consistent
+        assert!(before.airports.is_empty());
+
+        std::fs::remove_file(&path).ok();
+        assert!(state.reload().is_err());
+        assert_eq!(state.snapshot().airports.len(), 1);
+    }
+
+    /// Tests that parallel work runs on the dedicated search pool when one is set
+    #[actix_web::test]
+    async fn test_search_pool() {
+        let mut state = AppState::new(Vec::new(), "airports.csv");
+        assert_eq!(state.search_threads(), rayon::current_num_threads());
+
+        state.search_pool = Some(build_search_pool(2).unwrap());
+        assert_eq!(state.search_threads(), 2);
+        let names: Vec<String> = state.in_search_pool(|| {
+            use rayon::prelude::*;
+            (0..4)
+                .into_par_iter()
+                .map(|_| {
+                    std::thread::current()
+                        .name()
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .collect()
+        });
+        assert!(
+            names.iter().all(|name| name.starts_with("search-")),
+            "{names:?}"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Dataset-wide summary counts, computed once per load.
+
+use crate::model::Airport;
+use crate::search::count_by;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+/// Aggregate counts over a whole dataset, served by `/stats`.
+///
+/// Built by `Dataset::new`, so a reload replaces them together with the
+/// airports they describe and reading them costs nothing per request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DatasetStats {
+    /// Number of airports loaded
+    pub total: usize,
+    /// Airports per facility type, keyed by type
+    pub by_type: BTreeMap<String, usize>,
+    /// Airports per continent code, keyed by code
+    pub by_continent: BTreeMap<String, usize>,
+    /// Airports with both latitude and longitude
+    pub with_coordinates: usize,
+    /// Airports with scheduled commercial service
+    pub with_scheduled_service: usize,
+    /// When the dataset was loaded, in seconds since the Unix epoch
+    pub loaded_at: u64,
+}
+
+impl DatasetStats {
+    /// Counts `airports`, stamping the result with the current time
+    pub fn new(airports: &[Airport]) -> Self {
+        let owned = |counts: Vec<(&str, usize)>| {
+            counts
+                .into_iter()
+                .map(|(key, count)| (key.to_string(), count))
+                .collect()
+        };
+
+        DatasetStats {
+            total: airports.len(),
+            by_type: owned(count_by(airports, |airport| &airport.kind)),
+            by_continent: owned(count_by(airports, |airport| &airport.continent)),
+            with_coordinates: airports
+                .par_iter()
+                .filter(|airport| airport.latitude.is_some() && airport.longitude.is_some())
+                .count(),
+            with_scheduled_service: airports
+                .par_iter()
+                .filter(|airport| airport.scheduled_service)
+                .count(),
+            loaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::CsvAirport;
+    use crate::test_support::create_test_state;
+
+    /// Tests every count, including airports lacking coordinates or service
+    #[actix_web::test]
+    async fn test_dataset_stats() {
+        let mut airports = create_test_state().snapshot().airports.clone();
+        airports.push(Airport::from(CsvAirport {
+            ident: "XHEL".into(),
+            name: "Rooftop Heliport".into(),
+            kind: "heliport".into(),
+            continent: "EU".into(),
+            ..Default::default()
+        }));
+
+        let stats = DatasetStats::new(&airports);
+        assert_eq!(stats.total, 4);
+        assert_eq!(
+            stats.by_type,
+            BTreeMap::from([("heliport".into(), 1), ("large_airport".into(), 3)])
+        );
+        assert_eq!(
+            stats.by_continent,
+            BTreeMap::from([("EU".into(), 2), ("NA".into(), 2)])
+        );
+        assert_eq!(stats.with_coordinates, 3);
+        assert_eq!(stats.with_scheduled_service, 3);
+        assert!(stats.loaded_at > 0);
+    }
+}
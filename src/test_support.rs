@@ -0,0 +1,96 @@
+//! Fixtures shared by the unit tests of every module.
+
+use crate::model::{Airport, CsvAirport};
+use crate::state::AppState;
+use actix_web::web;
+use serde::Deserialize;
+use std::io::Write;
+use tempfile::TempPath;
+
+/// Test-specific response structure enabling deserialization
+/// of paginated responses with typed data payloads
+#[derive(Debug, Deserialize)]
+pub(crate) struct TestPaginatedResponse<T> {
+    pub total: usize,
+    pub has_more: bool,
+    pub remaining: usize,
+    pub data: T,
+}
+
+/// Creates test application state with predefined airport data
+pub(crate) fn create_test_state() -> web::Data<AppState> {
+    let airports = vec![
+        Airport::from(CsvAirport {
+            ident: "KJFK".into(),
+            name: "John F. Kennedy International Airport".into(),
+            latitude_deg: Some(40.6398),
+            longitude_deg: Some(-73.7789),
+            elevation_ft: Some(13),
+            iata_code: Some("JFK".into()),
+            kind: "large_airport".into(),
+            iso_country: "US".into(),
+            iso_region: "US-NY".into(),
+            continent: "NA".into(),
+            municipality: Some("New York".into()),
+            scheduled_service: Some("yes".into()),
+            wikipedia_link: Some(
+                "https://en.wikipedia.org/wiki/John_F._Kennedy_International_Airport".into(),
+            ),
+            home_link: Some("https://www.jfkairport.com/".into()),
+            keywords: Some("Manhattan, New York City, NYC, Idlewild".into()),
+        }),
+        Airport::from(CsvAirport {
+            ident: "KLAX".into(),
+            name: "Los Angeles International Airport".into(),
+            latitude_deg: Some(33.9425),
+            longitude_deg: Some(-118.408),
+            elevation_ft: Some(125),
+            iata_code: Some("LAX".into()),
+            kind: "large_airport".into(),
+            iso_country: "US".into(),
+            iso_region: "US-CA".into(),
+            continent: "NA".into(),
+            municipality: Some("Los Angeles".into()),
+            scheduled_service: Some("yes".into()),
+            wikipedia_link: Some(
+                "https://en.wikipedia.org/wiki/Los_Angeles_International_Airport".into(),
+            ),
+            home_link: Some("https://www.flylax.com/".into()),
+            keywords: None,
+        }),
+        Airport::from(CsvAirport {
+            ident: "EGLL".into(),
+            name: "London Heathrow Airport".into(),
+            latitude_deg: Some(51.4706),
+            longitude_deg: Some(-0.461941),
+            elevation_ft: Some(83),
+            iata_code: Some("LHR".into()),
+            kind: "large_airport".into(),
+            iso_country: "GB".into(),
+            iso_region: "GB-ENG".into(),
+            continent: "EU".into(),
+            municipality: Some("London".into()),
+            scheduled_service: Some("yes".into()),
+            wikipedia_link: Some("https://en.wikipedia.org/wiki/Heathrow_Airport".into()),
+            home_link: Some("https://www.heathrow.com/".into()),
+            keywords: Some("LON, Londres".into()),
+        }),
+    ];
+    web::Data::new(AppState::new(airports, "airports.csv"))
+}
+
+/// Writes a CSV fixture to a uniquely named file in the system temp directory,
+/// ending in `name` so extensions are kept.
+///
+/// The returned path derefs to `Path` and deletes the file when dropped, so
+/// fixtures are cleaned up even when an assertion fails.
+pub(crate) fn write_fixture(name: &str, contents: &str) -> TempPath {
+    let mut file = tempfile::Builder::new()
+        .prefix("icao-api-")
+        .suffix(&format!("-{name}"))
+        .tempfile()
+        .expect("Failed to create CSV fixture");
+    file.write_all(contents.as_bytes())
+        .expect("Failed to write CSV fixture");
+    file.into_temp_path()
+}
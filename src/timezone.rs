@@ -0,0 +1,59 @@
+//! IANA timezone resolution from airport coordinates.
+//!
+//! Built with the `timezone` feature (on by default), which pulls in the
+//! `tzf-rs` crate and its bundled timezone boundary polygons (derived from
+//! OpenStreetMap by timezone-boundary-builder, about 4 MB).
+
+use std::sync::LazyLock;
+use tzf_rs::DefaultFinder;
+
+/// Boundary polygon index, expanded from the bundled data on first use
+static FINDER: LazyLock<DefaultFinder> = LazyLock::new(DefaultFinder::new);
+
+/// Resolves the IANA timezone of a point.
+///
+/// # Parameters
+/// - `point`: `(latitude, longitude)` in decimal degrees
+///
+/// # Behavior
+/// - Looks the point up in the timezone boundary polygons, so airports near
+///   a zone border get the zone they actually lie in
+/// - Points at sea resolve to the `Etc/GMT±N` nautical zone covering them
+///
+/// # Returns
+/// The zone name, or `None` if no polygon covers the point
+pub fn resolve(point: (f64, f64)) -> Option<&'static str> {
+    let (latitude, longitude) = point;
+    let name = FINDER.get_tz_name(longitude, latitude);
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests resolution of principal cities, airports far from their zone's
+    /// principal location, and airports near zone borders
+    #[actix_web::test]
+    async fn test_resolve() {
+        for (code, point, zone) in [
+            ("JFK", (40.6398, -73.7789), "America/New_York"),
+            ("LAX", (33.9425, -118.408), "America/Los_Angeles"),
+            ("LHR", (51.4706, -0.461941), "Europe/London"),
+            ("SYD", (-33.9461, 151.177), "Australia/Sydney"),
+            ("KEF", (63.985, -22.6056), "Atlantic/Reykjavik"),
+            ("DFW", (32.8968, -97.038), "America/Chicago"),
+            ("SEA", (47.449, -122.309), "America/Los_Angeles"),
+            ("ATL", (33.6367, -84.4281), "America/New_York"),
+            ("MIA", (25.7932, -80.2906), "America/New_York"),
+            ("IAH", (29.9844, -95.3414), "America/Chicago"),
+            // El Paso lies in the Mountain zone, across Texas's zone border
+            ("ELP", (31.8072, -106.378), "America/Denver"),
+            // Pristina, Kosovo, which has no zone of its own
+            ("PRN", (42.5728, 21.0358), "Europe/Belgrade"),
+        ] {
+            assert_eq!(resolve(point), Some(zone), "{code}");
+        }
+        assert_eq!(resolve((0.0, -30.0)), Some("Etc/GMT+2"));
+    }
+}
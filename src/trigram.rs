@@ -0,0 +1,171 @@
+//! Character trigram index shortlisting candidates for fuzzy search.
+
+use crate::model::Airport;
+use crate::search::fold_text;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Pads both ends of an indexed string, so its first and last characters
+/// each start or end trigrams of their own
+const PAD: char = '\0';
+
+/// Three consecutive characters of a padded string
+type Trigram = [char; 3];
+
+/// Distinct trigrams of `text` padded with two `PAD`s on each side, so a
+/// string of `n` characters yields up to `n + 2`
+fn trigrams(text: &str, grams: &mut HashSet<Trigram>) {
+    let chars: Vec<char> = [PAD, PAD]
+        .into_iter()
+        .chain(text.chars())
+        .chain([PAD, PAD])
+        .collect();
+    grams.extend(
+        chars
+            .windows(3)
+            .map(|window| [window[0], window[1], window[2]]),
+    );
+}
+
+/// Trigram index built once per dataset load over the strings fuzzy search
+/// compares: each airport's lowercase ICAO code, folded full name, and name
+/// words.
+///
+/// # Shortlisting
+/// Each edit of a string destroys at most three of its trigrams, so a target
+/// within `k` edits of the query still contains all but at most `3k` of the
+/// query's distinct trigrams. Airports sharing fewer with the query cannot
+/// match and are skipped without computing an edit distance. The filter never
+/// drops a match; it only stops helping when the query has `3k` or fewer
+/// distinct trigrams, and then every airport is a candidate.
+///
+/// # Memory
+/// One `u32` per distinct trigram per airport, typically 40-60 per airport, plus
+/// the map of distinct trigrams; a few tens of megabytes for the full
+/// OurAirports dataset.
+#[derive(Debug, Default)]
+pub struct TrigramIndex {
+    /// Trigram → ascending indexes of the airports containing it
+    postings: HashMap<Trigram, Vec<u32>>,
+    /// Number of airports indexed
+    len: usize,
+}
+
+impl TrigramIndex {
+    /// Indexes the trigrams of each airport's lowercase ICAO code, folded name,
+    /// and whitespace-separated name words
+    pub fn new(airports: &[Airport]) -> Self {
+        let postings = airports
+            .par_iter()
+            .enumerate()
+            .fold(
+                HashMap::new,
+                |mut postings: HashMap<Trigram, Vec<u32>>, (index, airport)| {
+                    let mut grams = HashSet::new();
+                    trigrams(&airport.lower_icao, &mut grams);
+                    trigrams(&airport.lower_name, &mut grams);
+                    for word in airport.lower_name.split_whitespace() {
+                        trigrams(word, &mut grams);
+                    }
+                    for gram in grams {
+                        postings.entry(gram).or_default().push(index as u32);
+                    }
+                    postings
+                },
+            )
+            .reduce(HashMap::new, |mut left, right| {
+                // Rayon reduces adjacent ranges left to right, so appending
+                // keeps every posting list ascending
+                for (gram, ids) in right {
+                    left.entry(gram).or_default().extend(ids);
+                }
+                left
+            });
+        TrigramIndex {
+            postings,
+            len: airports.len(),
+        }
+    }
+
+    /// Shortlists airports that may lie within `max_distance` edits of `query`
+    /// (trimmed and folded with `fold_text`).
+    ///
+    /// # Returns
+    /// - Ascending indexes of the airports sharing enough trigrams with the
+    ///   query to possibly match
+    /// - `None` when the query is too short for trigrams to rule anything out,
+    ///   in which case every airport must be checked
+    pub fn candidates(&self, query: &str, max_distance: usize) -> Option<Vec<usize>> {
+        let mut grams = HashSet::new();
+        trigrams(&fold_text(query.trim()), &mut grams);
+        let required = grams
+            .len()
+            .checked_sub(max_distance.saturating_mul(3))
+            .filter(|&required| required > 0)?;
+
+        let mut shared = vec![0u16; self.len];
+        for ids in grams.iter().filter_map(|gram| self.postings.get(gram)) {
+            for &id in ids {
+                shared[id as usize] += 1;
+            }
+        }
+        Some(
+            shared
+                .iter()
+                .enumerate()
+                .filter(|(_, &count)| usize::from(count) >= required)
+                .map(|(index, _)| index)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{fuzzy_search, fuzzy_search_indexed};
+    use crate::test_support::create_test_state;
+
+    /// Tests shortlisting, the short-query fallback, and that indexed fuzzy
+    /// search returns exactly what the full scan does
+    #[actix_web::test]
+    async fn test_trigram_candidates() {
+        let airports = create_test_state().snapshot().airports.clone();
+        let index = TrigramIndex::new(&airports);
+        let icaos = |query: &str, max_distance: usize| -> Option<Vec<&str>> {
+            index.candidates(query, max_distance).map(|candidates| {
+                candidates
+                    .into_iter()
+                    .map(|i| airports[i].icao.as_str())
+                    .collect()
+            })
+        };
+
+        assert_eq!(icaos("heathrw", 1), Some(vec!["EGLL"]));
+        assert_eq!(icaos("Kenedy", 1), Some(vec!["KJFK"]));
+        assert_eq!(icaos("qwxyzqwxyz", 2), Some(vec![]));
+        // Too few distinct trigrams to rule anything out
+        assert_eq!(icaos("lax", 2), None);
+        assert_eq!(icaos("zzzzzzzz", 2), None);
+
+        for (query, max_distance) in [
+            ("heathrw", 1),
+            ("heathrw", 2),
+            ("internatonal", 2),
+            ("kjfc", 1),
+            ("los angelos international airport", 3),
+            ("lax", 2),
+        ] {
+            let scanned: Vec<(&str, usize)> = fuzzy_search(&airports, query, max_distance)
+                .iter()
+                .map(|m| (m.airport.icao.as_str(), m.distance))
+                .collect();
+            let indexed: Vec<(&str, usize)> =
+                fuzzy_search_indexed(&airports, &index, query, max_distance)
+                    .iter()
+                    .map(|m| (m.airport.icao.as_str(), m.distance))
+                    .collect();
+            assert_eq!(indexed, scanned, "{query} within {max_distance}");
+        }
+    }
+}